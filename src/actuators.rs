@@ -8,6 +8,10 @@ pub enum Action
 {
     NoAction,
     MoveAction(MoveAction),
+    // Change to the temperature of the Polymini, positive warms it up
+    ThermoAction(f32),
+    // Pheromone left where the Polymini stands
+    ScentAction(f32),
 }
 
 impl ToJson for Action
@@ -26,6 +30,18 @@ impl ToJson for Action
                 json_obj.insert("direction".to_string(), d.to_json());
                 json_obj.insert("impulse".to_string(), i.to_json());
                 Json::Object(json_obj)
+            },
+            Action::ThermoAction(delta) =>
+            {
+                let mut json_obj = pmJsonObject::new();
+                json_obj.insert("temperature".to_string(), delta.to_json());
+                Json::Object(json_obj)
+            },
+            Action::ScentAction(amount) =>
+            {
+                let mut json_obj = pmJsonObject::new();
+                json_obj.insert("scent".to_string(), amount.to_json());
+                Json::Object(json_obj)
             }
         }
     }
@@ -44,7 +60,20 @@ pub enum ActuatorTag
 {
     MoveHorizontal,
     MoveVertical,
+    // Positive stimulus turns counter-clockwise, negative clockwise
+    Rotate,
+    // Positive stimulus warms up / cools down the Polymini, negative does nothing
+    Heat,
+    Cool,
+    // Positive stimulus leaves scent for the rest of the species to smell
+    EmitScent,
 }
+
+// Most a Thermal actuator can change the temperature in one substep
+pub const THERMO_ACTUATOR_DELTA: f32 = 0.05;
+// Most scent a Polymini can leave on a grid square in one substep
+pub const SCENT_EMIT_AMOUNT: f32 = 0.2;
+
 impl ActuatorTag
 {
     pub fn to_action(&self, stimulus_p: f32, coord: Coord) -> Action
@@ -70,6 +99,22 @@ impl ActuatorTag
                 let torque = coord.0 as f32 * stimulus;
                 Action::MoveAction(MoveAction::Move(Direction::VERTICAL, stimulus, torque))
             },
+            ActuatorTag::Rotate =>
+            {
+                Action::MoveAction(MoveAction::Move(Direction::ROTATION, stimulus, 0.0))
+            },
+            ActuatorTag::Heat =>
+            {
+                Action::ThermoAction(stimulus.max(0.0) * THERMO_ACTUATOR_DELTA)
+            },
+            ActuatorTag::Cool =>
+            {
+                Action::ThermoAction(-1.0 * stimulus.max(0.0) * THERMO_ACTUATOR_DELTA)
+            },
+            ActuatorTag::EmitScent =>
+            {
+                Action::ScentAction(stimulus.max(0.0) * SCENT_EMIT_AMOUNT)
+            },
         }
     }
 }
@@ -91,8 +136,12 @@ impl Deserializable for ActuatorTag
             {
                 match json_string.to_lowercase().as_ref()
                 {
-                    "hormov" => { to_ret = ActuatorTag::MoveHorizontal; }, 
-                    "vermov" => { to_ret = ActuatorTag::MoveVertical; }, 
+                    "hormov" | "movehorizontal" => { to_ret = ActuatorTag::MoveHorizontal; }, 
+                    "vermov" | "movevertical"   => { to_ret = ActuatorTag::MoveVertical; }, 
+                    "rotate"                    => { to_ret = ActuatorTag::Rotate; },
+                    "heat"                      => { to_ret = ActuatorTag::Heat; },
+                    "cool"                      => { to_ret = ActuatorTag::Cool; },
+                    "emitscent"                 => { to_ret = ActuatorTag::EmitScent; },
                     _ =>
                     {
                         return None;
@@ -129,6 +178,14 @@ impl Actuator
     {
         Actuator { tag: tag, index: index, coords: coords}
     }
+    pub fn get_tag(&self) -> ActuatorTag
+    {
+        self.tag
+    }
+    pub fn get_coords(&self) -> Coord
+    {
+        self.coords
+    }
     pub fn get_action(&self, stimulus: f32) -> Action
     {
         self.tag.to_action(stimulus, self.coords)
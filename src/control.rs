@@ -10,43 +10,881 @@ pub use ::actuators::*;
 pub use ::sensors::*;
 
 use ::genetics::*;
+use ::neat::*;
+use ::random::gen_gaussian;
 use ::serialization::*;
 use ::types::*;
 
+use std::any::Any;
 use std::cmp::{max, min};
+use std::fmt;
+use std::mem;
 
 pub struct Perspective
 {
     pub id: usize,
+    // Normalized to [0..1]
     pub pos: (f32, f32),
+    // In world coordinates
+    pub world_pos: (f32, f32),
     pub orientation: Direction,
     pub last_move_succeeded: bool, 
+    // uuid of the Species the Polymini belongs to
+    pub species: usize,
+    // Rays cast by its eyes, 0 if it has none
+    pub vision_rays: usize,
+    // Own condition, HP and Energy as a fraction of their max, Temperature and Ph
+    // within the tolerance range (0 and 1 are the limits)
+    pub hp: f32,
+    pub energy: f32,
+    pub temperature: f32,
+    pub ph: f32,
 }
 impl Perspective
 {
-    pub fn new(id: usize, pos: (f32, f32),
+    pub fn new(id: usize, pos: (f32, f32), world_pos: (f32, f32),
                orientation: Direction, move_succeded: bool) -> Perspective
     {
-        Perspective { id: id, pos: pos, orientation: orientation,
-                      last_move_succeeded: move_succeded }
+        Perspective::new_for_species(id, 0, pos, world_pos, orientation, move_succeded)
+    }
+    pub fn new_for_species(id: usize, species: usize, pos: (f32, f32), world_pos: (f32, f32),
+                           orientation: Direction, move_succeded: bool) -> Perspective
+    {
+        Perspective { id: id, pos: pos, world_pos: world_pos, orientation: orientation,
+                      last_move_succeeded: move_succeded, species: species, vision_rays: 0,
+                      hp: 1.0, energy: 1.0, temperature: 0.5, ph: 0.5 }
+    }
+}
+
+
+pub type NNLayer = FeedforwardLayer<f32, fn(f32) -> f32, fn(f32) -> f32>;
+
+// Activation function applied to the outputs of a layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Activation
+{
+    Sigmoid,
+    Tanh,
+    ReLU,
+    Identity,
+}
+impl Activation
+{
+    pub fn apply(&self, v: f32) -> f32
+    {
+        match *self
+        {
+            Activation::Sigmoid  => { 1.0 / (1.0 + (-v).exp()) },
+            Activation::Tanh     => { v.tanh() },
+            Activation::ReLU     => { v.max(0.0) },
+            Activation::Identity => { v },
+        }
+    }
+
+    // Actuators expect stimuli centered around 0
+    pub fn center(&self, v: f32) -> f32
+    {
+        match *self
+        {
+            Activation::Sigmoid => { v - 0.5 },
+            _ => { v },
+        }
+    }
+}
+impl Serializable for Activation
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl Deserializable for Activation
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<Activation>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "sigmoid"  => { Some(Activation::Sigmoid) },
+                    "tanh"     => { Some(Activation::Tanh) },
+                    "relu"     => { Some(Activation::ReLU) },
+                    "identity" => { Some(Activation::Identity) },
+                    _ =>
+                    {
+                        error!("Unknown Activation {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for Activation
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Kind of network the brains of a species are
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlKind
+{
+    // Fixed layers of neurons, see ControlConfig for the shape
+    Layered,
+    // Nodes and connections evolve with the individual
+    Neat,
+}
+impl Serializable for ControlKind
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl Deserializable for ControlKind
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<ControlKind>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "layered" => { Some(ControlKind::Layered) },
+                    "neat"    => { Some(ControlKind::Neat) },
+                    _ =>
+                    {
+                        error!("Unknown ControlKind {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for ControlKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// What happens to the weights learned during a lifetime
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inheritance
+{
+    // Learned weights die with the individual
+    Darwinian,
+    // Learned weights are passed on to the offspring
+    Lamarckian,
+}
+impl Serializable for Inheritance
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl Deserializable for Inheritance
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<Inheritance>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "darwinian"  => { Some(Inheritance::Darwinian) },
+                    "lamarckian" => { Some(Inheritance::Lamarckian) },
+                    _ =>
+                    {
+                        error!("Unknown Inheritance {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for Inheritance
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// How the weights of brand new layers are picked
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeightInitialisation
+{
+    // [-0.5, 0.5), what networks always started with
+    Uniform,
+    // [-range, range)
+    SymmetricUniform { range: f32 },
+    // Normal distribution centered at 0
+    Gaussian { sigma: f32 },
+    // Glorot - Uniform scaled by the number of inputs and outputs of the layer
+    Xavier,
+    // Normal distribution scaled by the number of inputs of the layer
+    He,
+}
+impl WeightInitialisation
+{
+    pub fn get_id(&self) -> &'static str
+    {
+        match *self
+        {
+            WeightInitialisation::Uniform => { "uniform" },
+            WeightInitialisation::SymmetricUniform { .. } => { "symmetricuniform" },
+            WeightInitialisation::Gaussian { .. } => { "gaussian" },
+            WeightInitialisation::Xavier => { "xavier" },
+            WeightInitialisation::He => { "he" },
+        }
+    }
+}
+impl Serializable for WeightInitialisation
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Mode".to_owned(), self.get_id().to_json());
+        match *self
+        {
+            WeightInitialisation::SymmetricUniform { range } =>
+            {
+                json_obj.insert("Range".to_owned(), range.to_json());
+            },
+            WeightInitialisation::Gaussian { sigma } =>
+            {
+                json_obj.insert("Sigma".to_owned(), sigma.to_json());
+            },
+            _ => {}
+        }
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for WeightInitialisation
+{
+    // Either just the mode ("xavier") or an object with the mode and its parameters
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<WeightInitialisation>
+    {
+        let (mode, params) = match *json
+        {
+            Json::String(ref json_string) => { (json_string.to_lowercase(), None) },
+            Json::Object(ref json_obj) =>
+            {
+                match json_obj.get("Mode").and_then(|m| m.as_string())
+                {
+                    Some(m) => { (m.to_lowercase(), Some(json_obj)) },
+                    None =>
+                    {
+                        error!("WeightInitialisation without a Mode");
+                        return None
+                    }
+                }
+            },
+            _ =>
+            {
+                return None
+            }
+        };
+
+        let param = |key: &str, default: f32|
+        {
+            params.and_then(|p| p.get(key)).and_then(|v| v.as_f64()).map_or(default, |v| (v as f32).abs())
+        };
+
+        match mode.as_str()
+        {
+            "uniform"          => { Some(WeightInitialisation::Uniform) },
+            "symmetricuniform" => { Some(WeightInitialisation::SymmetricUniform { range: param("Range", 1.0) }) },
+            "gaussian"         => { Some(WeightInitialisation::Gaussian { sigma: param("Sigma", 0.5) }) },
+            "xavier"           => { Some(WeightInitialisation::Xavier) },
+            "he"               => { Some(WeightInitialisation::He) },
+            _ =>
+            {
+                error!("Unknown WeightInitialisation {}", mode);
+                None
+            }
+        }
+    }
+}
+
+// What a weight mutation does to the weights it touches
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeightMutationMode
+{
+    // Brand new value in [0, 1), the original behaviour
+    Replace,
+    // Gaussian noise added to the current value
+    Perturb,
+}
+impl Serializable for WeightMutationMode
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl Deserializable for WeightMutationMode
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<WeightMutationMode>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "replace" => { Some(WeightMutationMode::Replace) },
+                    "perturb" => { Some(WeightMutationMode::Perturb) },
+                    _ =>
+                    {
+                        error!("Unknown WeightMutationMode {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for WeightMutationMode
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// How weights and biases change when a brain mutates without changing its structure
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeightMutation
+{
+    pub mode: WeightMutationMode,
+    // Perturb only - Standard deviation of the noise
+    pub sigma: f32,
+    // Chance of each weight and bias being mutated, 0 mutates a single one
+    pub fraction: f32,
+    // Mutated values are kept within [-clamp, clamp]
+    pub clamp: Option<f32>,
+}
+impl WeightMutation
+{
+    pub fn defaults() -> WeightMutation
+    {
+        WeightMutation { mode: WeightMutationMode::Replace, sigma: 0.1, fraction: 0.0, clamp: None }
+    }
+
+    pub fn mutate_value(&self, value: f32, random_ctx: &mut PolyminiRandomCtx) -> f32
+    {
+        let v = match self.mode
+        {
+            WeightMutationMode::Replace => { random_ctx.gen::<f32>() },
+            WeightMutationMode::Perturb => { value + gen_gaussian(random_ctx, 0.0, self.sigma) },
+        };
+        match self.clamp
+        {
+            Some(c) => { v.max(-c).min(c) },
+            None => { v }
+        }
+    }
+
+    // Every value has a 'fraction' chance of being mutated
+    pub fn mutate_values(&self, values: &mut Vec<f32>, random_ctx: &mut PolyminiRandomCtx)
+    {
+        for v in values.iter_mut()
+        {
+            if random_ctx.gen_range(0.0, 1.0) < self.fraction
+            {
+                *v = self.mutate_value(*v, random_ctx);
+            }
+        }
+    }
+}
+impl Serializable for WeightMutation
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Mode".to_owned(), self.mode.serialize(ctx));
+        json_obj.insert("Sigma".to_owned(), self.sigma.to_json());
+        json_obj.insert("Fraction".to_owned(), self.fraction.to_json());
+        if let Some(c) = self.clamp
+        {
+            json_obj.insert("Clamp".to_owned(), c.to_json());
+        }
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for WeightMutation
+{
+    fn new_from_json(json: &Json, ctx: &mut SerializationCtx) -> Option<WeightMutation>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let mut wm = WeightMutation::defaults();
+                if let Some(m) = json_obj.get("Mode").and_then(|m| WeightMutationMode::new_from_json(m, ctx))
+                {
+                    wm.mode = m;
+                }
+                if let Some(v) = json_obj.get("Sigma").and_then(|v| v.as_f64())
+                {
+                    wm.sigma = (v as f32).abs();
+                }
+                if let Some(v) = json_obj.get("Fraction").and_then(|v| v.as_f64())
+                {
+                    wm.fraction = (v as f32).max(0.0).min(1.0);
+                }
+                wm.clamp = json_obj.get("Clamp").and_then(|v| v.as_f64()).map(|v| (v as f32).abs());
+                Some(wm)
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+
+// Per-species configuration of the brains
+#[derive(Clone, Debug)]
+pub struct ControlConfig
+{
+    pub kind: ControlKind,
+
+    // Hidden layers a new individual starts with
+    pub hidden_layers: usize,
+    pub max_hidden_layers: usize,
+
+    // Range (inclusive) of the size of new hidden layers
+    pub min_hidden_size: usize,
+    pub max_hidden_size: usize,
+
+    pub hidden_activation: Activation,
+    pub output_activation: Activation,
+
+    // Probability of a mutation adding or removing a hidden layer
+    pub topology_mutation: f32,
+
+    // Feed the values of each hidden layer back into itself on the next step
+    pub recurrent: bool,
+
+    // Neat only - Probability of a mutation splitting a connection or adding a new one
+    pub add_node_mutation: f32,
+    pub add_connection_mutation: f32,
+
+    // Hebbian learning during the lifetime of the individual, new learning rates are
+    // picked from [0..learning_rate]
+    pub plasticity: bool,
+    pub learning_rate: f32,
+    pub inheritance: Inheritance,
+
+    // Layered only - Weights of the layers created for new individuals and new topology
+    pub weight_initialisation: WeightInitialisation,
+    pub weight_mutation: WeightMutation,
+}
+impl ControlConfig
+{
+    pub fn defaults() -> ControlConfig
+    {
+        ControlConfig
+        {
+            kind: ControlKind::Layered,
+            hidden_layers: 1,
+            max_hidden_layers: 1,
+            min_hidden_size: 3,
+            max_hidden_size: 6,
+            hidden_activation: Activation::Sigmoid,
+            output_activation: Activation::Sigmoid,
+            topology_mutation: 0.0,
+            recurrent: false,
+            add_node_mutation: 0.03,
+            add_connection_mutation: 0.05,
+            plasticity: false,
+            learning_rate: 0.01,
+            inheritance: Inheritance::Darwinian,
+            weight_initialisation: WeightInitialisation::Uniform,
+            weight_mutation: WeightMutation::defaults(),
+        }
+    }
+}
+impl Serializable for ControlConfig
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Kind".to_owned(), self.kind.serialize(ctx));
+        json_obj.insert("HiddenLayers".to_owned(), self.hidden_layers.to_json());
+        json_obj.insert("MaxHiddenLayers".to_owned(), self.max_hidden_layers.to_json());
+        json_obj.insert("MinHiddenSize".to_owned(), self.min_hidden_size.to_json());
+        json_obj.insert("MaxHiddenSize".to_owned(), self.max_hidden_size.to_json());
+        json_obj.insert("HiddenActivation".to_owned(), self.hidden_activation.serialize(ctx));
+        json_obj.insert("OutputActivation".to_owned(), self.output_activation.serialize(ctx));
+        json_obj.insert("TopologyMutation".to_owned(), self.topology_mutation.to_json());
+        json_obj.insert("Recurrent".to_owned(), self.recurrent.to_json());
+        json_obj.insert("AddNodeMutation".to_owned(), self.add_node_mutation.to_json());
+        json_obj.insert("AddConnectionMutation".to_owned(), self.add_connection_mutation.to_json());
+        json_obj.insert("Plasticity".to_owned(), self.plasticity.to_json());
+        json_obj.insert("LearningRate".to_owned(), self.learning_rate.to_json());
+        json_obj.insert("Inheritance".to_owned(), self.inheritance.serialize(ctx));
+        json_obj.insert("WeightInitialisation".to_owned(), self.weight_initialisation.serialize(ctx));
+        json_obj.insert("WeightMutation".to_owned(), self.weight_mutation.serialize(ctx));
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for ControlConfig
+{
+    fn new_from_json(json: &Json, ctx: &mut SerializationCtx) -> Option<ControlConfig>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                // Anything missing is left as the default
+                let mut config = ControlConfig::defaults();
+
+                if let Some(k) = json_obj.get("Kind").and_then(|k| ControlKind::new_from_json(k, ctx))
+                {
+                    config.kind = k;
+                }
+
+                if let Some(v) = json_obj.get("HiddenLayers").and_then(|v| v.as_u64())
+                {
+                    config.hidden_layers = max(v as usize, 1);
+                }
+                config.max_hidden_layers = max(config.hidden_layers,
+                                               json_obj.get("MaxHiddenLayers").and_then(|v| v.as_u64())
+                                                                              .unwrap_or(config.hidden_layers as u64) as usize);
+                if let Some(v) = json_obj.get("MinHiddenSize").and_then(|v| v.as_u64())
+                {
+                    config.min_hidden_size = max(v as usize, 1);
+                }
+                if let Some(v) = json_obj.get("MaxHiddenSize").and_then(|v| v.as_u64())
+                {
+                    config.max_hidden_size = v as usize;
+                }
+                config.max_hidden_size = max(config.max_hidden_size, config.min_hidden_size);
+
+                if let Some(a) = json_obj.get("HiddenActivation").and_then(|a| Activation::new_from_json(a, ctx))
+                {
+                    config.hidden_activation = a;
+                }
+                if let Some(a) = json_obj.get("OutputActivation").and_then(|a| Activation::new_from_json(a, ctx))
+                {
+                    config.output_activation = a;
+                }
+                if let Some(v) = json_obj.get("TopologyMutation").and_then(|v| v.as_f64())
+                {
+                    config.topology_mutation = v as f32;
+                }
+                if let Some(v) = json_obj.get("Recurrent").and_then(|v| v.as_boolean())
+                {
+                    config.recurrent = v;
+                }
+                if let Some(v) = json_obj.get("AddNodeMutation").and_then(|v| v.as_f64())
+                {
+                    config.add_node_mutation = v as f32;
+                }
+                if let Some(v) = json_obj.get("AddConnectionMutation").and_then(|v| v.as_f64())
+                {
+                    config.add_connection_mutation = v as f32;
+                }
+                if let Some(v) = json_obj.get("Plasticity").and_then(|v| v.as_boolean())
+                {
+                    config.plasticity = v;
+                }
+                if let Some(v) = json_obj.get("LearningRate").and_then(|v| v.as_f64())
+                {
+                    config.learning_rate = (v as f32).max(0.0);
+                }
+                if let Some(i) = json_obj.get("Inheritance").and_then(|i| Inheritance::new_from_json(i, ctx))
+                {
+                    config.inheritance = i;
+                }
+                if let Some(w) = json_obj.get("WeightInitialisation").and_then(|w| WeightInitialisation::new_from_json(w, ctx))
+                {
+                    config.weight_initialisation = w;
+                }
+                if let Some(w) = json_obj.get("WeightMutation").and_then(|w| WeightMutation::new_from_json(w, ctx))
+                {
+                    config.weight_mutation = w;
+                }
+                Some(config)
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+
+fn build_layer<T>(in_size: usize, out_size: usize, weight_generator: &mut T) -> NNLayer where T: WeightsGenerator
+{
+    FeedforwardLayer::new_from(in_size, out_size, sigmoid(), || (weight_generator.generate()))
+}
+
+fn build_initial_layer(in_size: usize, out_size: usize, initialisation: WeightInitialisation,
+                       rand_ctx: &mut PolyminiRandomCtx) -> NNLayer
+{
+    match initialisation
+    {
+        WeightInitialisation::Uniform =>
+        {
+            build_layer(in_size, out_size, &mut RandomWeightsGenerator::new(rand_ctx))
+        },
+        WeightInitialisation::SymmetricUniform { range } =>
+        {
+            build_layer(in_size, out_size, &mut SymmetricUniformWeightsGenerator::new(rand_ctx, range))
+        },
+        WeightInitialisation::Gaussian { sigma } =>
+        {
+            build_layer(in_size, out_size, &mut GaussianWeightsGenerator::new(rand_ctx, sigma))
+        },
+        WeightInitialisation::Xavier =>
+        {
+            build_layer(in_size, out_size, &mut XavierWeightsGenerator::new(rand_ctx, in_size, out_size))
+        },
+        WeightInitialisation::He =>
+        {
+            build_layer(in_size, out_size, &mut HeWeightsGenerator::new(rand_ctx, in_size))
+        },
+    }
+}
+
+// NOTE: tinnmann layers only know about sigmoid, so they are used to store the weights
+// and any other activation is applied here
+//
+// 'context' is the recurrent layer of 'layer' and the values 'layer' produced on the last step,
+// 'deltas' what was learned on top of the weights of 'layer'
+fn feed(layer: &NNLayer, activation: Activation, ins: &Vec<f32>, context: Option<(&NNLayer, &Vec<f32>)>,
+        deltas: Option<&Vec<f32>>) -> Vec<f32>
+{
+    if activation == Activation::Sigmoid && context.is_none() && deltas.is_none()
+    {
+        return layer.compute(ins)
+    }
+
+    let coefficients = layer.get_coefficients();
+    let biases = layer.get_biases();
+    let out_size = layer.output_size();
+    let in_size = min(ins.len(), layer.input_size());
+
+    (0..out_size).map(|j|
+                      {
+                          let mut v = biases[j];
+                          for i in 0..in_size
+                          {
+                              let learned = match deltas
+                              {
+                                  Some(d) => { d[i * out_size + j] },
+                                  None => { 0.0 }
+                              };
+                              v += ins[i] * (coefficients[i * out_size + j] + learned);
+                          }
+                          // Biases of the recurrent layer are not used
+                          if let Some((recurrent, values)) = context
+                          {
+                              let rec_coefficients = recurrent.get_coefficients();
+                              for c in 0..min(values.len(), recurrent.input_size())
+                              {
+                                  v += values[c] * rec_coefficients[c * out_size + j];
+                              }
+                          }
+                          activation.apply(v)
+                      }).collect()
+}
+
+// Copy of 'layer' with 'deltas' added to its weights
+fn layer_with_deltas(layer: &NNLayer, deltas: Option<&Vec<f32>>) -> NNLayer
+{
+    let mut coefficients = layer.get_coefficients().clone();
+    if let Some(d) = deltas
+    {
+        for i in 0..min(coefficients.len(), d.len())
+        {
+            coefficients[i] += d[i];
+        }
+    }
+    FeedforwardLayer::new_from_values(layer.input_size(), layer.output_size(), sigmoid(),
+                                      coefficients, layer.get_biases().clone())
+}
+
+// Learned weights are kept within [-MAX_HEBBIAN_DELTA, MAX_HEBBIAN_DELTA] of the inherited ones
+const MAX_HEBBIAN_DELTA: f32 = 1.0;
+
+// What the network saw and fired on a single step
+#[derive(Clone, Debug)]
+pub struct TraceStep
+{
+    pub step: usize,
+    pub inputs: Vec<f32>,
+    pub hidden: Vec<f32>,
+    pub outputs: Vec<f32>,
+}
+impl Serializable for TraceStep
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Step".to_owned(), self.step.to_json());
+        json_obj.insert("Inputs".to_owned(), self.inputs.to_json());
+        json_obj.insert("Hidden".to_owned(), self.hidden.to_json());
+        json_obj.insert("Outputs".to_owned(), self.outputs.to_json());
+        Json::Object(json_obj)
+    }
+}
+
+// Recording of every step a Control took while tracing was on, with a name for each column
+#[derive(Clone, Debug)]
+pub struct ControlTrace
+{
+    input_labels: Vec<String>,
+    hidden_labels: Vec<String>,
+    output_labels: Vec<String>,
+    steps: Vec<TraceStep>,
+
+    // Simulation substep the next recording belongs to, see Control::set_step
+    current_step: usize,
+}
+impl ControlTrace
+{
+    fn new(sensors: &Vec<Sensor>, actuators: &Vec<Actuator>, hidden_sizes: &Vec<usize>) -> ControlTrace
+    {
+        let input_labels = Sensor::get_input_keys(sensors).iter().map(|&(tag, occurrence, slot)|
+                           {
+                               format!("{}_{}_{}", tag, occurrence, slot)
+                           }).collect();
+
+        let mut hidden_labels = vec![];
+        for (k, size) in hidden_sizes.iter().enumerate()
+        {
+            for i in 0..*size
+            {
+                hidden_labels.push(format!("Hidden_{}_{}", k, i));
+            }
+        }
+
+        let output_labels = actuators.iter().enumerate().map(|(i, a)|
+                            {
+                                format!("{}_{}", a.get_tag(), i)
+                            }).collect();
+
+        ControlTrace { input_labels: input_labels, hidden_labels: hidden_labels,
+                       output_labels: output_labels, steps: vec![], current_step: 0 }
+    }
+
+    pub fn get_steps(&self) -> &Vec<TraceStep>
+    {
+        &self.steps
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.steps.len()
+    }
+
+    // One row per step, hidden columns are only there for layered networks
+    pub fn to_csv(&self) -> String
+    {
+        let mut header = vec!["Step".to_owned()];
+        header.extend(self.input_labels.iter().map(|l| format!("In_{}", l)));
+        header.extend(self.hidden_labels.iter().cloned());
+        header.extend(self.output_labels.iter().map(|l| format!("Out_{}", l)));
+
+        let mut csv = header.join(",");
+        csv.push('\n');
+        for step in self.steps.iter()
+        {
+            let mut row = vec![step.step.to_string()];
+            row.extend(step.inputs.iter().map(|v| v.to_string()));
+            // Pad so every row has the same columns as the header
+            for i in 0..self.hidden_labels.len()
+            {
+                row.push(step.hidden.get(i).map_or("".to_owned(), |v| v.to_string()));
+            }
+            row.extend(step.outputs.iter().map(|v| v.to_string()));
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+        csv
+    }
+
+    fn record(&mut self, inputs: &Vec<f32>, hidden: &Vec<Vec<f32>>, outputs: &Vec<f32>)
+    {
+        self.steps.push(TraceStep { step: self.current_step,
+                                    inputs: inputs.clone(),
+                                    hidden: hidden.iter().flat_map(|h| h.iter().cloned()).collect(),
+                                    outputs: outputs.clone() });
+    }
+}
+impl Serializable for ControlTrace
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("InputLabels".to_owned(), self.input_labels.to_json());
+        json_obj.insert("HiddenLabels".to_owned(), self.hidden_labels.to_json());
+        json_obj.insert("OutputLabels".to_owned(), self.output_labels.to_json());
+        json_obj.insert("Steps".to_owned(), Json::Array(self.steps.iter().map(|s| s.serialize(ctx)).collect()));
+        Json::Object(json_obj)
     }
 }
 
-pub type NNLayer = FeedforwardLayer<f32, fn(f32) -> f32, fn(f32) -> f32>;
 pub struct Control
 {
-    sensor_list: Vec<Sensor>, 
+    sensor_list: Vec<Sensor>,
     actuator_list: Vec<Actuator>,
 
-    hidden_layer_size: usize,
-
-    // NN
+    // NN - Input to first hidden layer, ..., last hidden layer to output
     nn: Vec<NNLayer>,
+    activations: Vec<Activation>,
 
+    // Hidden to hidden (Elman) layers, one per hidden layer or none at all
+    recurrent: Vec<NNLayer>,
+
+    // When set it replaces the layers above
+    neat: Option<NeatNetwork>,
+
+    // Hebbian plasticity - One learning rate and one set of deltas per layer in nn, both are
+    // empty for networks that don't learn
+    learning_rates: Vec<f32>,
+    deltas: Vec<Vec<f32>>,
+    inheritance: Inheritance,
+
+    // Only recorded while debugging, see start_trace
+    trace: Option<ControlTrace>,
 
     //
     inputs: Vec<f32>,
-    hidden: Vec<f32>,
+    hidden: Vec<Vec<f32>>,
     outputs: Vec<f32>,
 }
 impl Control
@@ -56,8 +894,14 @@ impl Control
         Control {
                   sensor_list: vec![],
                   actuator_list: vec![],
-                  hidden_layer_size: 0,
                   nn: vec![FeedforwardLayer::new(1, 1, sigmoid())],
+                  activations: vec![Activation::Sigmoid],
+                  recurrent: vec![],
+                  neat: None,
+                  learning_rates: vec![],
+                  deltas: vec![],
+                  inheritance: Inheritance::Darwinian,
+                  trace: None,
                   inputs: vec![],
                   hidden: vec![],
                   outputs: vec![],
@@ -70,20 +914,11 @@ impl Control
         let in_len = Sensor::get_total_cardinality(&sensor_list);
         let out_len = actuator_list.len();
 
-        let in_to_hidden:  NNLayer = FeedforwardLayer::new_from(in_len, hidden_layer_size, sigmoid(), ||(in_to_hid_weight_generator.generate()));
-        let hidden_to_out: NNLayer = FeedforwardLayer::new_from(hidden_layer_size, out_len, sigmoid(), ||(hid_to_out_weight_generator.generate()));
-
-        Control
-        {
-          sensor_list: sensor_list,
-          actuator_list: actuator_list,
-          hidden_layer_size: hidden_layer_size,
-          nn: vec![in_to_hidden, hidden_to_out],
-          inputs: vec![0.0; in_len],
-          hidden: vec![0.0; hidden_layer_size],
-          outputs: vec![0.0; out_len],
-        }
+        let in_to_hidden  = build_layer(in_len, hidden_layer_size, in_to_hid_weight_generator);
+        let hidden_to_out = build_layer(hidden_layer_size, out_len, hid_to_out_weight_generator);
 
+        Control::new_from_layers(sensor_list, actuator_list, vec![in_to_hidden, hidden_to_out],
+                                 vec![Activation::Sigmoid, Activation::Sigmoid], vec![])
     }
 
     pub fn new_from_random_ctx(sensor_list: Vec<Sensor>, actuator_list: Vec<Actuator>, hidden_layer_size: usize,
@@ -92,27 +927,99 @@ impl Control
         let in_len = Sensor::get_total_cardinality(&sensor_list);
         let out_len = actuator_list.len();
 
-        let in_to_hidden:  NNLayer =
+        let in_to_hidden  = build_layer(in_len, hidden_layer_size, &mut RandomWeightsGenerator::new(rnd_ctx));
+        let hidden_to_out = build_layer(hidden_layer_size, out_len, &mut RandomWeightsGenerator::new(rnd_ctx));
+
+        Control::new_from_layers(sensor_list, actuator_list, vec![in_to_hidden, hidden_to_out],
+                                 vec![Activation::Sigmoid, Activation::Sigmoid], vec![])
+    }
+
+    pub fn new_from_config(sensor_list: Vec<Sensor>, actuator_list: Vec<Actuator>, config: &ControlConfig,
+                           rnd_ctx: &mut PolyminiRandomCtx) -> Control
+    {
+        if config.kind == ControlKind::Neat
         {
+            let network = NeatNetwork::new(&sensor_list, &actuator_list, config, rnd_ctx);
+            return Control::new_from_neat(sensor_list, actuator_list, network)
+        }
 
-            let mut in_to_hid_gen = RandomWeightsGenerator::new(rnd_ctx);
-            FeedforwardLayer::new_from(in_len, hidden_layer_size, sigmoid(), ||(in_to_hid_gen.generate()))
-        };
+        let mut in_len = Sensor::get_total_cardinality(&sensor_list);
+        let out_len = actuator_list.len();
 
-        let hidden_to_out: NNLayer = 
+        let mut layers = vec![];
+        let mut activations = vec![];
+        let mut recurrent = vec![];
+        for _ in 0..max(config.hidden_layers, 1)
         {
-            let mut hid_to_out_gen = RandomWeightsGenerator::new(rnd_ctx);
-            FeedforwardLayer::new_from(hidden_layer_size, out_len, sigmoid(), ||(hid_to_out_gen.generate()))
-        };
+            let size = rnd_ctx.gen_range(config.min_hidden_size, config.max_hidden_size + 1);
+            layers.push(build_initial_layer(in_len, size, config.weight_initialisation, rnd_ctx));
+            activations.push(config.hidden_activation);
+            in_len = size;
+        }
+        layers.push(build_initial_layer(in_len, out_len, config.weight_initialisation, rnd_ctx));
+        activations.push(config.output_activation);
+
+        if config.recurrent
+        {
+            for k in 0..layers.len() - 1
+            {
+                let size = layers[k].output_size();
+                recurrent.push(build_initial_layer(size, size, config.weight_initialisation, rnd_ctx));
+            }
+        }
+
+        let mut control = Control::new_from_layers(sensor_list, actuator_list, layers, activations, recurrent);
+        control.update_plasticity(config, rnd_ctx);
+        control
+    }
+
+    fn new_from_layers(sensor_list: Vec<Sensor>, actuator_list: Vec<Actuator>, nn: Vec<NNLayer>,
+                       activations: Vec<Activation>, recurrent: Vec<NNLayer>) -> Control
+    {
+        assert_eq!(nn.len(), activations.len());
+        assert!(recurrent.len() == 0 || recurrent.len() == nn.len() - 1);
+
+        let in_len = nn[0].input_size();
+        let out_len = nn[nn.len() - 1].output_size();
+        let hidden = nn[0..nn.len() - 1].iter().map(|l| vec![0.0; l.output_size()]).collect();
+
+        Control
+        {
+          sensor_list: sensor_list,
+          actuator_list: actuator_list,
+          nn: nn,
+          activations: activations,
+          recurrent: recurrent,
+          neat: None,
+          learning_rates: vec![],
+          deltas: vec![],
+          inheritance: Inheritance::Darwinian,
+          trace: None,
+          inputs: vec![0.0; in_len],
+          hidden: hidden,
+          outputs: vec![0.0; out_len],
+        }
+    }
+
+    fn new_from_neat(sensor_list: Vec<Sensor>, actuator_list: Vec<Actuator>, network: NeatNetwork) -> Control
+    {
+        let in_len = Sensor::get_total_cardinality(&sensor_list);
+        let out_len = actuator_list.len();
 
         Control
         {
           sensor_list: sensor_list,
           actuator_list: actuator_list,
-          hidden_layer_size: hidden_layer_size,
-          nn: vec![in_to_hidden, hidden_to_out],
+          nn: vec![],
+          activations: vec![],
+          recurrent: vec![],
+          neat: Some(network),
+          learning_rates: vec![],
+          deltas: vec![],
+          inheritance: Inheritance::Darwinian,
+          trace: None,
           inputs: vec![0.0; in_len],
-          hidden: vec![0.0; hidden_layer_size],
+          hidden: vec![],
           outputs: vec![0.0; out_len],
         }
     }
@@ -123,25 +1030,88 @@ impl Control
         {
             Json::Object(ref json_obj) =>
             {
-                let mut nn_json = json_obj.get("InToHidden").unwrap();
-                let in_to_hid = FeedforwardLayer::new_from_json(&nn_json, &mut SerializationCtx::new()).unwrap();
+                if let Some(neat_json) = json_obj.get("Neat")
+                {
+                    return NeatNetwork::new_from_json(neat_json, &mut SerializationCtx::new()).map(
+                        |network| Control::new_from_neat(sensor_list, actuator_list, network))
+                }
+
+                let mut nn = vec![];
+                let mut activations = vec![];
+
+                match json_obj.get("Layers")
+                {
+                    Some(&Json::Array(ref layers_json)) =>
+                    {
+                        for layer_json in layers_json
+                        {
+                            match NNLayer::new_from_json(layer_json, &mut SerializationCtx::new())
+                            {
+                                Some(layer) => { nn.push(layer); },
+                                None =>
+                                {
+                                    error!("Control - Invalid Layer {}", layer_json);
+                                    return None
+                                }
+                            }
+                            activations.push(layer_json.find("Activation")
+                                                       .and_then(|a| Activation::new_from_json(a, &mut SerializationCtx::new()))
+                                                       .unwrap_or(Activation::Sigmoid));
+                        }
+                    },
+                    _ =>
+                    {
+                        // Single hidden layer format
+                        let mut nn_json = json_obj.get("InToHidden").unwrap();
+                        nn.push(FeedforwardLayer::new_from_json(&nn_json, &mut SerializationCtx::new()).unwrap());
+
+                        nn_json = json_obj.get("HiddenToOutput").unwrap();
+                        nn.push(FeedforwardLayer::new_from_json(&nn_json, &mut SerializationCtx::new()).unwrap());
+
+                        activations = vec![Activation::Sigmoid, Activation::Sigmoid];
+                    }
+                }
+
+                if nn.len() == 0
+                {
+                    error!("Control - No layers found");
+                    return None
+                }
 
-                nn_json = json_obj.get("HiddenToOutput").unwrap();
-                let hid_to_out = FeedforwardLayer::new_from_json(&nn_json, &mut SerializationCtx::new()).unwrap();
-                let in_len = json_obj.get("Input").unwrap().as_u64().unwrap() as usize;
-                let hid_len = json_obj.get("Hidden").unwrap().as_u64().unwrap() as usize;
-                let out_len = json_obj.get("Output").unwrap().as_u64().unwrap() as usize;
-                
-                Some(Control
+                let mut recurrent = vec![];
+                if let Some(&Json::Array(ref recurrent_json)) = json_obj.get("Recurrent")
+                {
+                    for layer_json in recurrent_json
                     {
-                        sensor_list: sensor_list,
-                        actuator_list: actuator_list,
-                        hidden_layer_size: hid_len,
-                        nn: vec![in_to_hid, hid_to_out],
-                        inputs: vec![0.0; in_len],
-                        hidden: vec![0.0; hid_len],
-                        outputs: vec![0.0; out_len],
-                    })
+                        match NNLayer::new_from_json(layer_json, &mut SerializationCtx::new())
+                        {
+                            Some(layer) => { recurrent.push(layer); },
+                            None =>
+                            {
+                                error!("Control - Invalid Recurrent Layer {}", layer_json);
+                                return None
+                            }
+                        }
+                    }
+                }
+
+                if recurrent.len() != 0 && recurrent.len() != nn.len() - 1
+                {
+                    error!("Control - {} recurrent layers for {} hidden layers", recurrent.len(), nn.len() - 1);
+                    return None
+                }
+
+                let mut control = Control::new_from_layers(sensor_list, actuator_list, nn, activations, recurrent);
+                if let Some(&Json::Array(ref rates_json)) = json_obj.get("LearningRates")
+                {
+                    control.learning_rates = rates_json.iter().map(|r| r.as_f64().unwrap_or(0.0) as f32).collect();
+                    control.learning_rates.truncate(control.nn.len());
+                    control.deltas = control.nn.iter().map(|l| vec![0.0; l.get_coefficients().len()]).collect();
+                    control.inheritance = json_obj.get("Inheritance")
+                                                  .and_then(|i| Inheritance::new_from_json(i, &mut SerializationCtx::new()))
+                                                  .unwrap_or(Inheritance::Darwinian);
+                }
+                Some(control)
             },
             _ =>
             {
@@ -149,7 +1119,55 @@ impl Control
             }
         }
     }
-    
+
+    pub fn get_hidden_sizes(&self) -> Vec<usize>
+    {
+        if self.is_neat()
+        {
+            return vec![]
+        }
+        self.nn[0..self.nn.len() - 1].iter().map(|l| l.output_size()).collect()
+    }
+
+    pub fn get_activations(&self) -> &Vec<Activation>
+    {
+        &self.activations
+    }
+
+    pub fn is_neat(&self) -> bool
+    {
+        self.neat.is_some()
+    }
+
+    pub fn get_neat(&self) -> Option<&NeatNetwork>
+    {
+        self.neat.as_ref()
+    }
+
+    pub fn is_recurrent(&self) -> bool
+    {
+        self.recurrent.len() > 0
+    }
+
+    // Forget whatever was fed back from previous steps
+    pub fn reset_memory(&mut self)
+    {
+        for h in self.hidden.iter_mut()
+        {
+            for v in h.iter_mut()
+            {
+                *v = 0.0;
+            }
+        }
+    }
+
+    // Single hidden layer with sigmoids everywhere, the original topology
+    fn is_classic(&self) -> bool
+    {
+        !self.is_neat() && self.nn.len() == 2 && self.recurrent.len() == 0 &&
+        self.activations.iter().all(|a| *a == Activation::Sigmoid)
+    }
+
     pub fn sense(&mut self, sensed: &SensoryPayload)
     {
         trace!("Sensing...");
@@ -164,46 +1182,262 @@ impl Control
             {
                 Some(payload) =>
                 {
-                    //TODO: Multi-input sensors (e.g. Direction + Distance)
-                    self.inputs.push(*payload);
+                    for slot in 0..sensor.cardinality
+                    {
+                        self.inputs.push(*payload.get(slot).unwrap_or(&NEUTRAL_INPUT));
+                    }
                     j += 1;
                 },
                 None =>
                 {
-                    // Not an Error could be that there's nothing to be 
-                    // sensed for that particular tag
+                    // Not an Error could be that there's nothing to be
+                    // sensed for that particular tag, keep the inputs lined up anyway
+                    for _ in 0..sensor.cardinality
+                    {
+                        self.inputs.push(NEUTRAL_INPUT);
+                    }
+                }
+            }
+        }
+        trace!("Sensed... {}/{}", i, j);
+    }
+    pub fn think(&mut self)
+    {
+        trace!("Thinking...");
+        // Feedforward NN, recurrent layers also get what their hidden layer fired on the last step
+
+        // Move all the values from [0..1] to [-0.5, 0.5] and amplify them by 10x
+        // NOTE: This 10x was needed because the inputs were changing too subtly and the NN wasn't
+        // really 'learning'
+        let ins: Vec<f32> = self.inputs.iter().map(|&v| { (v - 0.5) * 10.0 }).collect();
+
+        if let Some(ref network) = self.neat
+        {
+            self.outputs = network.compute(&ins);
+            debug!("NNDebug::Think - Inputs:  {:?}", ins);
+            debug!("NNDebug::Think - Outputs: {:?}", self.outputs);
+            if let Some(ref mut trace) = self.trace
+            {
+                trace.record(&self.inputs, &self.hidden, &self.outputs);
+            }
+            return
+        }
+
+        let last = self.nn.len() - 1;
+        let mut values = ins.clone();
+        let previous = mem::replace(&mut self.hidden, vec![]);
+        for (k, layer) in self.nn.iter().enumerate()
+        {
+            let context = if k < self.recurrent.len() && k < previous.len()
+            {
+                Some((&self.recurrent[k], &previous[k]))
+            }
+            else
+            {
+                None
+            };
+            values = feed(layer, self.activations[k], &values, context, self.deltas.get(k));
+            if k < last
+            {
+                self.hidden.push(values.clone());
+            }
+        }
+        let outs = values;
+
+        assert_eq!(outs.len(), self.outputs.len());
+
+        if outs.len() > 0
+        {
+            debug!("NNDebug::Think - Inputs:  {:?}", ins);
+            debug!("NNDebug::Think - Hiddens: {:?}", self.hidden);
+            debug!("NNDebug::Think - Outputs: {:?}", outs);
+        }
+        let out_activation = self.activations[last];
+        self.outputs = outs.iter().map(|&v| { out_activation.center(v) }).collect();
+
+        if self.is_plastic()
+        {
+            self.learn(&ins);
+        }
+
+        if let Some(ref mut trace) = self.trace
+        {
+            trace.record(&self.inputs, &self.hidden, &self.outputs);
+        }
+    }
+
+    // Start recording inputs, hidden activations and outputs on every think, any previous
+    // recording is thrown away
+    pub fn start_trace(&mut self)
+    {
+        let hidden_sizes = self.get_hidden_sizes();
+        self.trace = Some(ControlTrace::new(&self.sensor_list, &self.actuator_list, &hidden_sizes));
+    }
+
+    pub fn stop_trace(&mut self) -> Option<ControlTrace>
+    {
+        self.trace.take()
+    }
+
+    pub fn get_trace(&self) -> Option<&ControlTrace>
+    {
+        self.trace.as_ref()
+    }
+
+    // Simulation substep the next think belongs to, it is what the trace records
+    pub fn set_step(&mut self, step: usize)
+    {
+        if let Some(ref mut trace) = self.trace
+        {
+            trace.current_step = step;
+        }
+    }
+
+    // Shape of the network if it can think as part of a ControlBatch - Networks that carry
+    // anything from one step to the next (memory, learning) or are NEAT can't
+    pub fn get_topology(&self) -> Option<ControlTopology>
+    {
+        if self.is_neat() || self.is_recurrent() || self.is_plastic()
+        {
+            return None
+        }
+        Some(ControlTopology { sizes: self.nn.iter().map(|l| (l.input_size(), l.output_size())).collect(),
+                               activations: self.activations.clone() })
+    }
+
+    // Hebbian rule, the weight between two neurons grows when both fire together (and shrinks
+    // when they don't) - values are centered around 0 so both directions happen
+    fn learn(&mut self, ins: &Vec<f32>)
+    {
+        let last = self.nn.len() - 1;
+        for k in 0..self.nn.len()
+        {
+            let pre: Vec<f32> = if k == 0
+            {
+                ins.clone()
+            }
+            else
+            {
+                self.hidden[k - 1].iter().map(|&v| self.activations[k - 1].center(v)).collect()
+            };
+            let post: Vec<f32> = if k == last
+            {
+                self.outputs.clone()
+            }
+            else
+            {
+                self.hidden[k].iter().map(|&v| self.activations[k].center(v)).collect()
+            };
+
+            let rate = self.learning_rates[k];
+            let out_size = self.nn[k].output_size();
+            let deltas = &mut self.deltas[k];
+            for i in 0..min(pre.len(), self.nn[k].input_size())
+            {
+                for j in 0..min(post.len(), out_size)
+                {
+                    let d = &mut deltas[i * out_size + j];
+                    *d = (*d + rate * pre[i] * post[j]).min(MAX_HEBBIAN_DELTA).max(-MAX_HEBBIAN_DELTA);
                 }
             }
-        }
-        trace!("Sensed... {}/{}", i, j);
+        }
+    }
+
+    pub fn is_plastic(&self) -> bool
+    {
+        self.learning_rates.len() > 0
+    }
+
+    pub fn get_learning_rates(&self) -> &Vec<f32>
+    {
+        &self.learning_rates
+    }
+
+    // Called when the individual is done living, what it learned goes into its weights or is
+    // lost depending on the inheritance
+    pub fn end_of_life(&mut self)
+    {
+        if !self.is_plastic()
+        {
+            return
+        }
+
+        if self.inheritance == Inheritance::Lamarckian
+        {
+            for k in 0..self.nn.len()
+            {
+                let baked = layer_with_deltas(&self.nn[k], self.deltas.get(k));
+                self.nn[k].set_coefficients(baked.get_coefficients().clone());
+            }
+        }
+        self.deltas = self.nn.iter().map(|l| vec![0.0; l.get_coefficients().len()]).collect();
+    }
+
+    // Copy of this network with what it learned added to its weights
+    fn baked(&self) -> Control
+    {
+        let mut control = match self.neat
+        {
+            Some(ref network) =>
+            {
+                Control::new_from_neat(self.sensor_list.clone(), self.actuator_list.clone(), network.clone())
+            },
+            None =>
+            {
+                let nn = self.nn.iter().enumerate().map(|(k, l)| layer_with_deltas(l, self.deltas.get(k))).collect();
+                let recurrent = self.recurrent.iter().map(|l| layer_with_deltas(l, None)).collect();
+                Control::new_from_layers(self.sensor_list.clone(), self.actuator_list.clone(), nn,
+                                         self.activations.clone(), recurrent)
+            }
+        };
+        control.learning_rates = self.learning_rates.clone();
+        control.deltas = control.nn.iter().map(|l| vec![0.0; l.get_coefficients().len()]).collect();
+        control.inheritance = self.inheritance;
+        control
     }
-    pub fn think(&mut self)
-    {
-        trace!("Thinking...");
-        // Feedforward NN
-        let mut ins : Vec<f32> = self.inputs.clone();
-
-        // Move all the values from [0..1] to [-0.5, 0.5] and amplify them by 10x
-        // NOTE: This 10x was needed because the inputs were changing too subtly and the NN wasn't
-        // really 'learning'
-        ins = ins.iter().map(|&v| { (v - 0.5) * 10.0 }).collect();
-        
 
-        let hid = self.nn[0].compute(&ins);
-        let outs = self.nn[1].compute(&hid);
+    fn new_learning_rate(config: &ControlConfig, random_ctx: &mut PolyminiRandomCtx) -> f32
+    {
+        if config.learning_rate > 0.0 { random_ctx.gen_range(0.0, config.learning_rate) } else { 0.0 }
+    }
 
-        self.hidden = hid.clone();
+    // Keep the learning rates and deltas lined up with the layers, networks become plastic if
+    // the config asks for it (and stay plastic if they already were)
+    fn update_plasticity(&mut self, config: &ControlConfig, random_ctx: &mut PolyminiRandomCtx)
+    {
+        self.inheritance = config.inheritance;
+        if self.is_neat() || (!config.plasticity && !self.is_plastic())
+        {
+            return
+        }
 
-        assert_eq!(outs.len(), self.outputs.len());
+        self.learning_rates.truncate(self.nn.len());
+        while self.learning_rates.len() < self.nn.len()
+        {
+            let rate = Control::new_learning_rate(config, random_ctx);
+            self.learning_rates.push(rate);
+        }
 
-        if outs.len() > 0
+        self.deltas.truncate(self.nn.len());
+        for k in 0..self.nn.len()
         {
-            debug!("NNDebug::Think - Inputs:  {:?}", ins); 
-            debug!("NNDebug::Think - Hiddens: {:?}", hid); 
-            debug!("NNDebug::Think - Outputs: {:?}", outs);
+            let size = self.nn[k].get_coefficients().len();
+            if k == self.deltas.len()
+            {
+                self.deltas.push(vec![0.0; size]);
+            }
+            else if self.deltas[k].len() != size
+            {
+                self.deltas[k] = vec![0.0; size];
+            }
         }
-        self.outputs = outs.iter().map(|&v| { v - 0.5 }).collect();
     }
+    // What the network fired on the last think, one value per actuator
+    pub fn get_outputs(&self) -> &Vec<f32>
+    {
+        &self.outputs
+    }
+
     pub fn get_actions(&self) -> ActionList
     {
         // Get actions from Actuators
@@ -223,77 +1457,396 @@ impl Control
         action_list
     }
 
-    pub fn crossover(&self, other: &Control, rand_ctx: &mut PolyminiRandomCtx, new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>) -> Control
+    // Where each input for 'new_sensor_list' was in this network, if the sensors and the
+    // first layer agree on how many inputs there are
+    fn get_input_mapping(&self, new_sensor_list: &Vec<Sensor>) -> Option<Vec<Option<usize>>>
     {
-        // Make sure we pick the smaller size and then the bigger and that we at least have a
-        // correct range
-        let s_size = max(min(self.hidden_layer_size, other.hidden_layer_size) - 1, 1);
-        let b_size = min(max(self.hidden_layer_size, other.hidden_layer_size) + 1, s_size + 1);
-        let hid_len = rand_ctx.gen_range(s_size, b_size);
+        if self.is_neat() || Sensor::get_total_cardinality(&self.sensor_list) != self.nn[0].input_size()
+        {
+            return None
+        }
+        Some(Sensor::get_input_mapping(&self.sensor_list, new_sensor_list))
+    }
 
-        let new_in_size = Sensor::get_total_cardinality(&new_sensor_list);
-        debug!("Crossing In to Hidden Layer - {}", self.nn[0].get_coefficients().len());
-        let mut in_to_hid_generator = CrossoverWeightsGenerator::new(rand_ctx, &self.nn[0], &other.nn[0], self.inputs.len(), self.hidden_layer_size, new_in_size, hid_len);
-        debug!("Crossing Hidden Layer to Out");
-        let mut hid_to_out_generator = CrossoverWeightsGenerator::new(rand_ctx, &self.nn[1], &other.nn[1], self.hidden_layer_size, self.outputs.len(), hid_len, new_actuator_list.len());
-        
-        Control::new_from(new_sensor_list, new_actuator_list, hid_len, &mut in_to_hid_generator, &mut hid_to_out_generator)
+    // k-th layer of this network as seen from a network of 'depth' hidden layers, the output
+    // layer always lines up with the output layer
+    fn get_layer_for(&self, k: usize, depth: usize) -> Option<(&NNLayer, Activation)>
+    {
+        let own_depth = self.nn.len() - 1;
+        if k == depth
+        {
+            Some((&self.nn[own_depth], self.activations[own_depth]))
+        }
+        else if k < own_depth
+        {
+            Some((&self.nn[k], self.activations[k]))
+        }
+        else
+        {
+            None
+        }
     }
 
-    pub fn mutate(&mut self, random_ctx: &mut PolyminiRandomCtx, new_sensor_list: Vec<Sensor>,
-                  new_actuator_list: Vec<Actuator>)
+    pub fn crossover(&self, other: &Control, rand_ctx: &mut PolyminiRandomCtx, config: &ControlConfig,
+                     new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>) -> Control
     {
-        
-        let delta_hl: i32 = random_ctx.gen_range(-2, 2);
+        if config.inheritance == Inheritance::Lamarckian && (self.is_plastic() || other.is_plastic())
+        {
+            return self.baked().crossover_genes(&other.baked(), rand_ctx, config, new_sensor_list, new_actuator_list)
+        }
+        self.crossover_genes(other, rand_ctx, config, new_sensor_list, new_actuator_list)
+    }
+
+    fn crossover_genes(&self, other: &Control, rand_ctx: &mut PolyminiRandomCtx, config: &ControlConfig,
+                       new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>) -> Control
+    {
+        // A layered parent has nothing to give to a Neat one
+        let neat_parents = match (&self.neat, &other.neat)
+        {
+            (&Some(ref n1), &Some(ref n2)) => { Some((n1, n2)) },
+            (&Some(ref n1), &None) | (&None, &Some(ref n1)) =>
+            {
+                warn!("Crossing a Neat network with a layered one, only the Neat parent is used");
+                Some((n1, n1))
+            },
+            (&None, &None) => { None }
+        };
+        if let Some((n1, n2)) = neat_parents
+        {
+            let network = n1.crossover(n2, rand_ctx, config, &new_sensor_list, &new_actuator_list);
+            return Control::new_from_neat(new_sensor_list, new_actuator_list, network)
+        }
+
+        let self_hidden = self.get_hidden_sizes();
+        let other_hidden = other.get_hidden_sizes();
 
-        let new_hid_size = if (self.hidden_layer_size as i32 + delta_hl) >= 1
+        // Networks of different depth produce a child somewhere in between
+        let depth = if self_hidden.len() == other_hidden.len()
         {
-            ((self.hidden_layer_size as i32) + delta_hl) as usize
+            self_hidden.len()
         }
         else
         {
-            random_ctx.gen_range(1,3) 
+            rand_ctx.gen_range(min(self_hidden.len(), other_hidden.len()),
+                               max(self_hidden.len(), other_hidden.len()) + 1)
         };
 
-        let new_in_size =  Sensor::get_total_cardinality(&new_sensor_list);
+        let mut hidden_sizes = vec![];
+        for k in 0..depth
+        {
+            let size = match (self_hidden.get(k), other_hidden.get(k))
+            {
+                (Some(&s_hid), Some(&o_hid)) =>
+                {
+                    // Make sure we pick the smaller size and then the bigger and that we at least have a
+                    // correct range
+                    let s_size = max(min(s_hid, o_hid) - 1, 1);
+                    let b_size = min(max(s_hid, o_hid) + 1, s_size + 1);
+                    rand_ctx.gen_range(s_size, b_size)
+                },
+                (Some(&hid), None) | (None, Some(&hid)) =>
+                {
+                    hid
+                },
+                (None, None) =>
+                {
+                    rand_ctx.gen_range(config.min_hidden_size, config.max_hidden_size + 1)
+                }
+            };
+            // Parents might come from a different config, and crossing keeps shrinking layers
+            hidden_sizes.push(max(config.min_hidden_size, min(size, config.max_hidden_size)));
+        }
+
+        let new_in_size = Sensor::get_total_cardinality(&new_sensor_list);
         let new_out_size = new_actuator_list.len();
 
-        let old_in_size =  self.inputs.len();
-        let old_out_size = self.outputs.len();
-        if  new_in_size  != old_in_size ||
-            new_out_size != old_out_size ||
-            new_hid_size  != self.hidden_layer_size         /* Brain Changed */
+        let mut layers = vec![];
+        let mut activations = vec![];
+        for k in 0..depth + 1
+        {
+            let layer_in = if k == 0 { new_in_size } else { hidden_sizes[k - 1] };
+            let layer_out = if k == depth { new_out_size } else { hidden_sizes[k] };
+
+            // When only one of the parents has the layer it is crossed with itself
+            let parents = match (self.get_layer_for(k, depth), other.get_layer_for(k, depth))
+            {
+                (Some((l1, a)), Some((l2, _))) => { Some((l1, l2, a)) },
+                (Some((l1, a)), None) | (None, Some((l1, a))) => { Some((l1, l1, a)) },
+                (None, None) => { None }
+            };
+
+            let layer = match parents
+            {
+                Some((l1, l2, a)) =>
+                {
+                    debug!("Crossing Layer {} - {}", k, l1.get_coefficients().len());
+                    let mappings = if k == 0
+                    {
+                        (self.get_input_mapping(&new_sensor_list), other.get_input_mapping(&new_sensor_list))
+                    }
+                    else
+                    {
+                        (None, None)
+                    };
+                    let mut generator = match mappings
+                    {
+                        (Some(m1), Some(m2)) =>
+                        {
+                            CrossoverWeightsGenerator::new_remapped(rand_ctx, l1, l2, &m1, &m2, layer_out)
+                        },
+                        _ =>
+                        {
+                            CrossoverWeightsGenerator::new(rand_ctx, l1, l2, l1.input_size(), l1.output_size(),
+                                                           layer_in, layer_out)
+                        }
+                    };
+                    activations.push(a);
+                    build_layer(layer_in, layer_out, &mut generator)
+                },
+                None =>
+                {
+                    activations.push(if k == depth { config.output_activation } else { config.hidden_activation });
+                    build_initial_layer(layer_in, layer_out, config.weight_initialisation, rand_ctx)
+                }
+            };
+            layers.push(layer);
+        }
+
+        // Memory is kept if any of the parents had it
+        let mut recurrent = vec![];
+        if config.recurrent || self.is_recurrent() || other.is_recurrent()
+        {
+            for k in 0..depth
+            {
+                let size = hidden_sizes[k];
+                let layer = match (self.recurrent.get(k), other.recurrent.get(k))
+                {
+                    (Some(l1), _) | (None, Some(l1)) =>
+                    {
+                        let l2 = other.recurrent.get(k).unwrap_or(l1);
+                        let mut generator = CrossoverWeightsGenerator::new(rand_ctx, l1, l2, l1.input_size(), l1.output_size(),
+                                                                           size, size);
+                        build_layer(size, size, &mut generator)
+                    },
+                    (None, None) =>
+                    {
+                        build_initial_layer(size, size, config.weight_initialisation, rand_ctx)
+                    }
+                };
+                recurrent.push(layer);
+            }
+        }
+
+        let mut child = Control::new_from_layers(new_sensor_list, new_actuator_list, layers, activations, recurrent);
+
+        // Learning rates come from either parent
+        for k in 0..child.nn.len()
+        {
+            let rate = match (self.learning_rates.get(k), other.learning_rates.get(k))
+            {
+                (Some(&r1), Some(&r2)) => { Some(if rand_ctx.gen_range(0.0, 1.0) < 0.5 { r1 } else { r2 }) },
+                (Some(&r), None) | (None, Some(&r)) => { Some(r) },
+                (None, None) => { None }
+            };
+            match rate
+            {
+                Some(r) => { child.learning_rates.push(r); },
+                None => { break }
+            }
+        }
+        child.update_plasticity(config, rand_ctx);
+        child
+    }
+
+    pub fn mutate(&mut self, random_ctx: &mut PolyminiRandomCtx, config: &ControlConfig, new_sensor_list: Vec<Sensor>,
+                  new_actuator_list: Vec<Actuator>)
+    {
+        if let Some(ref mut network) = self.neat
+        {
+            network.mutate(random_ctx, config, &new_sensor_list, &new_actuator_list);
+            self.inputs.resize(Sensor::get_total_cardinality(&new_sensor_list), 0.0);
+            self.outputs.resize(new_actuator_list.len(), 0.0);
+            self.actuator_list = new_actuator_list.clone();
+            self.sensor_list = new_sensor_list.clone();
+            return
+        }
+
+        let mut topology_changed = false;
+
+        // Add or remove a whole hidden layer
+        if config.topology_mutation > 0.0 && random_ctx.gen_range(0.0, 1.0) < config.topology_mutation
         {
-            if new_in_size != old_in_size
+            let hidden_layers = self.nn.len() - 1;
+            let add = hidden_layers < config.max_hidden_layers &&
+                      (hidden_layers <= 1 || random_ctx.gen_range(0.0, 1.0) < 0.5);
+            if add
+            {
+                // The layer after the new one is resized below
+                let p = random_ctx.gen_range(0, hidden_layers + 1);
+                let in_size = self.nn[p].input_size();
+                let size = random_ctx.gen_range(config.min_hidden_size, config.max_hidden_size + 1);
+                let layer = build_initial_layer(in_size, size, config.weight_initialisation, random_ctx);
+                self.nn.insert(p, layer);
+                self.activations.insert(p, config.hidden_activation);
+                if self.is_plastic()
+                {
+                    let rate = Control::new_learning_rate(config, random_ctx);
+                    let coefficients = self.nn[p].get_coefficients().len();
+                    self.learning_rates.insert(p, rate);
+                    self.deltas.insert(p, vec![0.0; coefficients]);
+                }
+                if self.is_recurrent()
+                {
+                    let layer = build_initial_layer(size, size, config.weight_initialisation, random_ctx);
+                    self.recurrent.insert(p, layer);
+                }
+                topology_changed = true;
+            }
+            else if hidden_layers > 1
             {
-                let mut weight_gen = MutateWeightsGenerator::new(random_ctx,  &self.nn[0],
-                                                                 old_in_size, self.hidden_layer_size,
-                                                                 new_in_size, new_hid_size);
-                self.nn[0] = FeedforwardLayer::new_from(new_in_size, new_hid_size, sigmoid(),
-                                                        || (weight_gen.generate()));
-                debug!("{}", self.nn[0].get_coefficients().len());
+                let p = random_ctx.gen_range(0, hidden_layers);
+                self.nn.remove(p);
+                self.activations.remove(p);
+                if self.is_plastic()
+                {
+                    self.learning_rates.remove(p);
+                    self.deltas.remove(p);
+                }
+                if self.is_recurrent()
+                {
+                    self.recurrent.remove(p);
+                }
+                topology_changed = true;
+            }
+        }
 
+        let mut hidden_sizes = self.get_hidden_sizes();
+        let delta_hl: i32 = random_ctx.gen_range(-2, 2);
+        if hidden_sizes.len() > 0
+        {
+            let h = if hidden_sizes.len() == 1 { 0 } else { random_ctx.gen_range(0, hidden_sizes.len()) };
+            hidden_sizes[h] = if (hidden_sizes[h] as i32 + delta_hl) >= 1
+            {
+                ((hidden_sizes[h] as i32) + delta_hl) as usize
             }
-            
-            if new_out_size != old_out_size
+            else
+            {
+                random_ctx.gen_range(1,3)
+            };
+        }
+
+        let new_in_size =  Sensor::get_total_cardinality(&new_sensor_list);
+        let new_out_size = new_actuator_list.len();
+
+        // Resize every layer that doesn't line up with its neighbours anymore
+        let depth = self.nn.len() - 1;
+        for k in 0..depth + 1
+        {
+            let layer_in = if k == 0 { new_in_size } else { hidden_sizes[k - 1] };
+            let layer_out = if k == depth { new_out_size } else { hidden_sizes[k] };
+
+            let old_in_size = self.nn[k].input_size();
+            let old_out_size = self.nn[k].output_size();
+
+            // Sensors can be swapped without the number of inputs changing
+            let mapping = if k == 0 { self.get_input_mapping(&new_sensor_list) } else { None };
+            let remapped = match mapping
+            {
+                Some(ref m) => { m.len() != old_in_size || m.iter().enumerate().any(|(i, o)| *o != Some(i)) },
+                None => { false }
+            };
+
+            if layer_in != old_in_size || layer_out != old_out_size || remapped    /* Brain Changed */
             {
-                let mut weight_gen = MutateWeightsGenerator::new(random_ctx,  &self.nn[1],
-                                                                 self.hidden_layer_size, old_out_size,
-                                                                 new_hid_size, new_out_size);
+                let layer =
+                {
+                    let mut weight_gen = match mapping
+                    {
+                        Some(ref m) =>
+                        {
+                            MutateWeightsGenerator::new_remapped(random_ctx, &self.nn[k], m, old_out_size, layer_out)
+                        },
+                        None =>
+                        {
+                            MutateWeightsGenerator::new(random_ctx, &self.nn[k],
+                                                        old_in_size, old_out_size,
+                                                        layer_in, layer_out)
+                        }
+                    };
+                    build_layer(layer_in, layer_out, &mut weight_gen)
+                };
+                self.nn[k] = layer;
+                debug!("{}", self.nn[k].get_coefficients().len());
+                topology_changed = true;
+            }
+        }
 
-                self.nn[1] = FeedforwardLayer::new_from(new_hid_size, new_out_size, sigmoid(),
-                                                        || (weight_gen.generate()));
-                debug!("{}", self.nn[1].get_coefficients().len());
+        // Recurrent layers follow the size of their hidden layer
+        if config.recurrent && !self.is_recurrent()
+        {
+            for k in 0..depth
+            {
+                let layer = build_initial_layer(hidden_sizes[k], hidden_sizes[k], config.weight_initialisation, random_ctx);
+                self.recurrent.push(layer);
+            }
+            topology_changed = true;
+        }
+        for k in 0..self.recurrent.len()
+        {
+            let old_size = self.recurrent[k].input_size();
+            if old_size != hidden_sizes[k]
+            {
+                let layer =
+                {
+                    let mut weight_gen = MutateWeightsGenerator::new(random_ctx, &self.recurrent[k],
+                                                                     old_size, old_size,
+                                                                     hidden_sizes[k], hidden_sizes[k]);
+                    build_layer(hidden_sizes[k], hidden_sizes[k], &mut weight_gen)
+                };
+                self.recurrent[k] = layer;
+                topology_changed = true;
             }
+        }
+
+        // Nudge how fast one of the layers learns
+        if self.is_plastic()
+        {
+            let k = random_ctx.gen_range(0, self.learning_rates.len());
+            self.learning_rates[k] *= random_ctx.gen_range(0.8, 1.25);
+        }
+        self.update_plasticity(config, random_ctx);
+
+        if topology_changed
+        {
+            self.hidden = hidden_sizes.iter().map(|s| vec![0.0; *s]).collect();
+        }
+        else if config.weight_mutation.fraction > 0.0  /* Structure of Brain unchanged, mutate a bit of everything */
+        {
+            for layer in self.nn.iter_mut().chain(self.recurrent.iter_mut())
+            {
+                let mut weights = layer.get_coefficients().clone();
+                config.weight_mutation.mutate_values(&mut weights, random_ctx);
+                layer.set_coefficients(weights);
 
-            self.hidden.resize(new_hid_size, 0.0);
-            self.hidden_layer_size = self.hidden.len();
+                let mut biases = layer.get_biases().clone();
+                config.weight_mutation.mutate_values(&mut biases, random_ctx);
+                layer.set_biases(biases);
+            }
         }
         else  /* Structure of Brain unchanged */
         {
             //
-            let layers = self.nn.len();
-            let layer_to_mutate = &mut self.nn[random_ctx.gen_range(0, layers)];
+            let nn_layers = self.nn.len();
+            let layers = nn_layers + self.recurrent.len();
+            let inx = random_ctx.gen_range(0, layers);
+            let layer_to_mutate = if inx < nn_layers
+            {
+                &mut self.nn[inx]
+            }
+            else
+            {
+                &mut self.recurrent[inx - nn_layers]
+            };
 
             if layer_to_mutate.get_coefficients().len() == 0
             {
@@ -306,7 +1859,7 @@ impl Control
             {
                 let mut weights = layer_to_mutate.get_coefficients().clone();
                 let inx = random_ctx.gen_range(0, weights.len());
-                weights[inx] = random_ctx.gen::<f32>();
+                weights[inx] = config.weight_mutation.mutate_value(weights[inx], random_ctx);
                 layer_to_mutate.set_coefficients(weights);
             }
             // Mutate a Bias
@@ -314,7 +1867,7 @@ impl Control
             {
                 let mut biases = layer_to_mutate.get_biases().clone();
                 let inx = random_ctx.gen_range(0, biases.len());
-                biases[inx] = random_ctx.gen::<f32>();
+                biases[inx] = config.weight_mutation.mutate_value(biases[inx], random_ctx);
                 layer_to_mutate.set_biases(biases);
             }
         }
@@ -329,27 +1882,154 @@ impl Serializable for Control
     fn serialize(&self, ctx: &mut SerializationCtx) -> Json
     {
        let mut json_obj = pmJsonObject::new();
-       if ctx.has_flag(PolyminiSerializationFlags::PM_SF_STATIC) 
+       if ctx.has_flag(PolyminiSerializationFlags::PM_SF_STATIC)
        {
            // Structure of the Neural Network
            json_obj.insert("Input".to_owned(),  self.inputs.len().to_json());
-           json_obj.insert("Hidden".to_owned(), self.hidden_layer_size.to_json());
+
+           if self.is_plastic()
+           {
+               json_obj.insert("LearningRates".to_owned(), self.learning_rates.to_json());
+               json_obj.insert("Inheritance".to_owned(), self.inheritance.serialize(ctx));
+           }
            json_obj.insert("Output".to_owned(), self.outputs.len().to_json());
-           
-           json_obj.insert("InToHidden".to_owned(),     self.nn[0].serialize(ctx));
-           json_obj.insert("HiddenToOutput".to_owned(), self.nn[1].serialize(ctx));
+
+           if let Some(ref network) = self.neat
+           {
+               json_obj.insert("Neat".to_owned(), network.serialize(ctx));
+           }
+           else if self.is_classic()
+           {
+               json_obj.insert("Hidden".to_owned(), self.nn[0].output_size().to_json());
+               json_obj.insert("InToHidden".to_owned(),     self.nn[0].serialize(ctx));
+               json_obj.insert("HiddenToOutput".to_owned(), self.nn[1].serialize(ctx));
+           }
+           else
+           {
+               json_obj.insert("HiddenLayers".to_owned(), self.get_hidden_sizes().to_json());
+               let mut layers_json = vec![];
+               for (k, layer) in self.nn.iter().enumerate()
+               {
+                   let mut layer_json = layer.serialize(ctx);
+                   if let Json::Object(ref mut layer_obj) = layer_json
+                   {
+                       layer_obj.insert("Activation".to_owned(), self.activations[k].serialize(ctx));
+                   }
+                   layers_json.push(layer_json);
+               }
+               json_obj.insert("Layers".to_owned(), Json::Array(layers_json));
+
+               if self.is_recurrent()
+               {
+                   json_obj.insert("Recurrent".to_owned(),
+                                   Json::Array(self.recurrent.iter().map(|l| l.serialize(ctx)).collect()));
+               }
+           }
        }
        if ctx.has_flag(PolyminiSerializationFlags::PM_SF_DYNAMIC)
        {
            // Values firing in the hidden and output layer each step
+           // (hidden layers are flattened one after the other)
+           let hidden: Vec<f32> = self.hidden.iter().flat_map(|h| h.iter().cloned()).collect();
            json_obj.insert("Inputs".to_owned(),  self.inputs.to_json());
-           json_obj.insert("Hidden".to_owned(),  self.hidden.to_json());
+           json_obj.insert("Hidden".to_owned(),  hidden.to_json());
            json_obj.insert("Outputs".to_owned(), self.outputs.to_json());
        }
        Json::Object(json_obj)
     }
 }
 
+// Anything that can drive a Polymini - Control (evolved NN) is the default, see scripted.rs
+// for hand written ones
+pub trait Controller : Serializable
+{
+    fn sense(&mut self, sensed: &SensoryPayload);
+    fn think(&mut self);
+    fn get_actions(&self) -> ActionList;
+
+    // Called before think with the simulation substep
+    fn set_step(&mut self, _: usize) {}
+
+    // 'other' might be a different kind of Controller
+    fn crossover(&self, other: &Controller, rand_ctx: &mut PolyminiRandomCtx, config: &ControlConfig,
+                 new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>) -> Box<Controller>;
+    fn mutate(&mut self, random_ctx: &mut PolyminiRandomCtx, config: &ControlConfig,
+              new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>);
+
+    // Called when the Polymini restarts (new scenario) and when it is reset (new generation)
+    fn reset_memory(&mut self) {}
+    fn end_of_life(&mut self) {}
+
+    // To get the concrete Controller back (e.g. for Control::start_trace)
+    fn as_any(&self) -> &Any;
+    fn as_any_mut(&mut self) -> &mut Any;
+}
+impl Controller for Control
+{
+    fn sense(&mut self, sensed: &SensoryPayload)
+    {
+        Control::sense(self, sensed)
+    }
+
+    fn think(&mut self)
+    {
+        Control::think(self)
+    }
+
+    fn set_step(&mut self, step: usize)
+    {
+        Control::set_step(self, step)
+    }
+
+    fn get_actions(&self) -> ActionList
+    {
+        Control::get_actions(self)
+    }
+
+    fn crossover(&self, other: &Controller, rand_ctx: &mut PolyminiRandomCtx, config: &ControlConfig,
+                 new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>) -> Box<Controller>
+    {
+        match other.as_any().downcast_ref::<Control>()
+        {
+            Some(other_control) =>
+            {
+                Box::new(Control::crossover(self, other_control, rand_ctx, config, new_sensor_list, new_actuator_list))
+            },
+            None =>
+            {
+                // Nothing to take from the other parent, all the genes come from this one
+                warn!("Crossing a Control with a different kind of Controller, only the Control is used");
+                Box::new(Control::crossover(self, self, rand_ctx, config, new_sensor_list, new_actuator_list))
+            }
+        }
+    }
+
+    fn mutate(&mut self, random_ctx: &mut PolyminiRandomCtx, config: &ControlConfig,
+              new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>)
+    {
+        Control::mutate(self, random_ctx, config, new_sensor_list, new_actuator_list)
+    }
+
+    fn reset_memory(&mut self)
+    {
+        Control::reset_memory(self)
+    }
+
+    fn end_of_life(&mut self)
+    {
+        Control::end_of_life(self)
+    }
+
+    fn as_any(&self) -> &Any
+    {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any
+    {
+        self
+    }
+}
 
 impl Serializable for NNLayer
 {
@@ -415,8 +2095,143 @@ impl Deserializable for NNLayer
     }
 }
 
+// Layer sizes (in, out) and activations, every Control in a ControlBatch has the same
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ControlTopology
+{
+    sizes: Vec<(usize, usize)>,
+    activations: Vec<Activation>,
+}
+
+// Weights of many Controls with the same topology packed one after the other, so a whole
+// generation thinks in a single pass over contiguous memory instead of one Control at a time
+//
+// The weights are copied when the batch is built, it has to be rebuilt if they change
+pub struct ControlBatch
+{
+    topology: ControlTopology,
+    members: usize,
+    // Per layer - members x (in x out) and members x out
+    weights: Vec<Vec<f32>>,
+    biases: Vec<Vec<f32>>,
+}
+
+// What a ControlBatch thought, per layer - members x out
+pub struct ControlBatchOutput
+{
+    values: Vec<Vec<f32>>,
+}
+
+impl ControlBatch
+{
+    pub fn new(topology: ControlTopology, controls: &Vec<&Control>) -> ControlBatch
+    {
+        let mut weights = vec![];
+        let mut biases = vec![];
+        for (k, &(in_size, out_size)) in topology.sizes.iter().enumerate()
+        {
+            let mut w = Vec::with_capacity(controls.len() * in_size * out_size);
+            let mut b = Vec::with_capacity(controls.len() * out_size);
+            for c in controls.iter()
+            {
+                assert_eq!(c.nn[k].input_size(), in_size);
+                assert_eq!(c.nn[k].output_size(), out_size);
+                w.extend(c.nn[k].get_coefficients().iter().cloned());
+                b.extend(c.nn[k].get_biases().iter().cloned());
+            }
+            weights.push(w);
+            biases.push(b);
+        }
+        ControlBatch { topology: topology, members: controls.len(), weights: weights, biases: biases }
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.members
+    }
+
+    pub fn get_topology(&self) -> &ControlTopology
+    {
+        &self.topology
+    }
+
+    // 'controls' must be the ones the batch was built from, in the same order - only their
+    // inputs are read, ControlBatchOutput::apply writes the result back
+    pub fn think(&self, controls: &Vec<&Control>) -> ControlBatchOutput
+    {
+        assert_eq!(controls.len(), self.members);
+        let in_size = self.topology.sizes[0].0;
+
+        // Same scaling Control::think does
+        let mut values = Vec::with_capacity(self.members * in_size);
+        for c in controls.iter()
+        {
+            for i in 0..in_size
+            {
+                values.push((*c.inputs.get(i).unwrap_or(&NEUTRAL_INPUT) - 0.5) * 10.0);
+            }
+        }
+
+        let mut output = ControlBatchOutput { values: vec![] };
+        for (k, &(in_size, out_size)) in self.topology.sizes.iter().enumerate()
+        {
+            let activation = self.topology.activations[k];
+            let mut next = self.biases[k].clone();
+            {
+                let ins = if k == 0 { &values } else { &output.values[k - 1] };
+                for m in 0..self.members
+                {
+                    let x = &ins[m * in_size..(m + 1) * in_size];
+                    let w = &self.weights[k][m * in_size * out_size..(m + 1) * in_size * out_size];
+                    let y = &mut next[m * out_size..(m + 1) * out_size];
+                    for i in 0..in_size
+                    {
+                        let xi = x[i];
+                        let row = &w[i * out_size..(i + 1) * out_size];
+                        for j in 0..out_size
+                        {
+                            y[j] += xi * row[j];
+                        }
+                    }
+                }
+            }
+            for v in next.iter_mut()
+            {
+                *v = activation.apply(*v);
+            }
+            output.values.push(next);
+        }
+        output
+    }
+}
+
+impl ControlBatchOutput
+{
+    // Leave 'control' as if it had thought on its own, 'member' is its place in the batch
+    pub fn apply(&self, member: usize, control: &mut Control)
+    {
+        let last = self.values.len() - 1;
+        control.hidden.clear();
+        for k in 0..last
+        {
+            let size = control.nn[k].output_size();
+            control.hidden.push(self.values[k][member * size..(member + 1) * size].to_vec());
+        }
+
+        let out_size = control.nn[last].output_size();
+        let out_activation = control.activations[last];
+        control.outputs = self.values[last][member * out_size..(member + 1) * out_size].iter()
+                                                                                     .map(|&v| { out_activation.center(v) })
+                                                                                     .collect();
+
+        if let Some(ref mut trace) = control.trace
+        {
+            trace.record(&control.inputs, &control.hidden, &control.outputs);
+        }
+    }
+}
+
 
-
 pub trait WeightsGenerator
 {
     fn generate(&mut self) -> f32;
@@ -444,6 +2259,98 @@ impl<'a> WeightsGenerator for RandomWeightsGenerator<'a>
     }
 }
 
+//
+//
+pub struct SymmetricUniformWeightsGenerator<'a>
+{
+    rand_ctx: &'a mut PolyminiRandomCtx,
+    range: f32,
+}
+impl<'a> SymmetricUniformWeightsGenerator<'a>
+{
+    pub fn new(ctx: &'a mut PolyminiRandomCtx, range: f32) -> SymmetricUniformWeightsGenerator
+    {
+        SymmetricUniformWeightsGenerator { rand_ctx: ctx, range: range.abs() }
+    }
+}
+impl<'a> WeightsGenerator for SymmetricUniformWeightsGenerator<'a>
+{
+    fn generate(&mut self) -> f32
+    {
+        if self.range == 0.0
+        {
+            return 0.0
+        }
+        self.rand_ctx.gen_range(-self.range, self.range)
+    }
+}
+
+//
+//
+pub struct GaussianWeightsGenerator<'a>
+{
+    rand_ctx: &'a mut PolyminiRandomCtx,
+    sigma: f32,
+}
+impl<'a> GaussianWeightsGenerator<'a>
+{
+    pub fn new(ctx: &'a mut PolyminiRandomCtx, sigma: f32) -> GaussianWeightsGenerator
+    {
+        GaussianWeightsGenerator { rand_ctx: ctx, sigma: sigma.abs() }
+    }
+}
+impl<'a> WeightsGenerator for GaussianWeightsGenerator<'a>
+{
+    fn generate(&mut self) -> f32
+    {
+        gen_gaussian(self.rand_ctx, 0.0, self.sigma)
+    }
+}
+
+// Glorot & Bengio - Keeps the variance of the values the same going forwards and backwards
+//
+pub struct XavierWeightsGenerator<'a>
+{
+    internal_generator: SymmetricUniformWeightsGenerator<'a>,
+}
+impl<'a> XavierWeightsGenerator<'a>
+{
+    pub fn new(ctx: &'a mut PolyminiRandomCtx, in_size: usize, out_size: usize) -> XavierWeightsGenerator
+    {
+        let limit = (6.0 / max(in_size + out_size, 1) as f32).sqrt();
+        XavierWeightsGenerator { internal_generator: SymmetricUniformWeightsGenerator::new(ctx, limit) }
+    }
+}
+impl<'a> WeightsGenerator for XavierWeightsGenerator<'a>
+{
+    fn generate(&mut self) -> f32
+    {
+        self.internal_generator.generate()
+    }
+}
+
+// He et al. - Same idea as Xavier but for layers whose activation cuts half the values (ReLU)
+//
+pub struct HeWeightsGenerator<'a>
+{
+    internal_generator: GaussianWeightsGenerator<'a>,
+}
+impl<'a> HeWeightsGenerator<'a>
+{
+    pub fn new(ctx: &'a mut PolyminiRandomCtx, in_size: usize) -> HeWeightsGenerator
+    {
+        let sigma = (2.0 / max(in_size, 1) as f32).sqrt();
+        HeWeightsGenerator { internal_generator: GaussianWeightsGenerator::new(ctx, sigma) }
+    }
+}
+impl<'a> WeightsGenerator for HeWeightsGenerator<'a>
+{
+    fn generate(&mut self) -> f32
+    {
+        self.internal_generator.generate()
+    }
+}
+
 //
 //
 pub struct MutateWeightsGenerator<'a>
@@ -462,6 +2369,14 @@ impl<'a> MutateWeightsGenerator<'a>
         let muts = ctx.gen_range(2, 5);
         MutateWeightsGenerator { rand_ctx: ctx, mutations: muts,  internal_generator: uwg }
     }
+
+    pub fn new_remapped(ctx: &'a mut PolyminiRandomCtx, l1: &NNLayer, input_mapping: &Vec<Option<usize>>,
+                        old_out_size: usize, new_out_size: usize) -> MutateWeightsGenerator<'a>
+    {
+        let uwg = UpdateWeightsGenerator::new_remapped(ctx, l1, input_mapping, old_out_size, new_out_size);
+        let muts = ctx.gen_range(2, 5);
+        MutateWeightsGenerator { rand_ctx: ctx, mutations: muts,  internal_generator: uwg }
+    }
 }
 impl<'a> WeightsGenerator for MutateWeightsGenerator<'a>
 {
@@ -529,6 +2444,45 @@ impl UpdateWeightsGenerator
         UpdateWeightsGenerator { has_mutated: false, weights_generated: 0, max_weights: new_in_size*new_out_size + new_out_size,
                                  weight_values: w_values, bias_values: b_values }
     }
+
+    // Same as new, but input i of the new layer takes the weights of input input_mapping[i]
+    // of the old one (or random ones if it didn't exist)
+    pub fn new_remapped(ctx: &mut PolyminiRandomCtx, l1: &NNLayer, input_mapping: &Vec<Option<usize>>,
+                        old_out_size: usize, new_out_size: usize) -> UpdateWeightsGenerator
+    {
+        let mut w_values = vec![];
+        let new_in_size = input_mapping.len();
+
+        debug!("Update Generator (Remapped) - {:?} {} {}", input_mapping, old_out_size, new_out_size);
+        for i in 0..new_in_size
+        {
+            for j in 0..new_out_size
+            {
+                let v = match input_mapping[i]
+                {
+                    Some(old_i) if j < old_out_size && old_i * old_out_size + j < l1.get_coefficients().len() =>
+                    {
+                        l1.get_coefficients()[old_i * old_out_size + j]
+                    },
+                    _ =>
+                    {
+                        ctx.gen_range(-0.5, 0.5)
+                    }
+                };
+                w_values.push(v);
+            }
+        }
+
+        let mut b_values = l1.get_biases().clone();
+        b_values.truncate(new_out_size);
+        while b_values.len() < new_out_size
+        {
+            b_values.push(ctx.gen_range(-0.5, 0.5));
+        }
+
+        UpdateWeightsGenerator { has_mutated: false, weights_generated: 0, max_weights: new_in_size*new_out_size + new_out_size,
+                                 weight_values: w_values, bias_values: b_values }
+    }
 }
 impl WeightsGenerator for UpdateWeightsGenerator
 {
@@ -625,6 +2579,60 @@ impl CrossoverWeightsGenerator
         CrossoverWeightsGenerator { weights_generated: 0, max_weights: new_in_size*new_out_size + new_out_size, weight_values: w_values, bias_values: b_values }
 
     }
+
+    // Input layers, each parent's weights are looked up through its own input mapping so
+    // they stay with the sensor they were learned for
+    fn new_remapped<'a>(rand_ctx: &'a mut PolyminiRandomCtx, l1: &NNLayer, l2: &NNLayer,
+                        mapping_1: &Vec<Option<usize>>, mapping_2: &Vec<Option<usize>>,
+                        new_out_size: usize) -> CrossoverWeightsGenerator
+    {
+        let mut w_values = vec![];
+        let mut b_values = vec![];
+        let new_in_size = min(mapping_1.len(), mapping_2.len());
+        let out_1 = l1.output_size();
+        let out_2 = l2.output_size();
+
+        debug!("Crossover Generator (Remapped) - {} {}", new_in_size, new_out_size);
+        for i in 0..new_in_size
+        {
+            for j in 0..new_out_size
+            {
+                let v = match (mapping_1[i], mapping_2[i])
+                {
+                    (Some(old_i), _) if j < out_1 && old_i * out_1 + j < l1.get_coefficients().len() =>
+                    {
+                        l1.get_coefficients()[old_i * out_1 + j]
+                    },
+                    (_, Some(old_i)) if j < out_2 && old_i * out_2 + j < l2.get_coefficients().len() =>
+                    {
+                        l2.get_coefficients()[old_i * out_2 + j]
+                    },
+                    _ =>
+                    {
+                        rand_ctx.gen_range(-0.5, 0.5)
+                    }
+                };
+                w_values.push(v);
+            }
+        }
+        for o in 0..new_out_size
+        {
+            if o < l1.get_biases().len()
+            {
+                b_values.push(l1.get_biases()[o]);
+            }
+            else if o < l2.get_biases().len()
+            {
+                b_values.push(l2.get_biases()[o]);
+            }
+            else
+            {
+                b_values.push(rand_ctx.gen_range(-0.5, 0.5));
+            }
+        }
+
+        CrossoverWeightsGenerator { weights_generated: 0, max_weights: new_in_size*new_out_size + new_out_size, weight_values: w_values, bias_values: b_values }
+    }
 }
 
 impl WeightsGenerator for CrossoverWeightsGenerator
@@ -799,7 +2807,7 @@ mod test
         };
 
         let mut c1 = Control::new_from(s_list.clone(), a_list.clone(), 5, &mut in_to_hid_generator, &mut hid_to_out_generator);
-        c1.mutate(&mut PolyminiRandomCtx::new_unseeded("Mutate Tests".to_string()), s_list.clone(), a_list.clone());
+        c1.mutate(&mut PolyminiRandomCtx::new_unseeded("Mutate Tests".to_string()), &ControlConfig::defaults(), s_list.clone(), a_list.clone());
 
         for c in c1.nn[0].get_coefficients()
         {
@@ -853,7 +2861,7 @@ mod test
 
         let mut c1 = Control::new_from(s_list.clone(), a_list.clone(), 5, &mut in_to_hid_generator, &mut hid_to_out_generator);
         s_list.push(Sensor::new(SensorTag::PositionX, 4));
-        c1.mutate(&mut PolyminiRandomCtx::new_unseeded("Mutate Tests".to_string()), s_list.clone(), a_list.clone());
+        c1.mutate(&mut PolyminiRandomCtx::new_unseeded("Mutate Tests".to_string()), &ControlConfig::defaults(), s_list.clone(), a_list.clone());
 
         for c in c1.nn[0].get_coefficients()
         {
@@ -907,7 +2915,7 @@ mod test
 
         let mut c1 = Control::new_from(s_list.clone(), a_list.clone(), 5, &mut in_to_hid_generator, &mut hid_to_out_generator);
         a_list.push(Actuator::new(ActuatorTag::MoveVertical, 3, (0,0)));
-        c1.mutate(&mut PolyminiRandomCtx::new_unseeded("Mutate Tests".to_string()), s_list.clone(), a_list.clone());
+        c1.mutate(&mut PolyminiRandomCtx::new_unseeded("Mutate Tests".to_string()), &ControlConfig::defaults(), s_list.clone(), a_list.clone());
 
         for c in c1.nn[0].get_coefficients()
         {
@@ -968,4 +2976,530 @@ mod test
 
         assert_eq!(json.pretty().to_string(), json_2.pretty().to_string());
     }
+
+    fn deep_config() -> ControlConfig
+    {
+        ControlConfig { kind: ControlKind::Layered, hidden_layers: 3, max_hidden_layers: 4, min_hidden_size: 2, max_hidden_size: 5,
+                        hidden_activation: Activation::Tanh, output_activation: Activation::Identity,
+                        topology_mutation: 1.0, recurrent: false,
+                        plasticity: false, learning_rate: 0.01, inheritance: Inheritance::Darwinian,
+                        weight_initialisation: WeightInitialisation::Uniform, weight_mutation: WeightMutation::defaults(),
+                        add_node_mutation: 0.0, add_connection_mutation: 0.0 }
+    }
+
+    fn think_with(c: &mut Control)
+    {
+        let mut payload = SensoryPayload::new();
+        payload.insert(SensorTag::PositionX, vec![0.75]);
+        c.sense(&payload);
+        c.think();
+    }
+
+    #[test]
+    fn test_control_deep_topology()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let config = deep_config();
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config,
+                                              &mut PolyminiRandomCtx::new_unseeded("Deep Tests".to_string()));
+        assert_eq!(c1.get_hidden_sizes().len(), 3);
+        for s in c1.get_hidden_sizes()
+        {
+            assert!(s >= config.min_hidden_size && s <= config.max_hidden_size);
+        }
+        assert_eq!(c1.get_activations(), &vec![Activation::Tanh, Activation::Tanh, Activation::Tanh, Activation::Identity]);
+
+        think_with(&mut c1);
+        assert_eq!(c1.get_actions().len(), 2);
+
+        let mut ctx = SerializationCtx::new_from_flags(PolyminiSerializationFlags::PM_SF_STATIC);
+        let json = c1.serialize(&mut ctx);
+        let c2 = Control::new_from_json(&json, s_list.clone(), a_list.clone()).unwrap();
+        assert_eq!(c2.get_activations(), c1.get_activations());
+        assert_eq!(json.pretty().to_string(), c2.serialize(&mut ctx).pretty().to_string());
+    }
+
+    #[test]
+    fn test_control_crossover_depths()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let config = deep_config();
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Deep Crossover Tests".to_string());
+
+        let shallow = Control::new_from_config(s_list.clone(), a_list.clone(), &ControlConfig::defaults(), &mut ctx);
+        let deep = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+
+        for _ in 0..20
+        {
+            let mut child = shallow.crossover(&deep, &mut ctx, &config, s_list.clone(), a_list.clone());
+            let depth = child.get_hidden_sizes().len();
+            assert!(depth >= 1 && depth <= 3);
+            think_with(&mut child);
+            assert_eq!(child.get_actions().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_control_crossover_hidden_size_range()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut config = deep_config();
+        config.min_hidden_size = 4;
+        config.max_hidden_size = 6;
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Hidden Size Crossover Tests".to_string());
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        let c2 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        for _ in 0..30
+        {
+            c1 = c1.crossover(&c2, &mut ctx, &config, s_list.clone(), a_list.clone());
+            for s in c1.get_hidden_sizes()
+            {
+                assert!(s >= config.min_hidden_size && s <= config.max_hidden_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_control_mutate_topology()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let config = deep_config();
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Topology Mutation Tests".to_string());
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        for _ in 0..20
+        {
+            c1.mutate(&mut ctx, &config, s_list.clone(), a_list.clone());
+            let depth = c1.get_hidden_sizes().len();
+            assert!(depth >= 1 && depth <= config.max_hidden_layers);
+            think_with(&mut c1);
+            assert_eq!(c1.get_actions().len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_control_recurrent_memory()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut config = deep_config();
+        config.recurrent = true;
+        config.topology_mutation = 0.0;
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Recurrent Tests".to_string());
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        assert!(c1.is_recurrent());
+
+        // Same inputs, but the second step remembers the first one
+        think_with(&mut c1);
+        let first = c1.outputs.clone();
+        think_with(&mut c1);
+        assert!(first != c1.outputs);
+
+        c1.reset_memory();
+        think_with(&mut c1);
+        assert_eq!(first, c1.outputs);
+
+        let mut ser_ctx = SerializationCtx::new_from_flags(PolyminiSerializationFlags::PM_SF_STATIC);
+        let json = c1.serialize(&mut ser_ctx);
+        let c2 = Control::new_from_json(&json, s_list.clone(), a_list.clone()).unwrap();
+        assert!(c2.is_recurrent());
+        assert_eq!(json.pretty().to_string(), c2.serialize(&mut ser_ctx).pretty().to_string());
+
+        // Memory is inherited even from a single parent
+        let plain = Control::new_from_config(s_list.clone(), a_list.clone(), &ControlConfig::defaults(), &mut ctx);
+        let mut child = plain.crossover(&c1, &mut ctx, &ControlConfig::defaults(), s_list.clone(), a_list.clone());
+        assert!(child.is_recurrent());
+        child.mutate(&mut ctx, &config, s_list.clone(), a_list.clone());
+        think_with(&mut child);
+        assert_eq!(child.get_actions().len(), 2);
+    }
+
+    #[test]
+    fn test_control_neat()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut config = ControlConfig::defaults();
+        config.kind = ControlKind::Neat;
+        config.add_node_mutation = 0.5;
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Neat Control Tests".to_string());
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        let c2 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        assert!(c1.is_neat());
+        c1.mutate(&mut ctx, &config, s_list.clone(), a_list.clone());
+        think_with(&mut c1);
+        assert_eq!(c1.get_actions().len(), 2);
+
+        let mut child = c1.crossover(&c2, &mut ctx, &config, s_list.clone(), a_list.clone());
+        assert!(child.is_neat());
+        think_with(&mut child);
+        assert_eq!(child.get_actions().len(), 2);
+
+        let mut ser_ctx = SerializationCtx::new_from_flags(PolyminiSerializationFlags::PM_SF_STATIC);
+        let json = child.serialize(&mut ser_ctx);
+        let copy = Control::new_from_json(&json, s_list.clone(), a_list.clone()).unwrap();
+        assert!(copy.is_neat());
+        assert_eq!(json.pretty().to_string(), copy.serialize(&mut ser_ctx).pretty().to_string());
+    }
+
+    #[test]
+    fn test_control_plasticity()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut config = deep_config();
+        config.topology_mutation = 0.0;
+        config.plasticity = true;
+        config.learning_rate = 0.5;
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Plasticity Tests".to_string());
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        assert!(c1.is_plastic());
+        assert_eq!(c1.get_learning_rates().len(), c1.nn.len());
+        let genes: Vec<Vec<f32>> = c1.nn.iter().map(|l| l.get_coefficients().clone()).collect();
+
+        // Same inputs, but the network keeps learning from them
+        think_with(&mut c1);
+        let first = c1.outputs.clone();
+        think_with(&mut c1);
+        assert!(first != c1.outputs);
+        assert!(c1.deltas.iter().any(|d| d.iter().any(|&v| v != 0.0)));
+
+        // Darwinian, what was learned dies with the individual
+        let mut c2 = c1.baked();
+        c1.end_of_life();
+        let after: Vec<Vec<f32>> = c1.nn.iter().map(|l| l.get_coefficients().clone()).collect();
+        assert_eq!(genes, after);
+        assert!(c1.deltas.iter().all(|d| d.iter().all(|&v| v == 0.0)));
+
+        // Lamarckian, it gets written into the weights
+        think_with(&mut c1);
+        think_with(&mut c1);
+        c1.inheritance = Inheritance::Lamarckian;
+        c1.end_of_life();
+        let after: Vec<Vec<f32>> = c1.nn.iter().map(|l| l.get_coefficients().clone()).collect();
+        assert!(genes != after);
+
+        c2.mutate(&mut ctx, &config, s_list.clone(), a_list.clone());
+        let mut child = c1.crossover(&c2, &mut ctx, &config, s_list.clone(), a_list.clone());
+        assert!(child.is_plastic());
+        think_with(&mut child);
+        assert_eq!(child.get_actions().len(), 2);
+
+        let mut ser_ctx = SerializationCtx::new_from_flags(PolyminiSerializationFlags::PM_SF_STATIC);
+        let json = child.serialize(&mut ser_ctx);
+        let copy = Control::new_from_json(&json, s_list.clone(), a_list.clone()).unwrap();
+        assert!(copy.is_plastic());
+        assert_eq!(json.pretty().to_string(), copy.serialize(&mut ser_ctx).pretty().to_string());
+    }
+
+    #[test]
+    fn test_control_plasticity_mutate_topology()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut config = deep_config();
+        config.plasticity = true;
+        config.learning_rate = 0.5;
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Plasticity Topology Tests".to_string());
+
+        // Mutation nudges a rate by 1.25 at most, these are far enough apart to tell them apart
+        let same = |a: f32, b: f32| { a / b >= 0.79 && a / b <= 1.26 };
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        for _ in 0..20
+        {
+            for k in 0..c1.learning_rates.len()
+            {
+                c1.learning_rates[k] = 100.0f32.powi(k as i32 + 1);
+            }
+            let before = c1.learning_rates.clone();
+            c1.mutate(&mut ctx, &config, s_list.clone(), a_list.clone());
+            let after = c1.learning_rates.clone();
+
+            assert_eq!(after.len(), c1.nn.len());
+            assert_eq!(c1.deltas.len(), c1.nn.len());
+            for (d, l) in c1.deltas.iter().zip(c1.nn.iter())
+            {
+                assert_eq!(d.len(), l.get_coefficients().len());
+            }
+
+            // Taking the added (or removed) layer out, every other layer has its own rate
+            let (longer, shorter) = if after.len() >= before.len() { (&after, &before) } else { (&before, &after) };
+            let lines_up = |skip: Option<usize>|
+            {
+                let rest: Vec<f32> = longer.iter().enumerate().filter(|&(k, _)| Some(k) != skip).map(|(_, r)| *r).collect();
+                rest.len() == shorter.len() && rest.iter().zip(shorter.iter()).all(|(a, b)| same(*a, *b))
+            };
+            if longer.len() == shorter.len()
+            {
+                assert!(lines_up(None), "{:?} -> {:?}", before, after);
+            }
+            else
+            {
+                assert!((0..longer.len()).any(|p| lines_up(Some(p))), "{:?} -> {:?}", before, after);
+            }
+        }
+    }
+
+    #[test]
+    fn test_weight_initialisation()
+    {
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Initialisation Tests".to_string());
+
+        let limit = (6.0f32 / 12.0).sqrt();
+        let values: Vec<f32> = { let mut g = XavierWeightsGenerator::new(&mut ctx, 4, 8); (0..200).map(|_| g.generate()).collect() };
+        assert!(values.iter().all(|&v| v >= -limit && v < limit));
+        assert!(values.iter().any(|&v| v < 0.0));
+
+        let values: Vec<f32> = { let mut g = SymmetricUniformWeightsGenerator::new(&mut ctx, 2.0); (0..200).map(|_| g.generate()).collect() };
+        assert!(values.iter().all(|&v| v >= -2.0 && v < 2.0));
+        assert!(values.iter().any(|&v| v < -0.5));
+
+        let values: Vec<f32> = { let mut g = GaussianWeightsGenerator::new(&mut ctx, 0.1); (0..1000).map(|_| g.generate()).collect() };
+        let mean = values.iter().fold(0.0, |acc, v| acc + v) / values.len() as f32;
+        assert!(mean.abs() < 0.05);
+        assert!(values.iter().any(|&v| v < 0.0));
+
+        let values: Vec<f32> = { let mut g = HeWeightsGenerator::new(&mut ctx, 8); (0..200).map(|_| g.generate()).collect() };
+        assert!(values.iter().any(|&v| v < 0.0));
+
+        // Config picks the generator and survives the JSON round trip
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut config = deep_config();
+        config.weight_initialisation = WeightInitialisation::SymmetricUniform { range: 3.0 };
+        let c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        assert!(c1.nn.iter().all(|l| l.get_coefficients().iter().all(|&v| v.abs() <= 3.0)));
+        assert!(c1.nn.iter().any(|l| l.get_coefficients().iter().any(|&v| v.abs() > 0.5)));
+
+        let mut ser_ctx = SerializationCtx::new();
+        let json = config.serialize(&mut ser_ctx);
+        let config_prime = ControlConfig::new_from_json(&json, &mut ser_ctx).unwrap();
+        assert_eq!(config_prime.weight_initialisation, config.weight_initialisation);
+
+        assert_eq!(WeightInitialisation::new_from_json(&"Xavier".to_json(), &mut ser_ctx), Some(WeightInitialisation::Xavier));
+    }
+
+    #[test]
+    fn test_weight_mutation()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Weight Mutation Tests".to_string());
+        let mut config = deep_config();
+        config.topology_mutation = 0.0;
+        config.weight_mutation = WeightMutation { mode: WeightMutationMode::Perturb, sigma: 0.05, fraction: 0.5, clamp: Some(0.6) };
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx);
+        let before: Vec<f32> = c1.nn.iter().flat_map(|l| l.get_coefficients().iter().cloned()).collect();
+        c1.mutate(&mut ctx, &config, s_list.clone(), a_list.clone());
+        let after: Vec<f32> = c1.nn.iter().flat_map(|l| l.get_coefficients().iter().cloned()).collect();
+
+        // Weights are nudged, not replaced
+        assert_eq!(before.len(), after.len());
+        let changed = before.iter().zip(after.iter()).filter(|&(b, a)| b != a).count();
+        assert!(changed > 1);
+        assert!(before.iter().zip(after.iter()).all(|(b, a)| (b - a).abs() < 0.5));
+        assert!(after.iter().all(|&v| v.abs() <= 0.6));
+
+        // A single value, clamped
+        let wm = WeightMutation { mode: WeightMutationMode::Perturb, sigma: 10.0, fraction: 0.0, clamp: Some(1.0) };
+        for _ in 0..20
+        {
+            assert!(wm.mutate_value(0.0, &mut ctx).abs() <= 1.0);
+        }
+
+        let mut ser_ctx = SerializationCtx::new();
+        let json = config.serialize(&mut ser_ctx);
+        let config_prime = ControlConfig::new_from_json(&json, &mut ser_ctx).unwrap();
+        assert_eq!(config_prime.weight_mutation, config.weight_mutation);
+    }
+
+    // Thinks with 6 Controls one by one and as a ControlBatch and checks both agree
+    fn check_batch_matches_singles(config: &ControlConfig)
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Batch Tests".to_string());
+
+        let mut singles = vec![];
+        let mut batched = vec![];
+        for i in 0..6
+        {
+            let mut c = Control::new_from_config(s_list.clone(), a_list.clone(), config, &mut ctx);
+            let mut payload = SensoryPayload::new();
+            payload.insert(SensorTag::PositionX, vec![i as f32 / 6.0]);
+            payload.insert(SensorTag::PositionY, vec![1.0 - i as f32 / 6.0]);
+            let mut copy = c.baked();
+            copy.sense(&payload);
+            batched.push(copy);
+            c.sense(&payload);
+            c.think();
+            singles.push(c);
+        }
+
+        let topology = batched[0].get_topology().unwrap();
+        assert!(batched.iter().all(|c| c.get_topology() == Some(topology.clone())));
+
+        let output =
+        {
+            let controls = batched.iter().collect();
+            let batch = ControlBatch::new(topology, &controls);
+            assert_eq!(batch.len(), 6);
+            batch.think(&controls)
+        };
+        for (m, c) in batched.iter_mut().enumerate()
+        {
+            output.apply(m, c);
+        }
+
+        for (single, batch) in singles.iter().zip(batched.iter())
+        {
+            assert_eq!(single.hidden.len(), batch.hidden.len());
+            for (h_a, h_b) in single.hidden.iter().zip(batch.hidden.iter())
+            {
+                assert_eq!(h_a.len(), h_b.len());
+                for (a, b) in h_a.iter().zip(h_b.iter())
+                {
+                    assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+                }
+            }
+            assert_eq!(single.outputs.len(), batch.outputs.len());
+            for (a, b) in single.outputs.iter().zip(batch.outputs.iter())
+            {
+                assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_control_batch()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Batch Tests".to_string());
+        let mut config = deep_config();
+        config.min_hidden_size = 4;
+        config.max_hidden_size = 4;
+        check_batch_matches_singles(&config);
+
+        // Memory can't be batched
+        config.recurrent = true;
+        assert!(Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx).get_topology().is_none());
+    }
+
+    #[test]
+    fn test_control_batch_sigmoid()
+    {
+        // Control::think goes through tinnmann for Sigmoid layers, the batch doesn't, so this
+        // also checks the batch reads the coefficients with the same (in x out) layout
+        let mut config = ControlConfig::defaults();
+        config.min_hidden_size = 5;
+        config.max_hidden_size = 5;
+        assert_eq!(config.hidden_activation, Activation::Sigmoid);
+        check_batch_matches_singles(&config);
+    }
+
+    #[test]
+    fn test_control_trace()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionX, 1)];
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Trace Tests".to_string());
+
+        let mut c1 = Control::new_from_config(s_list.clone(), a_list.clone(), &deep_config(), &mut ctx);
+        think_with(&mut c1);
+        assert!(c1.get_trace().is_none());
+
+        c1.start_trace();
+        c1.set_step(4);
+        think_with(&mut c1);
+        c1.set_step(5);
+        think_with(&mut c1);
+        assert_eq!(c1.get_trace().unwrap().len(), 2);
+
+        let trace = c1.stop_trace().unwrap();
+        think_with(&mut c1);
+        assert!(c1.get_trace().is_none());
+
+        let hidden_total = c1.get_hidden_sizes().iter().fold(0, |acc, s| acc + s);
+        let step = &trace.get_steps()[1];
+        assert_eq!(step.step, 5);
+        assert_eq!(step.inputs.len(), 2);
+        assert_eq!(step.hidden.len(), hidden_total);
+        assert_eq!(step.outputs.len(), 2);
+
+        // Header plus one row per step, all with the same number of columns
+        let csv = trace.to_csv();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("Step,In_PositionX_0_0,In_PositionX_1_0"));
+        for row in rows.iter()
+        {
+            assert_eq!(row.split(',').count(), 1 + 2 + hidden_total + 2);
+        }
+
+        let json = trace.serialize(&mut SerializationCtx::new());
+        assert_eq!(json.find("Steps").unwrap().as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_control_multi_cardinality()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::TemperatureGradient, 1),
+                           Sensor::new(SensorTag::PositionY, 2)];
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Cardinality Tests".to_string());
+
+        let mut c1 = Control::new_from_random_ctx(s_list.clone(), a_list.clone(), 3, &mut ctx);
+        assert_eq!(c1.nn[0].input_size(), 6);
+
+        // Short payloads and missing sensors are padded so every input keeps its place
+        let mut payload = SensoryPayload::new();
+        payload.insert(SensorTag::PositionX, vec![0.1]);
+        payload.insert(SensorTag::TemperatureGradient, vec![0.2, 0.3]);
+        c1.sense(&payload);
+        assert_eq!(c1.inputs, vec![0.1, 0.2, 0.3, NEUTRAL_INPUT, NEUTRAL_INPUT, NEUTRAL_INPUT]);
+        c1.think();
+    }
+
+    #[test]
+    fn test_control_input_remapping()
+    {
+        let old_s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::TemperatureGradient, 1)];
+        let new_s_list = vec![ Sensor::new(SensorTag::TemperatureGradient, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Remapping Tests".to_string());
+
+        let mapping = Sensor::get_input_mapping(&old_s_list, &new_s_list);
+        assert_eq!(mapping, vec![Some(1), Some(2), Some(3), Some(4), None]);
+
+        let mut w_values = vec![];
+        for i in 0..5
+        {
+            for j in 0..2
+            {
+                w_values.push(i as f32 + (j as f32 / 10.0));
+            }
+        }
+        let l1: NNLayer = FeedforwardLayer::new_from_values(5, 2, sigmoid(), w_values, vec![8.1, 8.2]);
+
+        // The gradient weights move up one row, the new sensor gets new ones
+        let mut generator = UpdateWeightsGenerator::new_remapped(&mut ctx, &l1, &mapping, 2, 2);
+        let remapped = build_layer(5, 2, &mut generator);
+        assert_eq!(remapped.get_coefficients()[0..8].to_vec(), l1.get_coefficients()[2..10].to_vec());
+        for w in &remapped.get_coefficients()[8..10]
+        {
+            assert!(*w >= -0.5 && *w <= 0.5);
+        }
+        assert_eq!(remapped.get_biases(), l1.get_biases());
+    }
 }
@@ -2,6 +2,7 @@ use ::control::*;
 use ::ph::*;
 use ::physics::*;
 use ::polymini::*;
+use ::scent::*;
 use ::serialization::*;
 use ::species::*;
 use ::thermal::*;
@@ -217,11 +218,13 @@ pub struct Environment
     pub density: f32,
     pub default_sensors: Vec<Sensor>, 
     pub species_slots: usize,
+    pub sensor_noise: SensorNoiseConfig,
 
     // Worlds
     pub physical_world: PhysicsWorld,
     pub thermal_world: ThermoWorld,
     pub ph_world: PhWorld,
+    pub scent_world: ScentWorld,
 
     //
     pub objects: Vec<WorldObject>,
@@ -246,8 +249,10 @@ impl Environment
             physical_world: PhysicsWorld::new_with_dimensions(dimensions),
             thermal_world: ThermoWorld::new_with_dimensions(dimensions, 0.5),
             ph_world: PhWorld::new_with_dimensions(dimensions, 0.5),
+            scent_world: ScentWorld::new_with_dimensions(dimensions, ScentConfig::defaults()),
             default_sensors: default_sensors,
             species_slots: species_slots,
+            sensor_noise: SensorNoiseConfig::new(),
             objects: vec![],
             permanent_objects: HashSet::new(),
         };
@@ -302,6 +307,30 @@ impl Environment
                     }
                 };
 
+                let scent_config = match json_obj.get("Scent")
+                {
+                    Some(scent_json) =>
+                    {
+                        ScentConfig::new_from_json(scent_json, &mut SerializationCtx::new()).unwrap_or(ScentConfig::defaults())
+                    },
+                    None =>
+                    {
+                        ScentConfig::defaults()
+                    }
+                };
+
+                let sensor_noise = match json_obj.get("SensorNoise")
+                {
+                    Some(noise_json) =>
+                    {
+                        sensor_noise_config_from_json(noise_json)
+                    },
+                    None =>
+                    {
+                        SensorNoiseConfig::new()
+                    }
+                };
+
                 let density = match json_obj.get("Density")
                 {
                     Some(&Json::F64(rho)) =>
@@ -319,9 +348,11 @@ impl Environment
                               physical_world: PhysicsWorld::new_with_dimensions(dims),
                               thermal_world: tworld,
                               ph_world:  phworld,
+                              scent_world: ScentWorld::new_with_dimensions(dims, scent_config),
                               density: density as f32,
                               default_sensors: default_sensors,
                               species_slots: json_obj.get("SpeciesSlots").unwrap().as_u64().unwrap() as usize,
+                              sensor_noise: sensor_noise,
                               objects: vec![],
                               permanent_objects: HashSet::new(),
                             };
@@ -484,6 +515,8 @@ impl Environment
         let mut to_ret = Environment::new_with_dimensions(self.species_slots,
                                           self.default_sensors.clone(),
                                           self.dimensions);
+        to_ret.scent_world = self.scent_world.restart();
+        to_ret.sensor_noise = self.sensor_noise.clone();
 
         for o in &self.objects
         {
@@ -524,6 +557,15 @@ impl Serializable for Environment
             //
             json_obj.insert("SpeciesSlots".to_owned(), self.species_slots.to_json());
 
+            //
+            json_obj.insert("Scent".to_owned(), self.scent_world.get_config().serialize(ctx));
+
+            //
+            if self.sensor_noise.len() > 0
+            {
+                json_obj.insert("SensorNoise".to_owned(), sensor_noise_config_to_json(&self.sensor_noise, ctx));
+            }
+
             //
             let mut perm_obj_json_arr = pmJsonArray::new();
             for obj in &self.objects
@@ -552,6 +594,8 @@ impl Clone for Environment
         let mut to_ret = Environment::new_with_dimensions(self.species_slots,
                                                           self.default_sensors.clone(),
                                                           self.dimensions);
+        to_ret.scent_world = self.scent_world.restart();
+        to_ret.sensor_noise = self.sensor_noise.clone();
 
         for o in &self.objects
         {
@@ -2,6 +2,7 @@ use ::actuators::*;
 use ::instincts::*;
 use ::serialization::*;
 use std::collections::{HashMap, HashSet};
+use std::f32;
 use std::fmt;
 
 //TODO Maybe use naming or sub-enums?
@@ -61,7 +62,7 @@ impl fmt::Display for FitnessStatistic
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 pub enum FitnessEvaluator
 {
     // Movement
@@ -75,16 +76,52 @@ pub enum FitnessEvaluator
 
     // Basic Stuff
     Alive { weight: f32 },
+
+    // Composed from JSON, see FitnessExpression
+    Expression { weight: f32, instinct: Instinct, expression: FitnessExpression },
 }
 impl FitnessEvaluator
 {
     pub fn evaluate(&mut self, statistics: &Vec<FitnessStatistic>) -> (Instinct, f32)
+    {
+        let (i, raw) = self.evaluate_raw(statistics);
+        (i, self.weigh(raw, statistics))
+    }
+
+    // Applies the weight to 'raw', as returned by evaluate_raw for 'statistics'
+    pub fn weigh(&self, raw: f32, statistics: &Vec<FitnessStatistic>) -> f32
+    {
+        match *self
+        {
+            FitnessEvaluator::Shape { weight: w } =>
+            {
+                // Shape has always scored 'w - |10 - cells|' for every cell count, keep it that
+                // way so existing configs rank individuals the same
+                let counted = statistics.iter().filter(|stat|
+                                                {
+                                                    match **stat
+                                                    {
+                                                        FitnessStatistic::TotalCells(_) => { true },
+                                                        _ => { false }
+                                                    }
+                                                }).count();
+                raw + (w - 1.0) * counted as f32
+            },
+            _ =>
+            {
+                self.get_weight() * raw
+            }
+        }
+    }
+
+    // Value of the evaluator before its weight is applied
+    pub fn evaluate_raw(&self, statistics: &Vec<FitnessStatistic>) -> (Instinct, f32)
     {
         debug!("Evaluating - {}", statistics.len());
 
         match *self
         {
-            FitnessEvaluator::OverallMovement{ weight: w } => 
+            FitnessEvaluator::OverallMovement{ .. } => 
             {
                 let i = Instinct::Nomadic;
                 let v = statistics.iter().fold(0.0,
@@ -94,7 +131,7 @@ impl FitnessEvaluator
                                                   {
                                                       &FitnessStatistic::Moved =>
                                                       {
-                                                         accum += w;
+                                                         accum += 1.0;
                                                       },
                                                       _ => {}
                                                   }
@@ -103,7 +140,7 @@ impl FitnessEvaluator
                 debug!("Evaluated {} for {} due to Overall Movement", v, i);
                 (i, v)
             },
-            FitnessEvaluator::DistanceTravelled { weight: w } =>
+            FitnessEvaluator::DistanceTravelled { .. } =>
             {
                 let i = Instinct::Nomadic;
                 let v = statistics.iter().fold(0.0,
@@ -113,7 +150,7 @@ impl FitnessEvaluator
                                                   {
                                                       &FitnessStatistic::DistanceTravelled(dist) =>
                                                       {
-                                                         accum += (w * dist as f32);
+                                                         accum += dist as f32;
                                                       },
                                                       _ => {}
                                                   }
@@ -123,7 +160,7 @@ impl FitnessEvaluator
                 debug!("Evaluated {} for {} due to Distance Travelled", v, i);
                 (i, v)
             },
-            FitnessEvaluator::Shape { weight: w } =>
+            FitnessEvaluator::Shape { .. } =>
             {
                 let i = Instinct::Hoarding;
                 let v = statistics.iter().fold(0.0,
@@ -133,7 +170,7 @@ impl FitnessEvaluator
                                                   {
                                                       &FitnessStatistic::TotalCells(c) =>
                                                       {
-                                                         accum += w - ((10.0 - c as f32).abs());
+                                                         accum += 1.0 - ((10.0 - c as f32).abs());
                                                       },
                                                       _ => {}
                                                   }
@@ -142,10 +179,10 @@ impl FitnessEvaluator
                 debug!("Evaluated {} for {} due to Shape", v, i);
                 (i,v)
             },
-            FitnessEvaluator::Alive { weight: w } =>
+            FitnessEvaluator::Alive { .. } =>
             {
                 let i = Instinct::Basic;
-                let v = statistics.iter().fold(w,
+                let v = statistics.iter().fold(1.0,
 
                                                |mut accum, stat|
                                                {
@@ -162,7 +199,7 @@ impl FitnessEvaluator
                 debug!("Evaluated {} for {} due to Staying Alive", v, i);
                 (i,v)
             },
-            FitnessEvaluator::TargetPosition { weight: w, pos: target } =>
+            FitnessEvaluator::TargetPosition { pos: target, .. } =>
             {
                 let i = Instinct::Basic;
                 let v = statistics.iter().fold(0.0,
@@ -176,8 +213,7 @@ impl FitnessEvaluator
                                                                          (actual_y as f32) / 255.0);
                                                           let dx = (target.0 - actual.0).abs();
                                                           let dy = (target.1 - actual.1).abs();
-                                                          accum = w * (1.0 - dx)  +
-                                                                  w * (1.0 - dy);
+                                                          accum = (1.0 - dx) + (1.0 - dy);
                                                           accum /= (dx + dy);
                                                       },
                                                       _ => {}
@@ -188,7 +224,7 @@ impl FitnessEvaluator
                 debug!("Evaluated {} for {} due to Target Position {:?}", v, i, target);
                 (i,v)
             },
-            FitnessEvaluator::PositionsVisited { weight : w } =>
+            FitnessEvaluator::PositionsVisited { .. } =>
             {
                 let i = Instinct::Nomadic;
                 let mut already_counted = HashSet::new();
@@ -204,7 +240,7 @@ impl FitnessEvaluator
                                                           if !already_counted.contains(&pos)
                                                           {
                                                               already_counted.insert(pos);
-                                                              accum += w;
+                                                              accum += 1.0;
                                                           }
                                                           if !already_counted_x.contains(&pos.0)
                                                           {
@@ -228,6 +264,43 @@ impl FitnessEvaluator
                 }
                 debug!("Evaluated {} for {} due to Positions Visited", v, i);
                 (i,v)
+            },
+            FitnessEvaluator::Expression { instinct: i, ref expression, .. } =>
+            {
+                let v = expression.evaluate(statistics);
+                debug!("Evaluated {} for {} due to Expression", v, i);
+                (i,v)
+            }
+        }
+    }
+
+    pub fn get_id(&self) -> &'static str
+    {
+        match *self
+        {
+            FitnessEvaluator::OverallMovement   { .. } => { "overallmovement" },
+            FitnessEvaluator::DistanceTravelled { .. } => { "distancetravelled" },
+            FitnessEvaluator::TargetPosition    { .. } => { "targetposition" },
+            FitnessEvaluator::PositionsVisited  { .. } => { "positionsvisited" },
+            FitnessEvaluator::Shape             { .. } => { "shape" },
+            FitnessEvaluator::Alive             { .. } => { "alive" },
+            FitnessEvaluator::Expression        { .. } => { "expression" },
+        }
+    }
+
+    pub fn get_weight(&self) -> f32
+    {
+        match *self
+        {
+            FitnessEvaluator::OverallMovement   { weight: w }       |
+            FitnessEvaluator::DistanceTravelled { weight: w }       |
+            FitnessEvaluator::TargetPosition    { weight: w, .. }   |
+            FitnessEvaluator::PositionsVisited  { weight: w }       |
+            FitnessEvaluator::Shape             { weight: w }       |
+            FitnessEvaluator::Alive             { weight: w }       |
+            FitnessEvaluator::Expression        { weight: w, .. }   =>
+            {
+                w
             }
         }
     }
@@ -249,6 +322,11 @@ impl FitnessEvaluator
                 Instinct::Hoarding
             },
 
+            FitnessEvaluator::Expression { instinct: i, .. } =>
+            {
+                i
+            },
+
             _ =>
             {
                 Instinct::Basic
@@ -258,94 +336,851 @@ impl FitnessEvaluator
 }
 impl Serializable for FitnessEvaluator
 {
-    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
     {
         let mut json_obj = pmJsonObject::new();
-        let w;
-        let id;
         match *self
         {
-            FitnessEvaluator::OverallMovement{ weight: weight } => 
-            {
-                w = weight;
-                id = "overallmovement";
-            },
-            FitnessEvaluator::DistanceTravelled{ weight: weight } => 
-            {
-                w = weight;
-                id = "distancetravelled";
-            },
-            FitnessEvaluator::TargetPosition{ weight: weight, pos: pos } =>
+            FitnessEvaluator::TargetPosition{ pos: pos, .. } =>
             {
-                w = weight;
-                id = "targetposition";
-
                 let mut pos_json = pmJsonObject::new(); 
                 pos_json.insert("x".to_owned(), pos.0.to_json());
                 pos_json.insert("y".to_owned(), pos.1.to_json());
 
                 json_obj.insert("Position".to_owned(), Json::Object(pos_json));
             },
-            FitnessEvaluator::PositionsVisited{ weight: weight } => 
+            FitnessEvaluator::Expression{ instinct: instinct, ref expression, .. } =>
             {
-                id = "positionsvisited";
-                w = weight;
+                json_obj.insert("Instinct".to_owned(), instinct.serialize(ctx));
+                json_obj.insert("Expression".to_owned(), expression.serialize(ctx));
             },
-            FitnessEvaluator::Shape{ weight: weight } => 
+            _ => {}
+        };
+        json_obj.insert("EvaluatorId".to_owned(), self.get_id().to_json());
+        json_obj.insert("Weight".to_owned(), self.get_weight().to_json());
+        Json::Object(json_obj)
+    }
+}
+impl FitnessEvaluator
+{
+    // Same as new_from_json, but errors say what is wrong and where, like FitnessExpression::parse
+    pub fn parse(json: &Json, path: &str) -> Result<FitnessEvaluator, String>
+    {
+        let json_obj = match *json
+        {
+            Json::Object(ref json_obj) => { json_obj },
+            _ => { return Err(format!("{}: expected an object, found {}", path, json)) }
+        };
+
+        let w = match FitnessExpression::parse_number(json_obj, "Weight", path)
+        {
+            Ok(w) => { w },
+            Err(e) => { return Err(e) }
+        };
+
+        let evaluator_id = match json_obj.get("EvaluatorId").and_then(|e| e.as_string())
+        {
+            Some(evaluator_id) => { evaluator_id },
+            None => { return Err(format!("{}: missing 'EvaluatorId'", path)) }
+        };
+
+        match evaluator_id
+        {
+            "overallmovement" => { Ok(FitnessEvaluator::OverallMovement{ weight: w }) },
+            "distancetravelled" => { Ok(FitnessEvaluator::DistanceTravelled{ weight: w }) },
+            "shape" => { Ok(FitnessEvaluator::Shape{ weight: w }) },
+            "positionsvisited" => { Ok(FitnessEvaluator::PositionsVisited{ weight: w }) },
+
+            "targetposition" =>
             {
-                w = weight;
-                id = "shape";
+                let pos_path = format!("{}.Position", path);
+                let pos_json = match json_obj.get("Position").and_then(|p| p.as_object())
+                {
+                    Some(pos_json) => { pos_json },
+                    None => { return Err(format!("{}: missing 'Position'", path)) }
+                };
+                FitnessExpression::parse_number(pos_json, "x", &pos_path).and_then(|x|
+                {
+                    FitnessExpression::parse_number(pos_json, "y", &pos_path).map(|y|
+                    {
+                        FitnessEvaluator::TargetPosition{ weight: w, pos: (x, y) }
+                    })
+                })
             },
-            FitnessEvaluator::Alive{ weight: weight } => 
+            "alive" => { Ok(FitnessEvaluator::Alive{ weight: w }) },
+            "expression" =>
             {
-                w = weight;
-                id = "alive";
+                let instinct = match json_obj.get("Instinct")
+                {
+                    Some(instinct_json) =>
+                    {
+                        match Instinct::new_from_json(instinct_json, &mut SerializationCtx::new())
+                        {
+                            Some(instinct) => { instinct },
+                            None => { return Err(format!("{}.Instinct: unknown instinct {}", path, instinct_json)) }
+                        }
+                    },
+                    None => { Instinct::Basic }
+                };
+
+                match json_obj.get("Expression")
+                {
+                    Some(expression_json) =>
+                    {
+                        FitnessExpression::parse(expression_json, &format!("{}.Expression", path)).map(|expression|
+                        {
+                            FitnessEvaluator::Expression{ weight: w, instinct: instinct, expression: expression }
+                        })
+                    },
+                    None => { Err(format!("{}: missing 'Expression'", path)) }
+                }
             },
-        };
-        json_obj.insert("EvaluatorId".to_owned(), id.to_json());
-        json_obj.insert("Weight".to_owned(), w.to_json());
-        Json::Object(json_obj)
+            _ => { Err(format!("{}.EvaluatorId: unknown evaluator '{}'", path, evaluator_id)) }
+        }
     }
 }
 impl Deserializable for FitnessEvaluator
 {
     fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<FitnessEvaluator> 
     {
-        match *json
+        match FitnessEvaluator::parse(json, "FitnessEvaluator")
         {
-            Json::Object(ref json_obj) =>
+            Ok(fe) => { Some(fe) },
+            Err(e) =>
             {
-                let w = json_obj.get("Weight").unwrap().as_f64().unwrap() as f32;
-                let fe = match json_obj.get("EvaluatorId").unwrap().as_string().unwrap()
-                {
-                    "overallmovement" => { FitnessEvaluator::OverallMovement{ weight: w }},
-                    "distancetravelled" => { FitnessEvaluator::DistanceTravelled{ weight: w}},
-                    "shape" => { FitnessEvaluator::Shape{ weight: w }},
-                    "positionsvisited" => { FitnessEvaluator::PositionsVisited{ weight: w }},
+                error!("Invalid FitnessEvaluator - {}", e);
+                None
+            }
+        }
+    }
+}
+
+// Statistics that can be read directly from a FitnessExpression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatisticSelector
+{
+    Moved,
+    ConsumedFoodSource,
+    PositionsVisited,
+    DistanceTravelled,
+    TotalCells,
+    FinalPositionX,
+    FinalPositionY,
+    Survival,
+}
+impl StatisticSelector
+{
+    pub fn from_string(string: &str) -> Option<StatisticSelector>
+    {
+        match string.to_lowercase().as_str()
+        {
+            "moved"              => { Some(StatisticSelector::Moved) },
+            "consumedfoodsource" => { Some(StatisticSelector::ConsumedFoodSource) },
+            "positionsvisited"   => { Some(StatisticSelector::PositionsVisited) },
+            "distancetravelled"  => { Some(StatisticSelector::DistanceTravelled) },
+            "totalcells"         => { Some(StatisticSelector::TotalCells) },
+            "finalpositionx"     => { Some(StatisticSelector::FinalPositionX) },
+            "finalpositiony"     => { Some(StatisticSelector::FinalPositionY) },
+            "survival"           => { Some(StatisticSelector::Survival) },
+            _ => { None }
+        }
+    }
 
-                    "targetposition" =>
+    pub fn extract(&self, statistics: &Vec<FitnessStatistic>) -> f32
+    {
+        match *self
+        {
+            StatisticSelector::Moved =>
+            {
+                statistics.iter().filter(|s| **s == FitnessStatistic::Moved).count() as f32
+            },
+            StatisticSelector::ConsumedFoodSource =>
+            {
+                statistics.iter().filter(|s| **s == FitnessStatistic::ConsumedFoodSource).count() as f32
+            },
+            StatisticSelector::PositionsVisited =>
+            {
+                let mut visited = HashSet::new();
+                for stat in statistics
+                {
+                    match *stat
                     {
-                        let pos_json = json_obj.get("Position").unwrap().as_object().unwrap();
-                        FitnessEvaluator::TargetPosition{ weight: w,
-                                                          pos: (pos_json.get("x").unwrap().as_f64().unwrap() as f32,
-                                                                pos_json.get("y").unwrap().as_f64().unwrap() as f32) }
+                        FitnessStatistic::PositionVisited(pos) => { visited.insert(pos); },
+                        _ => {}
+                    }
+                }
+                visited.len() as f32
+            },
+            StatisticSelector::DistanceTravelled =>
+            {
+                statistics.iter().fold(0.0, |accum, stat|
+                                       {
+                                           match *stat
+                                           {
+                                               FitnessStatistic::DistanceTravelled(d) => { accum + d as f32 },
+                                               _ => { accum }
+                                           }
+                                       })
+            },
+            StatisticSelector::TotalCells =>
+            {
+                statistics.iter().fold(0.0, |accum, stat|
+                                       {
+                                           match *stat
+                                           {
+                                               FitnessStatistic::TotalCells(c) => { c as f32 },
+                                               _ => { accum }
+                                           }
+                                       })
+            },
+            StatisticSelector::FinalPositionX |
+            StatisticSelector::FinalPositionY =>
+            {
+                let x_axis = *self == StatisticSelector::FinalPositionX;
+                statistics.iter().fold(0.0, |accum, stat|
+                                       {
+                                           match *stat
+                                           {
+                                               FitnessStatistic::FinalPosition(x, y) =>
+                                               {
+                                                   (if x_axis { x } else { y }) as f32 / 255.0
+                                               },
+                                               _ => { accum }
+                                           }
+                                       })
+            },
+            StatisticSelector::Survival =>
+            {
+                statistics.iter().fold(1.0, |accum, stat|
+                                       {
+                                           match *stat
+                                           {
+                                               FitnessStatistic::Died(step, max) => { accum * (step as f32 / max as f32) },
+                                               _ => { accum }
+                                           }
+                                       })
+            },
+        }
+    }
+}
+impl Serializable for StatisticSelector
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl fmt::Display for StatisticSelector
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Small expression tree so fitness functions can be composed from JSON, e.g.:
+// {"Op":"product", "Args":[ {"Op":"threshold", "Threshold":1.0, "Value":{"Op":"statistic", "Statistic":"survival"}},
+//                           {"Op":"evaluator", "Evaluator":{"EvaluatorId":"targetposition", ... }} ]}
+#[derive(Clone, Debug)]
+pub enum FitnessExpression
+{
+    Constant(f32),
+    Evaluator(Box<FitnessEvaluator>),
+    Statistic(StatisticSelector),
+
+    Sum(Vec<FitnessExpression>),
+    Product(Vec<FitnessExpression>),
+    Min(Vec<FitnessExpression>),
+    Max(Vec<FitnessExpression>),
+
+    Threshold { value: Box<FitnessExpression>, threshold: f32 },
+    Clamp { value: Box<FitnessExpression>, min: f32, max: f32 },
+    Normalise { value: Box<FitnessExpression>, min: f32, max: f32 },
+}
+impl FitnessExpression
+{
+    pub fn evaluate(&self, statistics: &Vec<FitnessStatistic>) -> f32
+    {
+        match *self
+        {
+            FitnessExpression::Constant(v) =>
+            {
+                v
+            },
+            FitnessExpression::Evaluator(ref evaluator) =>
+            {
+                let mut evaluator = (**evaluator).clone();
+                evaluator.evaluate(statistics).1
+            },
+            FitnessExpression::Statistic(selector) =>
+            {
+                selector.extract(statistics)
+            },
+            FitnessExpression::Sum(ref args) =>
+            {
+                args.iter().fold(0.0, |accum, a| accum + a.evaluate(statistics))
+            },
+            FitnessExpression::Product(ref args) =>
+            {
+                args.iter().fold(1.0, |accum, a| accum * a.evaluate(statistics))
+            },
+            FitnessExpression::Min(ref args) =>
+            {
+                args.iter().fold(f32::INFINITY, |accum, a| accum.min(a.evaluate(statistics)))
+            },
+            FitnessExpression::Max(ref args) =>
+            {
+                args.iter().fold(f32::NEG_INFINITY, |accum, a| accum.max(a.evaluate(statistics)))
+            },
+            FitnessExpression::Threshold { ref value, threshold } =>
+            {
+                if value.evaluate(statistics) >= threshold { 1.0 } else { 0.0 }
+            },
+            FitnessExpression::Clamp { ref value, min, max } =>
+            {
+                value.evaluate(statistics).max(min).min(max)
+            },
+            FitnessExpression::Normalise { ref value, min, max } =>
+            {
+                if max <= min
+                {
+                    0.0
+                }
+                else
+                {
+                    ((value.evaluate(statistics) - min) / (max - min)).max(0.0).min(1.0)
+                }
+            },
+        }
+    }
 
+    // Errors are reported with the path to the offending sub-expression,
+    // e.g. "Expression.Args[1].Value: unknown Op 'foo'"
+    pub fn parse(json: &Json, path: &str) -> Result<FitnessExpression, String>
+    {
+        let json_obj = match *json
+        {
+            Json::Object(ref json_obj) => { json_obj },
+            _ => { return Err(format!("{}: expected an object, found {}", path, json)) }
+        };
+
+        let op = match json_obj.get("Op").and_then(|op| op.as_string())
+        {
+            Some(op) => { op.to_lowercase() },
+            None => { return Err(format!("{}: missing 'Op'", path)) }
+        };
 
+        match op.as_str()
+        {
+            "constant" =>
+            {
+                FitnessExpression::parse_number(json_obj, "Value", path).map(|v| FitnessExpression::Constant(v))
+            },
+            "evaluator" =>
+            {
+                match json_obj.get("Evaluator")
+                {
+                    Some(evaluator_json) =>
+                    {
+                        FitnessEvaluator::parse(evaluator_json, &format!("{}.Evaluator", path)).map(|evaluator|
+                        {
+                            FitnessExpression::Evaluator(Box::new(evaluator))
+                        })
                     },
-                    "alive" => { FitnessEvaluator::Alive{ weight: w }},
-                    _ => { return None }
+                    None => { Err(format!("{}: missing 'Evaluator'", path)) }
+                }
+            },
+            "statistic" =>
+            {
+                match json_obj.get("Statistic").and_then(|s| s.as_string())
+                {
+                    Some(s) =>
+                    {
+                        match StatisticSelector::from_string(s)
+                        {
+                            Some(selector) => { Ok(FitnessExpression::Statistic(selector)) },
+                            None => { Err(format!("{}.Statistic: unknown statistic '{}'", path, s)) }
+                        }
+                    },
+                    None => { Err(format!("{}: missing 'Statistic'", path)) }
+                }
+            },
+            "sum" | "product" | "min" | "max" =>
+            {
+                let args = match FitnessExpression::parse_args(json_obj, path)
+                {
+                    Ok(args) => { args },
+                    Err(e) => { return Err(e) }
+                };
+                match op.as_str()
+                {
+                    "sum"     => { Ok(FitnessExpression::Sum(args)) },
+                    "product" => { Ok(FitnessExpression::Product(args)) },
+                    "min"     => { Ok(FitnessExpression::Min(args)) },
+                    _         => { Ok(FitnessExpression::Max(args)) },
+                }
+            },
+            "threshold" =>
+            {
+                let value = match FitnessExpression::parse_value(json_obj, path)
+                {
+                    Ok(value) => { value },
+                    Err(e) => { return Err(e) }
                 };
-                Some(fe)
+                FitnessExpression::parse_number(json_obj, "Threshold", path).map(|t| FitnessExpression::Threshold { value: value,
+                                                                                                                   threshold: t })
             },
-            _ => 
+            "clamp" | "normalise" | "normalize" =>
+            {
+                let value = match FitnessExpression::parse_value(json_obj, path)
+                {
+                    Ok(value) => { value },
+                    Err(e) => { return Err(e) }
+                };
+                let min = match FitnessExpression::parse_number(json_obj, "Min", path)
+                {
+                    Ok(min) => { min },
+                    Err(e) => { return Err(e) }
+                };
+                let max = match FitnessExpression::parse_number(json_obj, "Max", path)
+                {
+                    Ok(max) => { max },
+                    Err(e) => { return Err(e) }
+                };
+
+                if op == "clamp"
+                {
+                    Ok(FitnessExpression::Clamp { value: value, min: min, max: max })
+                }
+                else
+                {
+                    Ok(FitnessExpression::Normalise { value: value, min: min, max: max })
+                }
+            },
+            _ =>
+            {
+                Err(format!("{}: unknown Op '{}'", path, op))
+            }
+        }
+    }
+
+    fn parse_number(json_obj: &pmJsonObject, field: &str, path: &str) -> Result<f32, String>
+    {
+        match json_obj.get(field).and_then(|v| v.as_f64())
+        {
+            Some(v) => { Ok(v as f32) },
+            None => { Err(format!("{}: missing or non-numeric '{}'", path, field)) }
+        }
+    }
+
+    fn parse_value(json_obj: &pmJsonObject, path: &str) -> Result<Box<FitnessExpression>, String>
+    {
+        match json_obj.get("Value")
+        {
+            Some(value_json) =>
+            {
+                FitnessExpression::parse(value_json, &format!("{}.Value", path)).map(|v| Box::new(v))
+            },
+            None => { Err(format!("{}: missing 'Value'", path)) }
+        }
+    }
+
+    fn parse_args(json_obj: &pmJsonObject, path: &str) -> Result<Vec<FitnessExpression>, String>
+    {
+        let args_json = match json_obj.get("Args").and_then(|a| a.as_array())
+        {
+            Some(args_json) => { args_json },
+            None => { return Err(format!("{}: missing 'Args' array", path)) }
+        };
+
+        if args_json.len() == 0
+        {
+            return Err(format!("{}.Args: needs at least one argument", path))
+        }
+
+        let mut args = vec![];
+        for (i, arg_json) in args_json.iter().enumerate()
+        {
+            match FitnessExpression::parse(arg_json, &format!("{}.Args[{}]", path, i))
+            {
+                Ok(arg) => { args.push(arg); },
+                Err(e) => { return Err(e) }
+            }
+        }
+        Ok(args)
+    }
+}
+impl Serializable for FitnessExpression
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        let op;
+        match *self
+        {
+            FitnessExpression::Constant(v) =>
+            {
+                op = "constant";
+                json_obj.insert("Value".to_owned(), v.to_json());
+            },
+            FitnessExpression::Evaluator(ref evaluator) =>
+            {
+                op = "evaluator";
+                json_obj.insert("Evaluator".to_owned(), evaluator.serialize(ctx));
+            },
+            FitnessExpression::Statistic(selector) =>
+            {
+                op = "statistic";
+                json_obj.insert("Statistic".to_owned(), selector.serialize(ctx));
+            },
+            FitnessExpression::Sum(ref args) |
+            FitnessExpression::Product(ref args) |
+            FitnessExpression::Min(ref args) |
+            FitnessExpression::Max(ref args) =>
+            {
+                op = match *self
+                {
+                    FitnessExpression::Sum(_) => { "sum" },
+                    FitnessExpression::Product(_) => { "product" },
+                    FitnessExpression::Min(_) => { "min" },
+                    _ => { "max" },
+                };
+                json_obj.insert("Args".to_owned(),
+                                Json::Array(args.iter().map(|a| a.serialize(ctx)).collect()));
+            },
+            FitnessExpression::Threshold { ref value, threshold } =>
+            {
+                op = "threshold";
+                json_obj.insert("Value".to_owned(), value.serialize(ctx));
+                json_obj.insert("Threshold".to_owned(), threshold.to_json());
+            },
+            FitnessExpression::Clamp { ref value, min, max } |
+            FitnessExpression::Normalise { ref value, min, max } =>
+            {
+                op = match *self
+                {
+                    FitnessExpression::Clamp { .. } => { "clamp" },
+                    _ => { "normalise" },
+                };
+                json_obj.insert("Value".to_owned(), value.serialize(ctx));
+                json_obj.insert("Min".to_owned(), min.to_json());
+                json_obj.insert("Max".to_owned(), max.to_json());
+            },
+        }
+        json_obj.insert("Op".to_owned(), op.to_json());
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for FitnessExpression
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<FitnessExpression>
+    {
+        match FitnessExpression::parse(json, "Expression")
+        {
+            Ok(expression) => { Some(expression) },
+            Err(e) =>
             {
-                error!("Incorrect Type Passed for FitnessEvaluator");
+                error!("Invalid FitnessExpression - {}", e);
                 None
             }
         }
     }
 }
 
+// Optional stage applied to each evaluator's unweighted score before its weight,
+// using the scores of the whole generation as reference
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitnessNormalisation
+{
+    NoNormalisation,
+    MinMax,
+    ZScore,
+    Rank,
+}
+impl Serializable for FitnessNormalisation
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let id = match *self
+        {
+            FitnessNormalisation::NoNormalisation => { "none" },
+            FitnessNormalisation::MinMax => { "minmax" },
+            FitnessNormalisation::ZScore => { "zscore" },
+            FitnessNormalisation::Rank => { "rank" },
+        };
+        id.to_json()
+    }
+}
+impl Deserializable for FitnessNormalisation
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<FitnessNormalisation>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "none"   => { Some(FitnessNormalisation::NoNormalisation) },
+                    "minmax" => { Some(FitnessNormalisation::MinMax) },
+                    "zscore" => { Some(FitnessNormalisation::ZScore) },
+                    "rank"   => { Some(FitnessNormalisation::Rank) },
+                    _ =>
+                    {
+                        error!("Unknown FitnessNormalisation {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for FitnessNormalisation
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Per-evaluator distribution of the unweighted scores of a generation
+#[derive(Clone, Debug)]
+pub struct FitnessNormaliser
+{
+    mode: FitnessNormalisation,
+    // Sorted samples per evaluator
+    samples: Vec<Vec<f32>>,
+    means: Vec<f32>,
+    std_devs: Vec<f32>,
+}
+impl FitnessNormaliser
+{
+    pub fn new(mode: FitnessNormalisation, samples: Vec<Vec<f32>>) -> FitnessNormaliser
+    {
+        let mut sorted = samples;
+        let mut means = vec![];
+        let mut std_devs = vec![];
+        for s in &mut sorted
+        {
+            s.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+
+            let n = s.len() as f32;
+            let mean = if s.len() > 0 { s.iter().fold(0.0, |accum, v| accum + v) / n } else { 0.0 };
+            let var  = if s.len() > 0 { s.iter().fold(0.0, |accum, v| accum + (v - mean) * (v - mean)) / n } else { 0.0 };
+            means.push(mean);
+            std_devs.push(var.sqrt());
+        }
+
+        FitnessNormaliser { mode: mode, samples: sorted, means: means, std_devs: std_devs }
+    }
+
+    pub fn normalise(&self, evaluator: usize, v: f32) -> f32
+    {
+        let samples = match self.samples.get(evaluator)
+        {
+            Some(samples) if samples.len() > 0 => { samples },
+            _ => { return v }
+        };
+
+        match self.mode
+        {
+            FitnessNormalisation::NoNormalisation =>
+            {
+                v
+            },
+            FitnessNormalisation::MinMax =>
+            {
+                let min = samples[0];
+                let max = samples[samples.len() - 1];
+                if max - min <= 0.0
+                {
+                    0.5
+                }
+                else
+                {
+                    ((v - min) / (max - min)).max(0.0).min(1.0)
+                }
+            },
+            FitnessNormalisation::ZScore =>
+            {
+                if self.std_devs[evaluator] <= 0.0
+                {
+                    0.0
+                }
+                else
+                {
+                    (v - self.means[evaluator]) / self.std_devs[evaluator]
+                }
+            },
+            FitnessNormalisation::Rank =>
+            {
+                if samples.len() < 2
+                {
+                    return 0.5
+                }
+                // Ties share the average of their ranks
+                let below = samples.iter().filter(|s| **s < v).count() as f32;
+                let equal = samples.iter().filter(|s| **s == v).count() as f32;
+                let rank = below + if equal > 0.0 { (equal - 1.0) / 2.0 } else { 0.0 };
+                rank / (samples.len() - 1) as f32
+            },
+        }
+    }
+}
+
+// How much a single FitnessEvaluator contributed to an evaluation
+#[derive(Clone, Debug)]
+pub struct EvaluatorScore
+{
+    pub evaluator: &'static str,
+    pub instinct: Instinct,
+
+    // Unweighted score (before normalisation), see FitnessEvaluator::evaluate_raw
+    pub raw: f32,
+    pub normalised: Option<f32>,
+    // What actually went into the accumulator
+    pub weighted: f32,
+}
+impl Serializable for EvaluatorScore
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Evaluator".to_owned(), self.evaluator.to_json());
+        json_obj.insert("Instinct".to_owned(), self.instinct.serialize(ctx));
+        json_obj.insert("Raw".to_owned(), self.raw.to_json());
+        if let Some(n) = self.normalised
+        {
+            json_obj.insert("Normalised".to_owned(), n.to_json());
+        }
+        json_obj.insert("Weighted".to_owned(), self.weighted.to_json());
+        Json::Object(json_obj)
+    }
+}
+
+// How the scores of several scenarios (environments) are combined into one
+// when accumulating over several calls to evaluate
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvaluationAggregation
+{
+    Mean,
+    WorstCase,
+    BestCase,
+    Weighted,
+}
+impl EvaluationAggregation
+{
+    // scores are (score, importance) pairs
+    pub fn aggregate(&self, scores: &Vec<(f32, f32)>) -> f32
+    {
+        if scores.len() == 0
+        {
+            return 0.0
+        }
+
+        match *self
+        {
+            EvaluationAggregation::Mean =>
+            {
+                scores.iter().fold(0.0, |accum, s| accum + s.0) / scores.len() as f32
+            },
+            EvaluationAggregation::WorstCase =>
+            {
+                scores.iter().fold(f32::INFINITY, |accum, s| accum.min(s.0))
+            },
+            EvaluationAggregation::BestCase =>
+            {
+                scores.iter().fold(f32::NEG_INFINITY, |accum, s| accum.max(s.0))
+            },
+            EvaluationAggregation::Weighted =>
+            {
+                let total_importance = scores.iter().fold(0.0, |accum, s| accum + s.1);
+                if total_importance <= 0.0
+                {
+                    0.0
+                }
+                else
+                {
+                    scores.iter().fold(0.0, |accum, s| accum + s.0 * s.1) / total_importance
+                }
+            },
+        }
+    }
+}
+impl Serializable for EvaluationAggregation
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let id = match *self
+        {
+            EvaluationAggregation::Mean => { "mean" },
+            EvaluationAggregation::WorstCase => { "worstcase" },
+            EvaluationAggregation::BestCase => { "bestcase" },
+            EvaluationAggregation::Weighted => { "weighted" },
+        };
+        id.to_json()
+    }
+}
+impl Deserializable for EvaluationAggregation
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<EvaluationAggregation>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "mean"      => { Some(EvaluationAggregation::Mean) },
+                    "worstcase" => { Some(EvaluationAggregation::WorstCase) },
+                    "bestcase"  => { Some(EvaluationAggregation::BestCase) },
+                    "weighted"  => { Some(EvaluationAggregation::Weighted) },
+                    _ =>
+                    {
+                        error!("Unknown EvaluationAggregation {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for EvaluationAggregation
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Score obtained in a single scenario
+#[derive(Clone, Copy, Debug)]
+pub struct ScenarioScore
+{
+    pub raw: f32,
+    pub fitness: f32,
+    pub importance: f32,
+}
+impl Serializable for ScenarioScore
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Raw".to_owned(), self.raw.to_json());
+        json_obj.insert("Fitness".to_owned(), self.fitness.to_json());
+        json_obj.insert("Importance".to_owned(), self.importance.to_json());
+        Json::Object(json_obj)
+    }
+}
+
 pub struct PolyminiEvaluationCtx
 {
     evaluators: Vec<FitnessEvaluator>,
@@ -353,6 +1188,13 @@ pub struct PolyminiEvaluationCtx
     instinct_weights: HashMap<Instinct, f32>,
 
     accumulates_over: bool,
+    aggregation: EvaluationAggregation,
+    importance: f32,
+
+    normaliser: Option<FitnessNormaliser>,
+    breakdown: Vec<EvaluatorScore>,
+    // Only used by the collection pass that builds the normaliser
+    collected: Option<Vec<Vec<f32>>>,
 }
 impl PolyminiEvaluationCtx
 {
@@ -362,21 +1204,110 @@ impl PolyminiEvaluationCtx
         PolyminiEvaluationCtx { evaluators: evaluators,
                                 accumulator: accumulator,
                                 instinct_weights: instinct_weights,
-                                accumulates_over: accumulates_over }
+                                accumulates_over: accumulates_over,
+                                aggregation: EvaluationAggregation::Mean,
+                                importance: 1.0,
+                                normaliser: None,
+                                breakdown: vec![],
+                                collected: None }
+    }
+
+    // A context that doesn't score, it only records the unweighted value of each
+    // evaluator so a FitnessNormaliser can be built for the generation
+    pub fn new_collecting(evaluators: Vec<FitnessEvaluator>) -> PolyminiEvaluationCtx
+    {
+        let collected = evaluators.iter().map(|_| vec![]).collect();
+        PolyminiEvaluationCtx { evaluators: evaluators,
+                                accumulator: PolyminiFitnessAccumulator::new(vec![Instinct::Basic]),
+                                instinct_weights: HashMap::new(),
+                                accumulates_over: false,
+                                aggregation: EvaluationAggregation::Mean,
+                                importance: 1.0,
+                                normaliser: None,
+                                breakdown: vec![],
+                                collected: Some(collected) }
+    }
+
+    pub fn is_collecting(&self) -> bool
+    {
+        self.collected.is_some()
+    }
+
+    pub fn collect(&mut self, statistics: &Vec<FitnessStatistic>)
+    {
+        match self.collected
+        {
+            Some(ref mut collected) =>
+            {
+                for (i, evaluator) in self.evaluators.iter().enumerate()
+                {
+                    collected[i].push(evaluator.evaluate_raw(statistics).1);
+                }
+            },
+            None =>
+            {
+                error!("PolyminiEvaluationCtx::collect called on a scoring context");
+            }
+        }
+    }
+
+    pub fn build_normaliser(&self, mode: FitnessNormalisation) -> FitnessNormaliser
+    {
+        FitnessNormaliser::new(mode, self.collected.clone().unwrap_or(vec![]))
+    }
+
+    pub fn set_normaliser(&mut self, normaliser: FitnessNormaliser)
+    {
+        self.normaliser = Some(normaliser);
+    }
+
+    pub fn set_aggregation(&mut self, aggregation: EvaluationAggregation, importance: f32)
+    {
+        self.aggregation = aggregation;
+        self.importance = importance;
+    }
+
+    pub fn get_aggregation(&self) -> EvaluationAggregation
+    {
+        self.aggregation
+    }
+
+    pub fn get_importance(&self) -> f32
+    {
+        self.importance
     }
 
     pub fn evaluate(&mut self, statistics: &Vec<FitnessStatistic>)
     {
-        debug!("EvaluationCtx::evaluate Before fold - {}", self.evaluators.len());
-        self.evaluators.iter_mut().fold(&mut self.accumulator,
-                                        |accum, ref mut evaluator|
-                                        {
-                                            debug!("In fold iteration");
-                                            let v = evaluator.evaluate(statistics);
-                                            accum.add(&v.0, v.1);
-                                            accum
-                                        });
-        debug!("EvaluationCtx::evaluate After fold - {}", self.evaluators.len());
+        debug!("EvaluationCtx::evaluate - {}", self.evaluators.len());
+        for (i, evaluator) in self.evaluators.iter().enumerate()
+        {
+            let (instinct, raw) = evaluator.evaluate_raw(statistics);
+            let (normalised, weighted) = match self.normaliser
+            {
+                Some(ref normaliser) =>
+                {
+                    let n = normaliser.normalise(i, raw);
+                    (Some(n), evaluator.get_weight() * n)
+                },
+                None =>
+                {
+                    (None, evaluator.weigh(raw, statistics))
+                }
+            };
+
+            self.accumulator.add(&instinct, weighted);
+            self.breakdown.push(EvaluatorScore { evaluator: evaluator.get_id(),
+                                                 instinct: instinct,
+                                                 raw: raw,
+                                                 normalised: normalised,
+                                                 weighted: weighted });
+        }
+    }
+
+    pub fn get_breakdown(&self) -> &Vec<EvaluatorScore>
+    {
+        &self.breakdown
     }
 
     pub fn get_raw(&self) -> f32
@@ -461,6 +1392,7 @@ mod test
 {
     use ::actuators::*;
     use ::instincts::*;
+    use ::serialization::*;
     use std::collections::HashMap;
     use super::*;
 
@@ -477,9 +1409,138 @@ mod test
         
         let mut map = HashMap::new();
         map.insert(Instinct::Nomadic, 2.0);
-        let eval_ctx = PolyminiEvaluationCtx { evaluators: vec![], accumulator: accum, accumulates_over: false, instinct_weights: map.clone() };
+        let eval_ctx = PolyminiEvaluationCtx { evaluators: vec![], accumulator: accum, accumulates_over: false, instinct_weights: map.clone(),
+                                               aggregation: EvaluationAggregation::Mean, importance: 1.0,
+                                               normaliser: None, breakdown: vec![], collected: None };
 
         assert_eq!(eval_ctx.get_raw(), 3.0);
         assert_eq!(eval_ctx.get_fitness(), 5.0);
     }
+
+    #[test]
+    fn test_fitness_expression()
+    {
+        let json = Json::from_str("{\"EvaluatorId\":\"expression\",\"Weight\":2.0,\"Instinct\":\"basic\",
+                                    \"Expression\":{\"Op\":\"product\",\"Args\":[
+                                        {\"Op\":\"threshold\",\"Threshold\":1.0,\"Value\":{\"Op\":\"statistic\",\"Statistic\":\"survival\"}},
+                                        {\"Op\":\"clamp\",\"Min\":0.0,\"Max\":3.0,\"Value\":{\"Op\":\"statistic\",\"Statistic\":\"moved\"}}]}}").unwrap();
+        let mut evaluator = FitnessEvaluator::new_from_json(&json, &mut SerializationCtx::new()).unwrap();
+
+        let alive = vec![FitnessStatistic::Moved, FitnessStatistic::Moved,
+                         FitnessStatistic::Moved, FitnessStatistic::Moved];
+        assert_eq!(evaluator.evaluate(&alive), (Instinct::Basic, 6.0));
+
+        let dead = vec![FitnessStatistic::Moved, FitnessStatistic::Died(5, 10)];
+        assert_eq!(evaluator.evaluate(&dead), (Instinct::Basic, 0.0));
+
+        // Round trip
+        let json_2 = evaluator.serialize(&mut SerializationCtx::new());
+        let mut evaluator_2 = FitnessEvaluator::new_from_json(&json_2, &mut SerializationCtx::new()).unwrap();
+        assert_eq!(evaluator_2.evaluate(&alive), (Instinct::Basic, 6.0));
+    }
+
+    #[test]
+    fn test_evaluator_raw_and_weighted()
+    {
+        // Shape isn't linear on its weight, it keeps scoring 'weight - |10 - cells|'
+        let evaluators = vec![FitnessEvaluator::Shape { weight: 2.0 },
+                              FitnessEvaluator::OverallMovement { weight: 3.0 }];
+        let stats = vec![FitnessStatistic::TotalCells(7), FitnessStatistic::Moved, FitnessStatistic::Moved];
+
+        let mut ctx = PolyminiEvaluationCtx::new_from(evaluators, PolyminiFitnessAccumulator::new(vec![Instinct::Hoarding, Instinct::Nomadic]),
+                                                      HashMap::new(), false);
+        ctx.evaluate(&stats);
+
+        let breakdown = ctx.get_breakdown();
+        assert_eq!((breakdown[0].raw, breakdown[0].weighted), (-2.0, -1.0));
+        assert_eq!((breakdown[1].raw, breakdown[1].weighted), (2.0, 6.0));
+    }
+
+    #[test]
+    fn test_fitness_normalisation()
+    {
+        let evaluators = vec![FitnessEvaluator::OverallMovement { weight: 2.0 }];
+        let generation = vec![vec![FitnessStatistic::Moved],
+                              vec![FitnessStatistic::Moved, FitnessStatistic::Moved, FitnessStatistic::Moved],
+                              vec![FitnessStatistic::Moved, FitnessStatistic::Moved, FitnessStatistic::Moved,
+                                   FitnessStatistic::Moved, FitnessStatistic::Moved]];
+
+        let mut collecting = PolyminiEvaluationCtx::new_collecting(evaluators.clone());
+        for stats in &generation
+        {
+            collecting.collect(stats);
+        }
+
+        let expected = [(FitnessNormalisation::MinMax, [0.0, 1.0, 2.0]),
+                        (FitnessNormalisation::Rank,   [0.0, 1.0, 2.0])];
+        for &(mode, results) in expected.iter()
+        {
+            let normaliser = collecting.build_normaliser(mode);
+            for (stats, result) in generation.iter().zip(results.iter())
+            {
+                let mut ctx = PolyminiEvaluationCtx::new_from(evaluators.clone(), PolyminiFitnessAccumulator::new(vec![Instinct::Nomadic]),
+                                                              HashMap::new(), false);
+                ctx.set_normaliser(normaliser.clone());
+                ctx.evaluate(stats);
+                assert_eq!(ctx.get_raw(), *result);
+            }
+        }
+
+        // Breakdown keeps both the raw value and the contribution
+        let mut ctx = PolyminiEvaluationCtx::new_from(evaluators.clone(), PolyminiFitnessAccumulator::new(vec![Instinct::Nomadic]),
+                                                      HashMap::new(), false);
+        ctx.set_normaliser(collecting.build_normaliser(FitnessNormalisation::MinMax));
+        ctx.evaluate(&generation[1]);
+        assert_eq!(ctx.get_breakdown().len(), 1);
+        assert_eq!(ctx.get_breakdown()[0].evaluator, "overallmovement");
+        assert_eq!(ctx.get_breakdown()[0].raw, 3.0);
+        assert_eq!(ctx.get_breakdown()[0].normalised, Some(0.5));
+        assert_eq!(ctx.get_breakdown()[0].weighted, 1.0);
+
+        // Z-Score is centered on the mean (negative scores get clamped by the fitness)
+        let normaliser = collecting.build_normaliser(FitnessNormalisation::ZScore);
+        assert_eq!(normaliser.normalise(0, 3.0), 0.0);
+        assert!(normaliser.normalise(0, 5.0) > 0.0);
+    }
+
+    #[test]
+    fn test_evaluation_aggregation()
+    {
+        let scores = vec![(2.0, 1.0), (4.0, 3.0), (0.0, 0.0)];
+        assert_eq!(EvaluationAggregation::Mean.aggregate(&scores), 2.0);
+        assert_eq!(EvaluationAggregation::WorstCase.aggregate(&scores), 0.0);
+        assert_eq!(EvaluationAggregation::BestCase.aggregate(&scores), 4.0);
+        assert_eq!(EvaluationAggregation::Weighted.aggregate(&scores), 3.5);
+        assert_eq!(EvaluationAggregation::WorstCase.aggregate(&vec![]), 0.0);
+    }
+
+    #[test]
+    fn test_fitness_expression_error_path()
+    {
+        let json = Json::from_str("{\"Op\":\"sum\",\"Args\":[{\"Op\":\"constant\",\"Value\":1.0},
+                                                      {\"Op\":\"clamp\",\"Min\":0.0,\"Max\":1.0,\"Value\":{\"Op\":\"bogus\"}}]}").unwrap();
+        match FitnessExpression::parse(&json, "Expression")
+        {
+            Ok(_) => { panic!("Expected a parse error") },
+            Err(e) => { assert!(e.starts_with("Expression.Args[1].Value:"), "{}", e); }
+        }
+
+        // Errors inside a nested evaluator keep their path
+        let json = Json::from_str("{\"Op\":\"evaluator\",\"Evaluator\":{\"EvaluatorId\":\"expression\",\"Weight\":1.0,
+                                                              \"Expression\":{\"Op\":\"bogus\"}}}").unwrap();
+        match FitnessExpression::parse(&json, "Expression")
+        {
+            Ok(_) => { panic!("Expected a parse error") },
+            Err(e) => { assert!(e.starts_with("Expression.Evaluator.Expression:"), "{}", e); }
+        }
+
+        // So does a misspelled Instinct
+        let json = Json::from_str("{\"EvaluatorId\":\"expression\",\"Weight\":1.0,\"Instinct\":\"nutrition\",
+                                    \"Expression\":{\"Op\":\"constant\",\"Value\":1.0}}").unwrap();
+        match FitnessEvaluator::parse(&json, "FitnessEvaluator")
+        {
+            Ok(_) => { panic!("Expected a parse error") },
+            Err(e) => { assert!(e.starts_with("FitnessEvaluator.Instinct: unknown instinct"), "{}", e); }
+        }
+    }
 }
@@ -11,6 +11,7 @@ pub use self::rust_monster::ga::ga_core::GeneticAlgorithm as PolyminiGA;
 //
 //
 //
+use ::control::ControlConfig;
 use ::evaluation::*;
 use ::instincts::*;
 use ::serialization::*;
@@ -66,13 +67,40 @@ impl<T: PolyminiGAIndividual> PolyminiGeneration<T>
         self.individuals.raw_score_iterator()
     }
 
-    pub fn evaluate(&mut self, evaluators: &Vec<FitnessEvaluator>, instincts: &Vec<Instinct>,
-                    instinct_weights: &HashMap<Instinct,f32>, accumulates: bool)
+    pub fn evaluate(&mut self, config: &PGAConfig, instincts: &Vec<Instinct>, instinct_weights: &HashMap<Instinct,f32>)
     {
+        let evaluators = &config.fitness_evaluators;
+        let normalisation = config.normalisation;
+
+        // Normalising needs the scores of the whole generation first, individuals
+        // only report them to a collecting context (no side effects)
+        let normaliser = match normalisation
+        {
+            FitnessNormalisation::NoNormalisation =>
+            {
+                None
+            },
+            _ =>
+            {
+                let mut collect_ctx = PolyminiEvaluationCtx::new_collecting(evaluators.clone());
+                for ref mut ind in &mut self.individuals.population().iter_mut()
+                {
+                    ind.evaluate(&mut collect_ctx);
+                }
+                Some(collect_ctx.build_normaliser(normalisation))
+            }
+        };
+
         for ref mut ind in &mut self.individuals.population().iter_mut()
         {
             let mut ctx = PolyminiEvaluationCtx::new_from(evaluators.clone(),
-                                                          PolyminiFitnessAccumulator::new(instincts.clone()), instinct_weights.clone(), accumulates);
+                                                          PolyminiFitnessAccumulator::new(instincts.clone()), instinct_weights.clone(),
+                                                          config.accumulates_over);
+            ctx.set_aggregation(config.aggregation, config.environment_importance);
+            if let Some(ref n) = normaliser
+            {
+                ctx.set_normaliser(n.clone());
+            }
             ind.evaluate(&mut ctx);
         }
         self.individuals.force_sort();
@@ -95,6 +123,13 @@ pub struct PGAConfig
     // Evaluation Context
     pub accumulates_over: bool, // Accumulate the result of the evaulation over several calls to evaluate
     pub fitness_evaluators: Vec<FitnessEvaluator>,
+    pub normalisation: FitnessNormalisation,
+    // How scores from different environments are combined when accumulating
+    pub aggregation: EvaluationAggregation,
+    pub environment_importance: f32,
+
+    // Topology of the brains of new individuals
+    pub control: ControlConfig,
 
     // Genome Length
     pub genome_size: usize,
@@ -111,6 +146,10 @@ impl PGAConfig
             percentage_mutation: 0.2,
             fitness_evaluators: vec![],
             accumulates_over: false,
+            normalisation: FitnessNormalisation::NoNormalisation,
+            aggregation: EvaluationAggregation::Mean,
+            environment_importance: 1.0,
+            control: ControlConfig::defaults(),
             genome_size: 4,
         }
     }
@@ -150,6 +189,10 @@ impl Serializable for PGAConfig
                                     fe.serialize(ctx)  
                                 }
                             }).collect()));
+            json_obj.insert("Normalisation".to_owned(), self.normalisation.serialize(ctx));
+            json_obj.insert("Aggregation".to_owned(), self.aggregation.serialize(ctx));
+            json_obj.insert("EnvironmentImportance".to_owned(), self.environment_importance.to_json());
+            json_obj.insert("ControlConfiguration".to_owned(), self.control.serialize(ctx));
         }
         Json::Object(json_obj)
     }
@@ -189,9 +232,47 @@ impl Deserializable for PGAConfig
                 };
 
                 let ao = json_obj.get("AccumulatesOver").unwrap_or(&Json::Boolean(false)).as_boolean().unwrap();
+
+                let norm = match json_obj.get("Normalisation")
+                {
+                    Some(norm_json) =>
+                    {
+                        FitnessNormalisation::new_from_json(norm_json, ctx).unwrap_or(FitnessNormalisation::NoNormalisation)
+                    },
+                    None =>
+                    {
+                        FitnessNormalisation::NoNormalisation
+                    }
+                };
+ 
+                let agg = match json_obj.get("Aggregation")
+                {
+                    Some(agg_json) =>
+                    {
+                        EvaluationAggregation::new_from_json(agg_json, ctx).unwrap_or(EvaluationAggregation::Mean)
+                    },
+                    None =>
+                    {
+                        EvaluationAggregation::Mean
+                    }
+                };
+                let ei = json_obj.get("EnvironmentImportance").and_then(|ei| ei.as_f64()).unwrap_or(1.0) as f32;
+
+                let control = match json_obj.get("ControlConfiguration")
+                {
+                    Some(control_json) =>
+                    {
+                        ControlConfig::new_from_json(control_json, ctx).unwrap_or(ControlConfig::defaults())
+                    },
+                    None =>
+                    {
+                        ControlConfig::defaults()
+                    }
+                };
  
                 Some(PGAConfig { population_size: ps,
-                                 percentage_elitism: pe, fitness_evaluators: fe, accumulates_over: ao,
+                                 percentage_elitism: pe, fitness_evaluators: fe, accumulates_over: ao, normalisation: norm,
+                                 aggregation: agg, environment_importance: ei, control: control,
                                  percentage_mutation: pm, genome_size: gs })
             },
             _ =>
@@ -255,9 +336,9 @@ impl<T: PolyminiGAIndividual> PolyminiGeneticAlgorithm<T>
     pub fn evaluate_population(&mut self, instinct_weights: &HashMap<Instinct, f32>)
     {
         // TODO: Instincts should come from somehwere else like a config
-        self.population.evaluate(&self.config.fitness_evaluators,
+        self.population.evaluate(&self.config,
                                  &vec![ Instinct::Nomadic, Instinct::Basic, Instinct::Hoarding, Instinct::Herding, Instinct::Predatory ],
-                                 instinct_weights, self.config.accumulates_over);
+                                 instinct_weights);
     }
 
     pub fn population(&mut self) -> &mut GAPopulation<T>
@@ -320,6 +401,7 @@ impl<T: PolyminiGAIndividual> PolyminiGeneticAlgorithm<T>
 mod test
 {
     use super::*;
+    use ::control::*;
     use ::evaluation::*;
     use ::instincts::*;
     use ::serialization::*;
@@ -336,6 +418,17 @@ mod test
                                FitnessEvaluator::Shape { weight: 5.0 }];
         let cfg = PGAConfig { population_size: 50,
                               percentage_elitism: 0.11, percentage_mutation: 0.12, fitness_evaluators: evaluators, accumulates_over: false,
+                              normalisation: FitnessNormalisation::Rank,
+                              aggregation: EvaluationAggregation::Weighted, environment_importance: 2.0,
+                              control: ControlConfig { kind: ControlKind::Layered, hidden_layers: 2, max_hidden_layers: 4, min_hidden_size: 2, max_hidden_size: 5,
+                                                       hidden_activation: Activation::Tanh, output_activation: Activation::Sigmoid,
+                                                       topology_mutation: 0.1, recurrent: true,
+                                                       plasticity: true, learning_rate: 0.05,
+                                                       inheritance: Inheritance::Lamarckian,
+                                                       weight_initialisation: WeightInitialisation::Gaussian { sigma: 0.25 },
+                                                       weight_mutation: WeightMutation { mode: WeightMutationMode::Perturb, sigma: 0.2,
+                                                                                         fraction: 0.1, clamp: Some(4.0) },
+                                                       add_node_mutation: 0.1, add_connection_mutation: 0.2 },
                               genome_size: 8 };
         let ser_ctx = &mut SerializationCtx::new_from_flags(PolyminiSerializationFlags::PM_SF_DB);
                               
@@ -13,6 +13,8 @@ pub mod instincts;
 #[allow(dead_code)]
 pub mod morphology;
 #[allow(dead_code)]
+pub mod neat;
+#[allow(dead_code)]
 pub mod physics;
 #[allow(dead_code)]
 pub mod ph;
@@ -21,6 +23,10 @@ pub mod polymini;
 #[allow(dead_code)]
 pub mod random;
 #[allow(dead_code)]
+pub mod scent;
+#[allow(dead_code)]
+pub mod scripted;
+#[allow(dead_code)]
 pub mod sensors;
 #[allow(dead_code)]
 pub mod serialization;
@@ -3,6 +3,7 @@ use std::cmp::{min, max};
 use std::fmt;
 
 use ::actuators::*;
+use ::control::ControlConfig;
 use ::genetics::*;
 use ::serialization::*;
 use ::sensors::*;
@@ -19,6 +20,7 @@ pub struct PolyminiCreationCtx
     pub trans_table: TranslationTable,
     pub default_sensors: Vec<Sensor>,
     pub random_context: PolyminiRandomCtx,
+    pub control_config: ControlConfig,
 }
 impl PolyminiCreationCtx
 {
@@ -29,7 +31,8 @@ impl PolyminiCreationCtx
     pub fn new_from(tt: TranslationTable, default_sensors: Vec<Sensor>,
                     rand_ctx: PolyminiRandomCtx) -> PolyminiCreationCtx
     {
-        PolyminiCreationCtx { trans_table: tt, default_sensors: default_sensors, random_context: rand_ctx }
+        PolyminiCreationCtx { trans_table: tt, default_sensors: default_sensors, random_context: rand_ctx,
+                              control_config: ControlConfig::defaults() }
     }
 }
 impl GAContext for PolyminiCreationCtx
@@ -42,7 +45,7 @@ impl GAContext for PolyminiCreationCtx
 
 //
 //
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct AdjacencyInfo
 {
     adj: Vec<Direction>
@@ -84,6 +87,7 @@ impl AdjacencyInfo
 //
 // This might be a confusing name, but it refers to a cell in a grid, not an
 // actual biological cell
+#[derive(Clone)]
 pub struct Cell 
 {
     adjacency_info : AdjacencyInfo,
@@ -153,9 +157,244 @@ impl Deserializable for TTKey
     }
 }
 
+// What to do with the cells that don't end up as part of a single connected body
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MorphologyRepair
+{
+    // Keep the body as it comes out of the chromosome
+    NoRepair,
+    // Cells that overlap or can't be reached from the head are dropped
+    DropUnreachable,
+    // Those cells are moved to the closest free spot next to the body instead, the adjacency
+    // in the chromosomes is left as it is
+    Relocate,
+}
+impl Serializable for MorphologyRepair
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        match *self
+        {
+            MorphologyRepair::NoRepair        => { "none".to_json() },
+            MorphologyRepair::DropUnreachable => { "drop".to_json() },
+            MorphologyRepair::Relocate        => { "relocate".to_json() },
+        }
+    }
+}
+impl Deserializable for MorphologyRepair
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<MorphologyRepair>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_ref()
+                {
+                    "none"    => { Some(MorphologyRepair::NoRepair) },
+                    "drop"    => { Some(MorphologyRepair::DropUnreachable) },
+                    "relocate" => { Some(MorphologyRepair::Relocate) },
+                    _ =>
+                    {
+                        error!("Unknown Morphology Repair - {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                error!("Incorrect type passed - {:?}", json);
+                None
+            }
+        }
+    }
+}
+
+// Symmetry of the bodies of a species. With symmetry the chromosomes only describe half
+// (or a quarter) of the body, so crossover and mutation act on that part
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Symmetry
+{
+    NoSymmetry,
+    // Mirrored left to right across the column of the head
+    Mirror,
+    // Four way, the quarter right of the head is turned around it three times
+    Radial,
+}
+impl Symmetry
+{
+    // Whether 'c' is in the part of the body the chromosomes describe, that part is always
+    // at or below the row of the head
+    fn contains(&self, c: Coord) -> bool
+    {
+        match *self
+        {
+            Symmetry::NoSymmetry => { c.1 >= 0 },
+            Symmetry::Mirror => { c.0 >= 0 && c.1 >= 0 },
+            Symmetry::Radial => { c == (0, 0) || (c.0 > 0 && c.1 >= 0) },
+        }
+    }
+
+    // Brings 'c' into the part of the body the chromosomes describe
+    fn fold(&self, c: Coord) -> Coord
+    {
+        match *self
+        {
+            Symmetry::NoSymmetry => { c },
+            Symmetry::Mirror => { (c.0.abs(), c.1) },
+            Symmetry::Radial =>
+            {
+                // Every cell but the head is in the quarter after one of the turns
+                let mut f = c;
+                for _ in 0..3
+                {
+                    if self.contains(f)
+                    {
+                        break;
+                    }
+                    f = (f.1, -f.0);
+                }
+                f
+            },
+        }
+    }
+
+    // Where the copies of the cell at 'c' go, the head has none
+    fn copies(&self, c: Coord) -> Vec<Coord>
+    {
+        match *self
+        {
+            Symmetry::NoSymmetry => { vec![] },
+            Symmetry::Mirror if c.0 != 0 => { vec![(-c.0, c.1)] },
+            Symmetry::Radial if c != (0, 0) =>
+            {
+                // Quarter turns around the head
+                vec![(-c.1, c.0), (-c.0, -c.1), (c.1, -c.0)]
+            },
+            _ => { vec![] },
+        }
+    }
+}
+impl Serializable for Symmetry
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        match *self
+        {
+            Symmetry::NoSymmetry => { "none".to_json() },
+            Symmetry::Mirror     => { "mirror".to_json() },
+            Symmetry::Radial     => { "radial".to_json() },
+        }
+    }
+}
+impl Deserializable for Symmetry
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<Symmetry>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_ref()
+                {
+                    "none"   => { Some(Symmetry::NoSymmetry) },
+                    "mirror" => { Some(Symmetry::Mirror) },
+                    "radial" => { Some(Symmetry::Radial) },
+                    _ =>
+                    {
+                        error!("Unknown Symmetry - {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                error!("Incorrect type passed - {:?}", json);
+                None
+            }
+        }
+    }
+}
+
+// How the chromosomes of a species are turned into a body, see GenomeDecoder
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenomeDecoderKind
+{
+    // Each chromosome says which of its sides have neighbours, the body grows from the head
+    Adjacency,
+    // Each chromosome says where in the grid its cell goes
+    Grid,
+}
+impl GenomeDecoderKind
+{
+    pub fn new_decoder(&self) -> Box<GenomeDecoder>
+    {
+        match *self
+        {
+            GenomeDecoderKind::Adjacency => { Box::new(AdjacencyDecoder) },
+            GenomeDecoderKind::Grid => { Box::new(GridDecoder) },
+        }
+    }
+
+    // Cells on a grid land anywhere, without repair most of those bodies would be broken
+    pub fn default_repair(&self) -> MorphologyRepair
+    {
+        match *self
+        {
+            GenomeDecoderKind::Adjacency => { MorphologyRepair::NoRepair },
+            GenomeDecoderKind::Grid => { MorphologyRepair::Relocate },
+        }
+    }
+}
+impl Serializable for GenomeDecoderKind
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl Deserializable for GenomeDecoderKind
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<GenomeDecoderKind>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "adjacency" => { Some(GenomeDecoderKind::Adjacency) },
+                    "grid"      => { Some(GenomeDecoderKind::Grid) },
+                    _ =>
+                    {
+                        error!("Unknown GenomeDecoder {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                error!("Incorrect type passed - {:?}", json);
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for GenomeDecoderKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
 pub struct TranslationTable
 {
     trait_table:  HashMap<TTKey, PolyminiTrait>,
+    repair: MorphologyRepair,
+    decoder_kind: GenomeDecoderKind,
+    decoder: Box<GenomeDecoder>,
+    symmetry: Symmetry,
 }
 impl TranslationTable
 {
@@ -176,7 +415,10 @@ impl TranslationTable
                 filtered_table.insert(*k, *v);
             }
         }
-        TranslationTable { trait_table: filtered_table }
+        let decoder_kind = GenomeDecoderKind::Adjacency;
+        TranslationTable { trait_table: filtered_table, repair: decoder_kind.default_repair(),
+                           decoder_kind: decoder_kind, decoder: decoder_kind.new_decoder(),
+                           symmetry: Symmetry::NoSymmetry }
     }
 
     pub fn new_from_json(json: &Json, trait_table: &HashMap<TTKey, PolyminiTrait>) -> Option<TranslationTable>
@@ -201,6 +443,105 @@ impl TranslationTable
         }
     }
     
+    // Options on how bodies are built, they are stored in the Species next to the TranslationTable
+    pub fn read_options(&mut self, json_obj: &pmJsonObject)
+    {
+        // The decoder goes first, it picks the repair when none is given
+        match TranslationTable::read_option::<GenomeDecoderKind>(json_obj, "GenomeDecoder")
+        {
+            Some(decoder_kind) => { self.set_decoder_kind(decoder_kind); },
+            None => {}
+        }
+        match TranslationTable::read_option::<MorphologyRepair>(json_obj, "MorphologyRepair")
+        {
+            Some(repair) => { self.set_repair(repair); },
+            None => {}
+        }
+        match TranslationTable::read_option::<Symmetry>(json_obj, "Symmetry")
+        {
+            Some(symmetry) => { self.set_symmetry(symmetry); },
+            None => {}
+        }
+    }
+
+    // Only the options that aren't the defaults are written
+    pub fn write_options(&self, json_obj: &mut pmJsonObject, ctx: &mut SerializationCtx)
+    {
+        if self.decoder_kind != GenomeDecoderKind::Adjacency
+        {
+            json_obj.insert("GenomeDecoder".to_string(), self.decoder_kind.serialize(ctx));
+        }
+        if self.repair != self.decoder_kind.default_repair()
+        {
+            json_obj.insert("MorphologyRepair".to_string(), self.repair.serialize(ctx));
+        }
+        if self.symmetry != Symmetry::NoSymmetry
+        {
+            json_obj.insert("Symmetry".to_string(), self.symmetry.serialize(ctx));
+        }
+    }
+
+    fn read_option<T: Deserializable>(json_obj: &pmJsonObject, key: &str) -> Option<T>
+    {
+        match json_obj.get(key)
+        {
+            Some(json) =>
+            {
+                let option = T::new_from_json(json, &mut SerializationCtx::new());
+                if option.is_none()
+                {
+                    warn!("Invalid {}, using the default", key);
+                }
+                option
+            },
+            None =>
+            {
+                None
+            }
+        }
+    }
+
+    pub fn get_repair(&self) -> MorphologyRepair
+    {
+        self.repair
+    }
+
+    pub fn set_repair(&mut self, repair: MorphologyRepair)
+    {
+        self.repair = repair;
+    }
+
+    pub fn get_decoder(&self) -> &GenomeDecoder
+    {
+        &*self.decoder
+    }
+
+    pub fn get_decoder_kind(&self) -> GenomeDecoderKind
+    {
+        self.decoder_kind
+    }
+
+    // Also moves the repair to the decoder's default if the bodies weren't being repaired
+    pub fn set_decoder_kind(&mut self, decoder_kind: GenomeDecoderKind)
+    {
+        if self.repair == MorphologyRepair::NoRepair
+        {
+            self.repair = decoder_kind.default_repair();
+        }
+        self.decoder_kind = decoder_kind;
+        self.decoder = decoder_kind.new_decoder();
+    }
+
+    pub fn get_symmetry(&self) -> Symmetry
+    {
+        self.symmetry
+    }
+
+    pub fn set_symmetry(&mut self, symmetry: Symmetry)
+    {
+        self.symmetry = symmetry;
+    }
+
     fn create_for_chromosome(&self,
                              chromosome: Chromosome) -> Cell
     {
@@ -208,8 +549,6 @@ impl TranslationTable
         //TODO: Control / Metadata Payload
         let _ = 0xFF & chromosome[0];
         let ap = 0xFF & chromosome[1];
-        let gp1 = ((0xFF & chromosome[2]) as u16) << 8;
-        let gp  = gp1 + (0xFF & chromosome[3]) as u16;
 
         let dirs = vec![Direction::UP, Direction::DOWN, Direction::LEFT, Direction::RIGHT];
         let mut adj_dirs = vec![];
@@ -222,6 +561,15 @@ impl TranslationTable
             }
         }
 
+        Cell::new(AdjacencyInfo::new(adj_dirs), self.create_trait(chromosome))
+    }
+
+    // The trait is encoded in the last two bytes of the chromosome
+    fn create_trait(&self, chromosome: Chromosome) -> Trait
+    {
+        let gp1 = ((0xFF & chromosome[2]) as u16) << 8;
+        let gp  = gp1 + (0xFF & chromosome[3]) as u16;
+
         //TODO: Make this configurable - Using a list of Transform + Chain could work
         //TIER I
         let mut tier: u8 = 1;
@@ -257,8 +605,7 @@ impl TranslationTable
             }
         }
 
-        Cell::new(AdjacencyInfo::new(adj_dirs),
-                  Trait::new(TraitTier::from(tier), trait_num, polymini_trait))
+        Trait::new(TraitTier::from(tier), trait_num, polymini_trait)
     }
 }
 impl Serializable for TranslationTable
@@ -281,6 +628,8 @@ const TOTAL_ORIENTATIONS: usize = 4;
 struct Representation
 {
     cells: Vec<Cell>,
+    // Where each cell was placed (facing UP), in the same order as 'cells'
+    placement: Vec<Coord>,
     positions: [HashMap<Coord, usize>; TOTAL_ORIENTATIONS],
     dimensions: (u8, u8),
     corners: [(i8, i8); TOTAL_ORIENTATIONS],
@@ -293,12 +642,13 @@ impl Representation
         let mut all_positions : [HashMap<Coord, usize>; TOTAL_ORIENTATIONS] = [HashMap::new(), HashMap::new(),
                                                                                HashMap::new(), HashMap::new()];
 
-        // TODO: This should be configurable but enforceable
-        assert_eq!(corner.1, 0);
+        // The head is always part of the body, Radial symmetry puts cells above it
+        assert!(corner.0 <= 0 && corner.1 <= 0);
 
+        // A cell placed on top of another doesn't take its spot
         for i in 0..positions.len()
         {
-            all_positions[0].insert(positions[i], i);
+            all_positions[0].entry(positions[i]).or_insert(i);
         }
 
         all_positions[1] = Representation::rotate(&all_positions[0]); 
@@ -309,15 +659,16 @@ impl Representation
 
         let minx = corner.0;
         let miny = corner.1;
-        let maxx = dimensions.0 as i8 + minx - 1;
-        let maxy = dimensions.1 as i8 + miny - 1;
+        let maxx = dimensions.0 as i8 + minx;
+        let maxy = dimensions.1 as i8 + miny;
 
+        // Counter-clockwise quarter turns, same as the body in Physics
         corners[0] = (   minx,       miny);
         corners[1] = (-1*maxy,       minx);
-        corners[2] = (-1*maxx,       miny);
-        corners[3] = (   miny,       minx);
+        corners[2] = (-1*maxx,    -1*maxy);
+        corners[3] = (   miny,    -1*maxx);
 
-        Representation { cells: cells, positions: all_positions, dimensions: dimensions,
+        Representation { cells: cells, placement: positions, positions: all_positions, dimensions: dimensions,
                          corners: corners }
     }
 
@@ -329,7 +680,9 @@ impl Representation
             // Rotation matrix [ cosA  -sinA ]
             //                 [ sinA   cosA ]
             // A = 90 degrees, sinA = 1, cosA = 0
-            let new_c = (c.1, -1 * c.0);
+            // Cells are unit squares anchored at their bottom-left corner, so once
+            // rotated the square is anchored one unit to the left
+            let new_c = (-1 * c.1 - 1, c.0);
             to_ret.insert(new_c, *p);
         }
         to_ret
@@ -394,8 +747,271 @@ impl fmt::Debug for Representation
     }
 }
 
+// Side in pixels of a cell in Morphology::to_svg
+const SVG_CELL_SIZE: u32 = 16;
+
 //
 //
+// Problems with the body of a Polymini, cells are referred by their index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MorphologyIssue
+{
+    // There is no cell at (0, 0)
+    MissingHead,
+    // The cell was placed where another cell already was
+    Overlapping(usize),
+    // The cell can't be reached from the head going through other cells
+    Disconnected(usize),
+}
+
+// Coordinates in 'occupied' that can be reached from 'head' moving through 'occupied'
+fn reachable_from(head: Coord, occupied: &HashSet<Coord>) -> HashSet<Coord>
+{
+    let mut reachable = HashSet::new();
+    if !occupied.contains(&head)
+    {
+        return reachable
+    }
+
+    let mut stack = vec![head];
+    reachable.insert(head);
+    while let Some(c) = stack.pop()
+    {
+        for n in &[(c.0 + 1, c.1), (c.0 - 1, c.1), (c.0, c.1 + 1), (c.0, c.1 - 1)]
+        {
+            if occupied.contains(n) && !reachable.contains(n)
+            {
+                reachable.insert(*n);
+                stack.push(*n);
+            }
+        }
+    }
+    reachable
+}
+
+// 'positions' has the coordinate of each cell in order, the first cell to claim a
+// coordinate keeps it and the ones after it are overlapping
+fn validate_placement(positions: &Vec<Coord>) -> Vec<MorphologyIssue>
+{
+    let mut issues = vec![];
+    if positions.len() == 0
+    {
+        return issues
+    }
+
+    let head = (0, 0);
+    let mut occupied = HashSet::new();
+    for (i, p) in positions.iter().enumerate()
+    {
+        if !occupied.insert(*p)
+        {
+            issues.push(MorphologyIssue::Overlapping(i));
+        }
+    }
+
+    if !occupied.contains(&head)
+    {
+        issues.insert(0, MorphologyIssue::MissingHead);
+    }
+
+    let reachable = reachable_from(head, &occupied);
+    let mut seen = HashSet::new();
+    for (i, p) in positions.iter().enumerate()
+    {
+        if seen.insert(*p) && !reachable.contains(p)
+        {
+            issues.push(MorphologyIssue::Disconnected(i));
+        }
+    }
+    issues
+}
+
+// Turns chromosomes into cells and where they go (facing UP), the first cell is the head
+// and should be at (0, 0). Anything overlapping or disconnected is left for the repair policy
+pub trait GenomeDecoder
+{
+    fn decode(&self, chromosomes: &Vec<Chromosome>, translation_table: &TranslationTable) -> (Vec<Coord>, Vec<Cell>);
+}
+
+// Most cells a body grown through adjacency can have
+const MAX_ADJACENCY_CELLS: usize = 40;
+
+// Walks the chromosomes depth first, each cell adding the neighbours in its adjacency info
+pub struct AdjacencyDecoder;
+impl GenomeDecoder for AdjacencyDecoder
+{
+    fn decode(&self, chromosomes: &Vec<Chromosome>, translation_table: &TranslationTable) -> (Vec<Coord>, Vec<Cell>)
+    {
+        let mut cells = vec![];
+        for c in chromosomes
+        {
+            cells.push(translation_table.create_for_chromosome(*c));
+        }
+
+        let mut visited: HashSet<Coord> = HashSet::new();
+        let mut stack: Vec<Coord> = Vec::new();
+        let mut positions: Vec<Coord> = Vec::new();
+
+        // Iterate through the cells gathering adjacency info
+        let mut curr_coord = (0, 0);
+
+        positions.push(curr_coord);
+        for cell in &mut cells
+        {
+            let mut coords = cell.adjacency_info.get_neighbours(curr_coord);
+            for coord in &mut coords
+            {
+                if visited.contains(coord)
+                {
+                    continue;
+                }
+                stack.push(*coord);
+                positions.push(*coord);
+
+                visited.insert(*coord);
+            }
+            match stack.pop()
+            {
+                Some(c) => { curr_coord = c; },
+                None => { break; }
+            }
+
+            if positions.len() > MAX_ADJACENCY_CELLS
+            {
+                break;
+            }
+        }
+
+        let l = min(cells.len(), positions.len());
+        let drain_cell: Vec<Cell> = cells.drain(0..l).collect();
+        let drain_pos: Vec<Coord> = positions.drain(0..l).collect();
+        (drain_pos, drain_cell)
+    }
+}
+
+// Side of the grid GridDecoder places cells in
+const GRID_DECODER_SIZE: i8 = 16;
+
+// The second byte of each chromosome is the position of its cell, high nibble is x
+// (centred on the head) and low nibble is y. The head always goes at (0, 0)
+pub struct GridDecoder;
+impl GenomeDecoder for GridDecoder
+{
+    fn decode(&self, chromosomes: &Vec<Chromosome>, translation_table: &TranslationTable) -> (Vec<Coord>, Vec<Cell>)
+    {
+        let mut positions = vec![];
+        let mut cells = vec![];
+        for (i, c) in chromosomes.iter().enumerate()
+        {
+            let coord = if i == 0
+            {
+                (0, 0)
+            }
+            else
+            {
+                (((c[1] & 0xF0) >> 4) as i8 - GRID_DECODER_SIZE / 2, (c[1] & 0x0F) as i8)
+            };
+            positions.push(coord);
+            cells.push(Cell::new(AdjacencyInfo::new(vec![]), translation_table.create_trait(*c)));
+        }
+        (positions, cells)
+    }
+}
+
+// Applies 'policy' to the cells with issues, returns the coordinates and cells that make it.
+// Relocated cells stay in the part of the body 'symmetry' describes
+fn repair_placement(positions: Vec<Coord>, cells: Vec<Cell>, policy: MorphologyRepair, symmetry: Symmetry) -> (Vec<Coord>, Vec<Cell>)
+{
+    if policy == MorphologyRepair::NoRepair
+    {
+        return (positions, cells)
+    }
+
+    let issues = validate_placement(&positions);
+    if issues.len() == 0
+    {
+        return (positions, cells)
+    }
+
+    let broken: HashSet<usize> = issues.iter().filter_map(|i|
+                                 {
+                                     match *i
+                                     {
+                                         MorphologyIssue::Overlapping(c) | MorphologyIssue::Disconnected(c) => { Some(c) },
+                                         MorphologyIssue::MissingHead => { None },
+                                     }
+                                 }).collect();
+
+    let mut n_positions = vec![];
+    let mut n_cells = vec![];
+    let mut relocated = vec![];
+    for (i, (p, c)) in positions.into_iter().zip(cells.into_iter()).enumerate()
+    {
+        if broken.contains(&i)
+        {
+            relocated.push((p, c));
+        }
+        else
+        {
+            n_positions.push(p);
+            n_cells.push(c);
+        }
+    }
+
+    if policy == MorphologyRepair::Relocate
+    {
+        let mut occupied: HashSet<Coord> = n_positions.iter().cloned().collect();
+        for (p, c) in relocated
+        {
+            // Closest free spot next to the body
+            let mut free = vec![];
+            for o in &occupied
+            {
+                for n in &[(o.0 + 1, o.1), (o.0 - 1, o.1), (o.0, o.1 + 1), (o.0, o.1 - 1)]
+                {
+                    if symmetry.contains(*n) && !occupied.contains(n)
+                    {
+                        free.push(*n);
+                    }
+                }
+            }
+            free.sort_by_key(|n| ((n.0 - p.0).abs() + (n.1 - p.1).abs(), n.1, n.0));
+
+            match free.first()
+            {
+                Some(n) =>
+                {
+                    occupied.insert(*n);
+                    n_positions.push(*n);
+                    n_cells.push(c);
+                },
+                None =>
+                {
+                    warn!("Couldn't relocate cell at {:?}", p);
+                }
+            }
+        }
+    }
+
+    (n_positions, n_cells)
+}
+
+// Adds the copies of every cell 'symmetry' asks for. A connected body stays connected, cells
+// on top of each other have their copies on top of each other too
+fn apply_symmetry(mut positions: Vec<Coord>, mut cells: Vec<Cell>, symmetry: Symmetry) -> (Vec<Coord>, Vec<Cell>)
+{
+    for i in 0..positions.len()
+    {
+        for p in symmetry.copies(positions[i])
+        {
+            positions.push(p);
+            let c = cells[i].clone();
+            cells.push(c);
+        }
+    }
+    (positions, cells)
+}
+
 #[derive(Debug)]
 pub struct Morphology
 {
@@ -473,57 +1089,20 @@ impl Morphology
 
     fn create_representation (chromosomes: &Vec<Chromosome>, translation_table: &TranslationTable) -> Representation
     {
-        let mut cells = vec![];
-        for c in chromosomes
-        {
-            cells.push(translation_table.create_for_chromosome(*c));
-        }
-
-        let mut visited: HashSet<Coord> = HashSet::new();
-        let mut stack: Vec<Coord> = Vec::new();
-        let mut positions: Vec<Coord> = Vec::new();
+        let (drain_pos, drain_cell) = translation_table.get_decoder().decode(chromosomes, translation_table);
 
-        // Iterate through the cells gathering adjacency info
-        let mut curr_coord = (0, 0);
         // Init all of these to 0, as we are 100% to have the nucleus at 0,0
         let mut minx = 0;
         let mut maxx = 0;
         let mut miny = 0;
         let mut maxy = 0;
 
-
-        positions.push(curr_coord);
-        for cell in &mut cells
-        {
-            let mut coords = cell.adjacency_info.get_neighbours(curr_coord);
-            for coord in &mut coords
-            {
-                if visited.contains(coord)
-                {
-                    continue;
-                }
-                stack.push(*coord);
-                positions.push(*coord);
-
-                visited.insert(*coord);
-            }
-            match stack.pop()
-            {
-                Some(c) => { curr_coord = c; },
-                None => { break; }
-            }
-
-            if positions.len() > 40
-            {
-                break;
-            }
-        }
-
-        let l = min(cells.len(), positions.len());
-        let drain_cell: Vec<Cell> = cells.drain(0..l).collect();
-        let drain_pos: Vec<Coord> = positions.drain(0..l).collect();
-
-
+        // With symmetry the chromosomes describe part of the body, cells outside of it are folded
+        // in and the ones that land on another cell are left for the repair policy
+        let symmetry = translation_table.get_symmetry();
+        let drain_pos = drain_pos.into_iter().map(|p| symmetry.fold(p)).collect();
+        let (drain_pos, drain_cell) = repair_placement(drain_pos, drain_cell, translation_table.get_repair(), symmetry);
+        let (drain_pos, drain_cell) = apply_symmetry(drain_pos, drain_cell, symmetry);
 
         for p in &drain_pos
         {
@@ -545,6 +1124,12 @@ impl Morphology
         return self.dimensions
     }
 
+    // Checks the body is a single connected piece with a head and no cells on top of each other
+    pub fn validate(&self) -> Vec<MorphologyIssue>
+    {
+        validate_placement(&self.representations.placement)
+    }
+
     pub fn crossover(&self, other: &Morphology, creation_ctx: &mut PolyminiCreationCtx) -> Morphology
     {
         let mut chromosomes = vec![];
@@ -654,6 +1239,11 @@ impl Morphology
         {
             match c.pm_trait.pm_trait 
             {
+                PolyminiTrait::PolyminiSensor(SensorTag::Vision) =>
+                {
+                    sensors.push(Sensor::new_with_cardinality(SensorTag::Vision, i,
+                                                              VISION_VALUES_PER_RAY * self.get_vision_rays()));
+                },
                 PolyminiTrait::PolyminiSensor(t) =>
                 {
                     sensors.push(Sensor::new(t, i));
@@ -666,6 +1256,17 @@ impl Morphology
         sensors
     }
 
+    // How many rays the eyes of this morphology cast
+    pub fn get_vision_rays(&self) -> usize
+    {
+        get_vision_rays(self.get_traits_of_type(PolyminiTrait::PolyminiSimpleTrait(TraitTag::VisionAcuity)).len())
+    }
+
+    pub fn has_sensor(&self, tag: SensorTag) -> bool
+    {
+        self.representations.cells.iter().any(|c| c.pm_trait.pm_trait == PolyminiTrait::PolyminiSensor(tag))
+    }
+
     pub fn get_traits_of_type(&self, trait_type: PolyminiTrait) -> Vec<PolyminiTrait>
     {
         let mut to_ret = vec![];
@@ -692,6 +1293,14 @@ impl Morphology
         self.representations.corners[0]
     }
 
+    // Coordinates of the cells facing UP
+    pub fn get_cells(&self) -> Vec<Coord>
+    {
+        let mut cells: Vec<Coord> = self.representations.positions[0].keys().cloned().collect();
+        cells.sort();
+        cells
+    }
+
 
     pub fn get_corner_for_orientation(&self, orientation: Direction) -> (i8, i8)
     {
@@ -720,6 +1329,110 @@ impl Morphology
             }
         }
     }
+
+    // One line per row of the body, one glyph per cell (see PolyminiTrait::get_glyph)
+    // and '.' where there's no cell
+    pub fn to_ascii(&self, orientation: Direction) -> String
+    {
+        let mut ascii = String::new();
+        self.for_each_cell(orientation, |x, _, w, cell|
+        {
+            match cell
+            {
+                Some(i) => { ascii.push(self.representations.cells[i].pm_trait.pm_trait.get_glyph()); },
+                None => { ascii.push('.'); }
+            }
+            if x + 1 == w
+            {
+                ascii.push('\n');
+            }
+        });
+        ascii
+    }
+
+    // SVG image of the body, cells are coloured by trait, Actuators are marked with a triangle
+    // and Sensors with a circle. The head has a thicker outline
+    pub fn to_svg(&self, orientation: Direction) -> String
+    {
+        let (w, h) = self.get_dimensions_for_orientation(orientation);
+        let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n",
+                              w as u32 * SVG_CELL_SIZE, h as u32 * SVG_CELL_SIZE);
+        let head = self.representations.positions[0].get(&(0, 0)).cloned();
+        let s = SVG_CELL_SIZE;
+
+        self.for_each_cell(orientation, |x, y, _, cell|
+        {
+            let i = match cell
+            {
+                Some(i) => { i },
+                None => { return }
+            };
+            let pm_trait = self.representations.cells[i].pm_trait.pm_trait;
+            let (px, py) = (x as u32 * s, y as u32 * s);
+            let is_head = head == Some(i);
+
+            svg.push_str(&format!("  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"black\" stroke-width=\"{}\"><title>{}</title></rect>\n",
+                                  px, py, s, s, pm_trait.get_colour(), if is_head { 2 } else { 1 }, pm_trait));
+            match pm_trait
+            {
+                PolyminiTrait::PolyminiActuator(_) =>
+                {
+                    svg.push_str(&format!("  <polygon points=\"{},{} {},{} {},{}\" fill=\"black\"/>\n",
+                                          px + s / 2, py + 2, px + s - 2, py + s - 2, px + 2, py + s - 2));
+                },
+                PolyminiTrait::PolyminiSensor(_) =>
+                {
+                    svg.push_str(&format!("  <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"white\" stroke=\"black\"/>\n",
+                                          px + s / 2, py + s / 2, s / 4));
+                },
+                PolyminiTrait::PolyminiSimpleTrait(_) => {}
+            }
+        });
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    fn get_dimensions_for_orientation(&self, orientation: Direction) -> (u8, u8)
+    {
+        // Flip-flop width and height for rotations
+        let p = Morphology::orientation_index(orientation);
+        let dim = [self.representations.dimensions.0, self.representations.dimensions.1];
+        (dim[p % 2], dim[(p + 1) % 2])
+    }
+
+    // Walks the body row by row, 'f' gets the position in the grid, its width and the index
+    // of the cell if there's one
+    fn for_each_cell<F>(&self, orientation: Direction, mut f: F) where F: FnMut(u8, u8, u8, Option<usize>)
+    {
+        let p = Morphology::orientation_index(orientation);
+        let (w, h) = self.get_dimensions_for_orientation(orientation);
+        let corner = self.representations.corners[p];
+        for y in 0..h
+        {
+            for x in 0..w
+            {
+                let coord = (x as i8 + corner.0, y as i8 + corner.1);
+                f(x, y, w, self.representations.positions[p].get(&coord).cloned());
+            }
+        }
+    }
+
+    fn orientation_index(orientation: Direction) -> usize
+    {
+        match orientation
+        {
+            Direction::UP => { 0 },
+            Direction::LEFT => { 1 },
+            Direction::DOWN => { 2 },
+            Direction::RIGHT => { 3 },
+            _ =>
+            {
+                warn!("Drawing for Orientation: {}", orientation);
+                0
+            }
+        }
+    }
 }
 impl Serializable for Morphology
 {
@@ -748,10 +1461,14 @@ impl Serializable for Morphology
 #[cfg(test)]
 mod test
 {
+    use ::actuators::*;
     use ::genetics::*;
     use ::morphology::*;
+    use ::sensors::*;
     use ::serialization::*;
+    use ::traits::*;
     use ::types::*;
+    use std::collections::{HashSet, HashMap};
     #[test]
     fn test_adjacency_vertical()
     {
@@ -811,6 +1528,322 @@ mod test
         debug!("{:?}", morph);
     }
 
+    #[test]
+    fn test_corner_for_orientation()
+    {
+        let chromosomes = vec![[0, 0x09, 0x6A, 0xAD],
+                               [0, 0x0B, 0xBE, 0xDA],
+                               [0,    0, 0xBE, 0xEF],
+                               [0,    0, 0xDB, 0xAD]];
+        let morph = Morphology::new(&chromosomes, &TranslationTable::new());
+
+        let orientations = [Direction::UP, Direction::LEFT, Direction::DOWN, Direction::RIGHT];
+        for p in 0..4
+        {
+            // The corner is the bottom-left of the rotated cells
+            let positions = &morph.representations.positions[p];
+            let minx = positions.keys().map(|c| c.0).min().unwrap();
+            let miny = positions.keys().map(|c| c.1).min().unwrap();
+            assert_eq!(morph.get_corner_for_orientation(orientations[p]), (minx, miny));
+            assert_eq!(positions.len(), morph.get_total_cells());
+        }
+        assert_eq!(morph.get_corner(), morph.get_corner_for_orientation(Direction::UP));
+    }
+
+    #[test]
+    fn test_morphology_validate_and_repair()
+    {
+        // Second cell points back to the head, so the third one lands on top of it
+        let chromosomes = vec![[0, 0x08, 0, 0],
+                               [0, 0x04, 0, 0],
+                               [0,    0, 0, 0]];
+
+        let mut table = TranslationTable::new();
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![MorphologyIssue::Overlapping(2)]);
+        // The head keeps its spot
+        assert_eq!(morph.representations.positions[0].get(&(0, 0)), Some(&0));
+
+        table.set_repair(MorphologyRepair::DropUnreachable);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.get_total_cells(), 2);
+        assert_eq!(morph.get_dimensions(), (2, 1));
+
+        table.set_repair(MorphologyRepair::Relocate);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.get_total_cells(), 3);
+        assert_eq!(morph.representations.positions[0].get(&(-1, 0)), Some(&2));
+        assert_eq!(morph.get_dimensions(), (3, 1));
+    }
+
+    #[test]
+    fn test_morphology_repair_random_genomes()
+    {
+        for decoder in &[GenomeDecoderKind::Adjacency, GenomeDecoderKind::Grid]
+        {
+            for repair in &[MorphologyRepair::DropUnreachable, MorphologyRepair::Relocate]
+            {
+                let mut table = TranslationTable::new();
+                table.set_decoder_kind(*decoder);
+                table.set_repair(*repair);
+                for seed in 0..50
+                {
+                    let mut ctx = PolyminiRandomCtx::from_seed([seed, 3, 5, 7], "Test Repair".to_owned());
+                    let morph = Morphology::new_random(&table, &mut ctx, 12);
+                    assert_eq!(morph.validate(), vec![]);
+                    assert_eq!(morph.representations.positions[0].len(), morph.get_total_cells());
+                }
+            }
+        }
+    }
+
+    fn render_table() -> TranslationTable
+    {
+        let mut master = HashMap::new();
+        master.insert((TraitTier::TierI, 1), PolyminiTrait::PolyminiActuator(ActuatorTag::MoveHorizontal));
+        master.insert((TraitTier::TierI, 2), PolyminiTrait::PolyminiSensor(SensorTag::Vision));
+        master.insert((TraitTier::TierI, 3), PolyminiTrait::PolyminiSimpleTrait(TraitTag::SpeedTrait));
+        let active: HashSet<TTKey> = master.keys().cloned().collect();
+        TranslationTable::new_from(&master, &active)
+    }
+
+    #[test]
+    fn test_morphology_to_ascii()
+    {
+        // Expected shape:
+        // Hv
+        // .>
+        let chromosomes = vec![[0, 0x08, 1, 0],
+                               [0, 0x02, 2, 0],
+                               [0,    0, 3, 0]];
+        let morph = Morphology::new(&chromosomes, &render_table());
+
+        assert_eq!(morph.to_ascii(Direction::UP), "Hv\n.>\n");
+        assert_eq!(morph.to_ascii(Direction::DOWN), ">.\nvH\n");
+        for o in &[Direction::LEFT, Direction::RIGHT]
+        {
+            let ascii = morph.to_ascii(*o);
+            assert_eq!(ascii.lines().count(), 2);
+            assert_eq!(ascii.chars().filter(|c| *c == '.').count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_morphology_to_svg()
+    {
+        let chromosomes = vec![[0, 0x08, 1, 0],
+                               [0, 0x08, 2, 0],
+                               [0,    0, 3, 0]];
+        let morph = Morphology::new(&chromosomes, &render_table());
+
+        let svg = morph.to_svg(Direction::UP);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("width=\"48\" height=\"16\""));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert_eq!(svg.matches("<polygon").count(), 1);
+        assert_eq!(svg.matches("<circle").count(), 1);
+        assert!(svg.contains(PolyminiTrait::PolyminiSensor(SensorTag::Vision).get_colour()));
+
+        // Rotated a quarter, the body stands up
+        assert!(morph.to_svg(Direction::LEFT).contains("width=\"16\" height=\"48\""));
+    }
+
+    #[test]
+    fn test_grid_decoder()
+    {
+        // Head, then x = 1 / y = 0 and x = 1 / y = 1
+        let chromosomes = vec![[0, 0x00, 1, 0],
+                               [0, 0x90, 2, 0],
+                               [0, 0x91, 3, 0]];
+        let mut table = render_table();
+        table.set_decoder_kind(GenomeDecoderKind::Grid);
+        assert_eq!(table.get_repair(), MorphologyRepair::Relocate);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.to_ascii(Direction::UP), "Hv\n.>\n");
+
+        // Same chromosomes grown through adjacency don't go anywhere
+        table.set_decoder_kind(GenomeDecoderKind::Adjacency);
+        assert_eq!(Morphology::new(&chromosomes, &table).get_total_cells(), 1);
+
+        // A cell out on its own is disconnected unless the body is repaired
+        let chromosomes = vec![[0, 0x00, 1, 0],
+                               [0, 0xB2, 2, 0]];
+        table.set_decoder_kind(GenomeDecoderKind::Grid);
+        table.set_repair(MorphologyRepair::NoRepair);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![MorphologyIssue::Disconnected(1)]);
+
+        table.set_repair(MorphologyRepair::Relocate);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.get_total_cells(), 2);
+    }
+
+    #[test]
+    fn test_symmetry()
+    {
+        // Half body:
+        // Hv
+        // .>
+        let chromosomes = vec![[0, 0x08, 1, 0],
+                               [0, 0x02, 2, 0],
+                               [0,    0, 3, 0]];
+        let mut table = render_table();
+
+        table.set_symmetry(Symmetry::Mirror);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.to_ascii(Direction::UP), "vHv\n>.>\n");
+
+        // The quarter right of the head, turned around it
+        table.set_symmetry(Symmetry::Radial);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.to_ascii(Direction::UP), ">v>\nvHv\n>v>\n");
+        assert_eq!(morph.get_corner(), (-1, -1));
+
+        // Only the half body is in the genome
+        assert_eq!(morph.original_chromosome.len(), 3);
+    }
+
+    #[test]
+    fn test_symmetry_random_genomes()
+    {
+        let mut table = TranslationTable::new();
+        table.set_repair(MorphologyRepair::Relocate);
+        table.set_symmetry(Symmetry::Mirror);
+        for seed in 0..50
+        {
+            let mut ctx = PolyminiRandomCtx::from_seed([seed, 11, 13, 17], "Test Symmetry".to_owned());
+            let mut morph = Morphology::new_random(&table, &mut ctx, 12);
+            morph.mutate(&mut ctx, &table);
+            assert_eq!(morph.validate(), vec![]);
+
+            let positions = &morph.representations.positions[0];
+            for (c, i) in positions
+            {
+                let mirrored = positions.get(&(-c.0, c.1)).unwrap();
+                assert_eq!(morph.representations.cells[*i].pm_trait.pm_trait,
+                           morph.representations.cells[*mirrored].pm_trait.pm_trait);
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetry_folding()
+    {
+        // Cells on both sides of the head, folded they land on the same spot
+        let chromosomes = vec![[0, 0x0C, 1, 0],
+                               [0,    0, 2, 0],
+                               [0,    0, 3, 0]];
+        let mut table = render_table();
+        table.set_symmetry(Symmetry::Mirror);
+
+        // Nothing is dropped unless the repair policy says so
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.get_total_cells(), 5);
+        assert_eq!(morph.validate(), vec![MorphologyIssue::Overlapping(2), MorphologyIssue::Overlapping(4)]);
+
+        table.set_repair(MorphologyRepair::DropUnreachable);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.get_total_cells(), 3);
+
+        table.set_repair(MorphologyRepair::Relocate);
+        let morph = Morphology::new(&chromosomes, &table);
+        assert_eq!(morph.validate(), vec![]);
+        assert_eq!(morph.get_total_cells(), 5);
+        assert_eq!(morph.get_dimensions(), (5, 1));
+    }
+
+    #[test]
+    fn test_radial_symmetry_random_genomes()
+    {
+        let mut table = TranslationTable::new();
+        table.set_repair(MorphologyRepair::Relocate);
+        table.set_symmetry(Symmetry::Radial);
+        for seed in 0..50
+        {
+            let mut ctx = PolyminiRandomCtx::from_seed([seed, 19, 23, 29], "Test Radial Symmetry".to_owned());
+            let morph = Morphology::new_random(&table, &mut ctx, 12);
+            assert_eq!(morph.validate(), vec![]);
+
+            // A single head, every other cell has three copies turned around it
+            let positions = &morph.representations.positions[0];
+            assert_eq!(positions.len(), morph.get_total_cells());
+            assert_eq!(positions.get(&(0, 0)), Some(&0));
+            assert_eq!((morph.get_total_cells() - 1) % 4, 0);
+            for (c, i) in positions
+            {
+                let turned = positions.get(&(-c.1, c.0)).unwrap();
+                assert_eq!(morph.representations.cells[*i].pm_trait.pm_trait,
+                           morph.representations.cells[*turned].pm_trait.pm_trait);
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetry_serialization()
+    {
+        let mut ctx = SerializationCtx::new();
+        for symmetry in &[Symmetry::NoSymmetry, Symmetry::Mirror, Symmetry::Radial]
+        {
+            assert_eq!(Symmetry::new_from_json(&symmetry.serialize(&mut ctx), &mut ctx), Some(*symmetry));
+        }
+    }
+
+    #[test]
+    fn test_genome_decoder_serialization()
+    {
+        let mut ctx = SerializationCtx::new();
+        for decoder in &[GenomeDecoderKind::Adjacency, GenomeDecoderKind::Grid]
+        {
+            assert_eq!(GenomeDecoderKind::new_from_json(&decoder.serialize(&mut ctx), &mut ctx), Some(*decoder));
+        }
+    }
+
+    #[test]
+    fn test_translation_table_options()
+    {
+        let mut ctx = SerializationCtx::new();
+        let mut json_obj = pmJsonObject::new();
+        TranslationTable::new().write_options(&mut json_obj, &mut ctx);
+        assert_eq!(json_obj.len(), 0);
+
+        // Grid repairs by default, so turning it off has to be written down
+        let mut table = TranslationTable::new();
+        table.set_decoder_kind(GenomeDecoderKind::Grid);
+        table.set_repair(MorphologyRepair::NoRepair);
+        table.set_symmetry(Symmetry::Mirror);
+        table.write_options(&mut json_obj, &mut ctx);
+        assert_eq!(json_obj.len(), 3);
+
+        let mut read = TranslationTable::new();
+        read.read_options(&json_obj);
+        assert_eq!(read.get_decoder_kind(), GenomeDecoderKind::Grid);
+        assert_eq!(read.get_repair(), MorphologyRepair::NoRepair);
+        assert_eq!(read.get_symmetry(), Symmetry::Mirror);
+
+        // Invalid options are ignored
+        json_obj.insert("Symmetry".to_string(), "spiral".to_json());
+        let mut read = TranslationTable::new();
+        read.read_options(&json_obj);
+        assert_eq!(read.get_symmetry(), Symmetry::NoSymmetry);
+    }
+
+    #[test]
+    fn test_morphology_repair_serialization()
+    {
+        let mut ctx = SerializationCtx::new();
+        for repair in &[MorphologyRepair::NoRepair, MorphologyRepair::DropUnreachable, MorphologyRepair::Relocate]
+        {
+            assert_eq!(MorphologyRepair::new_from_json(&repair.serialize(&mut ctx), &mut ctx), Some(*repair));
+        }
+    }
+
     #[test]
     fn test_morphology_crossover()
     {
@@ -0,0 +1,763 @@
+//
+// NEAT style networks, the genome is a list of nodes and the connections between them
+// and the topology grows through mutation
+//
+use ::actuators::*;
+use ::control::{Activation, ControlConfig};
+use ::genetics::*;
+use ::sensors::*;
+use ::serialization::*;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+pub type NodeId = u64;
+pub type Innovation = u64;
+
+// Ids are hashes of what the gene stands for, so the same structural change gets the same
+// id in every individual without a global registry. They are saved with the genome so the
+// hash is FNV-1a over a fixed little-endian encoding, not std's Hash which can change with
+// the platform or the compiler. They are kept to 53 bits so they survive a round trip
+// through JSON numbers
+struct IdHasher
+{
+    state: u64,
+}
+impl IdHasher
+{
+    fn new() -> IdHasher
+    {
+        IdHasher { state: 0xCBF2_9CE4_8422_2325 }
+    }
+
+    fn bytes(mut self, bytes: &[u8]) -> IdHasher
+    {
+        for b in bytes
+        {
+            self.state ^= *b as u64;
+            self.state = self.state.wrapping_mul(0x0100_0000_01B3);
+        }
+        self
+    }
+
+    fn u64(self, v: u64) -> IdHasher
+    {
+        let mut le = [0u8; 8];
+        for i in 0..8
+        {
+            le[i] = (v >> (8 * i)) as u8;
+        }
+        self.bytes(&le)
+    }
+
+    // Length first so ("ab", "c") and ("a", "bc") don't collide
+    fn str(self, s: &str) -> IdHasher
+    {
+        self.u64(s.len() as u64).bytes(s.as_bytes())
+    }
+
+    fn coord(self, c: (i8, i8)) -> IdHasher
+    {
+        self.bytes(&[c.0 as u8, c.1 as u8])
+    }
+
+    fn finish(&self) -> u64
+    {
+        self.state & 0x1F_FFFF_FFFF_FFFF
+    }
+}
+
+// Inputs are identified by the sensor feeding them, so weights follow a sensor around when
+// others are gained or lost
+fn input_ids(sensors: &Vec<Sensor>) -> Vec<NodeId>
+{
+    Sensor::get_input_keys(sensors).iter().map(|&(tag, occurrence, slot)|
+                                               {
+                                                   IdHasher::new().str("in").str(&tag.to_string())
+                                                                  .u64(occurrence as u64).u64(slot as u64).finish()
+                                               }).collect()
+}
+
+fn output_ids(actuators: &Vec<Actuator>) -> Vec<NodeId>
+{
+    let mut occurrences = HashMap::new();
+    let mut ids = vec![];
+    for actuator in actuators
+    {
+        let occurrence = {
+            let o = occurrences.entry((actuator.get_tag(), actuator.get_coords())).or_insert(0);
+            *o += 1;
+            *o
+        };
+        ids.push(IdHasher::new().str("out").str(&actuator.get_tag().to_string())
+                                .coord(actuator.get_coords()).u64(occurrence as u64).finish());
+    }
+    ids
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind
+{
+    Input,
+    Hidden,
+    Output,
+}
+impl Serializable for NodeKind
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl Deserializable for NodeKind
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<NodeKind>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "input"  => { Some(NodeKind::Input) },
+                    "hidden" => { Some(NodeKind::Hidden) },
+                    "output" => { Some(NodeKind::Output) },
+                    _ =>
+                    {
+                        error!("Unknown NodeKind {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for NodeKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NodeGene
+{
+    pub id: NodeId,
+    pub kind: NodeKind,
+    pub bias: f32,
+    pub activation: Activation,
+}
+impl NodeGene
+{
+    pub fn new(id: NodeId, kind: NodeKind, bias: f32, activation: Activation) -> NodeGene
+    {
+        NodeGene { id: id, kind: kind, bias: bias, activation: activation }
+    }
+}
+impl Serializable for NodeGene
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Id".to_owned(), self.id.to_json());
+        json_obj.insert("Kind".to_owned(), self.kind.serialize(ctx));
+        json_obj.insert("Bias".to_owned(), self.bias.to_json());
+        json_obj.insert("Activation".to_owned(), self.activation.serialize(ctx));
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for NodeGene
+{
+    fn new_from_json(json: &Json, ctx: &mut SerializationCtx) -> Option<NodeGene>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let id = json_obj.get("Id").and_then(|v| v.as_u64());
+                let kind = json_obj.get("Kind").and_then(|v| NodeKind::new_from_json(v, ctx));
+                let bias = json_obj.get("Bias").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
+                let activation = json_obj.get("Activation").and_then(|v| Activation::new_from_json(v, ctx))
+                                                           .unwrap_or(Activation::Sigmoid);
+                match (id, kind)
+                {
+                    (Some(id), Some(kind)) =>
+                    {
+                        Some(NodeGene::new(id, kind, bias, activation))
+                    },
+                    _ =>
+                    {
+                        error!("Invalid NodeGene {}", json);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConnectionGene
+{
+    pub innovation: Innovation,
+    pub from: NodeId,
+    pub to: NodeId,
+    pub weight: f32,
+    pub enabled: bool,
+}
+impl ConnectionGene
+{
+    pub fn new(from: NodeId, to: NodeId, weight: f32) -> ConnectionGene
+    {
+        ConnectionGene { innovation: IdHasher::new().u64(from).u64(to).finish(), from: from, to: to, weight: weight, enabled: true }
+    }
+}
+impl Serializable for ConnectionGene
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Innovation".to_owned(), self.innovation.to_json());
+        json_obj.insert("From".to_owned(), self.from.to_json());
+        json_obj.insert("To".to_owned(), self.to.to_json());
+        json_obj.insert("Weight".to_owned(), self.weight.to_json());
+        json_obj.insert("Enabled".to_owned(), self.enabled.to_json());
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for ConnectionGene
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<ConnectionGene>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let from = json_obj.get("From").and_then(|v| v.as_u64());
+                let to = json_obj.get("To").and_then(|v| v.as_u64());
+                let weight = json_obj.get("Weight").and_then(|v| v.as_f64()).unwrap_or(0.0) as f32;
+                let enabled = json_obj.get("Enabled").and_then(|v| v.as_boolean()).unwrap_or(true);
+                match (from, to)
+                {
+                    (Some(from), Some(to)) =>
+                    {
+                        let mut gene = ConnectionGene::new(from, to, weight);
+                        gene.enabled = enabled;
+                        Some(gene)
+                    },
+                    _ =>
+                    {
+                        error!("Invalid ConnectionGene {}", json);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+
+// Attempts at finding a valid pair of nodes for a new connection
+const ADD_CONNECTION_TRIES: usize = 20;
+
+#[derive(Clone, Debug)]
+pub struct NeatNetwork
+{
+    nodes: Vec<NodeGene>,
+    // Kept sorted by innovation
+    connections: Vec<ConnectionGene>,
+
+    // Node for each input slot and each actuator, in order
+    inputs: Vec<NodeId>,
+    outputs: Vec<NodeId>,
+}
+impl NeatNetwork
+{
+    // Minimal network, every input connected to every output
+    pub fn new(sensors: &Vec<Sensor>, actuators: &Vec<Actuator>, config: &ControlConfig,
+               random_ctx: &mut PolyminiRandomCtx) -> NeatNetwork
+    {
+        let mut network = NeatNetwork { nodes: vec![], connections: vec![], inputs: vec![], outputs: vec![] };
+        network.set_interface(sensors, actuators, config, random_ctx);
+        network
+    }
+
+    pub fn get_nodes(&self) -> &Vec<NodeGene>
+    {
+        &self.nodes
+    }
+
+    pub fn get_connections(&self) -> &Vec<ConnectionGene>
+    {
+        &self.connections
+    }
+
+    fn has_node(&self, id: NodeId) -> bool
+    {
+        self.nodes.iter().any(|n| n.id == id)
+    }
+
+    fn has_connection(&self, innovation: Innovation) -> bool
+    {
+        self.connections.iter().any(|c| c.innovation == innovation)
+    }
+
+    fn add_connection_gene(&mut self, gene: ConnectionGene)
+    {
+        self.connections.push(gene);
+        self.connections.sort_by_key(|c| c.innovation);
+    }
+
+    // Match the input and output nodes to a new set of sensors and actuators, whatever is
+    // connected to sensors and actuators that are kept is left untouched
+    pub fn set_interface(&mut self, sensors: &Vec<Sensor>, actuators: &Vec<Actuator>, config: &ControlConfig,
+                         random_ctx: &mut PolyminiRandomCtx)
+    {
+        let new_inputs = input_ids(sensors);
+        let new_outputs = output_ids(actuators);
+
+        let keep: HashSet<NodeId> = new_inputs.iter().chain(new_outputs.iter()).cloned().collect();
+        let removed: HashSet<NodeId> = self.nodes.iter().filter(|n| n.kind != NodeKind::Hidden && !keep.contains(&n.id))
+                                                        .map(|n| n.id).collect();
+        self.nodes.retain(|n| !removed.contains(&n.id));
+        self.connections.retain(|c| !removed.contains(&c.from) && !removed.contains(&c.to));
+
+        let mut added_inputs = vec![];
+        for id in &new_inputs
+        {
+            if !self.has_node(*id)
+            {
+                self.nodes.push(NodeGene::new(*id, NodeKind::Input, 0.0, Activation::Identity));
+                added_inputs.push(*id);
+            }
+        }
+
+        // New outputs listen to every input, new inputs talk to every output that was already there
+        let mut added_outputs = vec![];
+        for id in &new_outputs
+        {
+            if !self.has_node(*id)
+            {
+                let bias = random_ctx.gen_range(-0.5, 0.5);
+                self.nodes.push(NodeGene::new(*id, NodeKind::Output, bias, config.output_activation));
+                added_outputs.push(*id);
+            }
+        }
+        for o in &new_outputs
+        {
+            for i in &new_inputs
+            {
+                if added_outputs.contains(o) || added_inputs.contains(i)
+                {
+                    let gene = ConnectionGene::new(*i, *o, random_ctx.gen_range(-0.5, 0.5));
+                    if !self.has_connection(gene.innovation)
+                    {
+                        self.add_connection_gene(gene);
+                    }
+                }
+            }
+        }
+
+        self.inputs = new_inputs;
+        self.outputs = new_outputs;
+    }
+
+    // Is there a path from 'from' to 'to' (disabled connections included, they can come back)
+    fn reaches(&self, from: NodeId, to: NodeId) -> bool
+    {
+        let mut visited = HashSet::new();
+        let mut open = vec![from];
+        while let Some(n) = open.pop()
+        {
+            if n == to
+            {
+                return true
+            }
+            if !visited.insert(n)
+            {
+                continue
+            }
+            for c in &self.connections
+            {
+                if c.from == n
+                {
+                    open.push(c.to);
+                }
+            }
+        }
+        false
+    }
+
+    // Non-input nodes in an order where every node comes after all of its inputs
+    fn evaluation_order(&self) -> Vec<NodeId>
+    {
+        let mut pending: HashMap<NodeId, usize> = HashMap::new();
+        for n in &self.nodes
+        {
+            pending.insert(n.id, 0);
+        }
+        for c in self.connections.iter().filter(|c| c.enabled)
+        {
+            if let Some(p) = pending.get_mut(&c.to)
+            {
+                *p += 1;
+            }
+        }
+
+        let mut order = vec![];
+        let mut ready: Vec<NodeId> = self.nodes.iter().filter(|n| pending[&n.id] == 0).map(|n| n.id).collect();
+        while ready.len() > 0
+        {
+            let n = ready.remove(0);
+            order.push(n);
+            for c in self.connections.iter().filter(|c| c.enabled && c.from == n)
+            {
+                if let Some(p) = pending.get_mut(&c.to)
+                {
+                    *p -= 1;
+                    if *p == 0
+                    {
+                        ready.push(c.to);
+                    }
+                }
+            }
+        }
+
+        let inputs: HashSet<NodeId> = self.nodes.iter().filter(|n| n.kind == NodeKind::Input).map(|n| n.id).collect();
+        order.into_iter().filter(|n| !inputs.contains(n)).collect()
+    }
+
+    // Outputs come back centered around 0 like the ones of the layered networks
+    pub fn compute(&self, ins: &Vec<f32>) -> Vec<f32>
+    {
+        let mut values: HashMap<NodeId, f32> = HashMap::new();
+        for (i, id) in self.inputs.iter().enumerate()
+        {
+            values.insert(*id, *ins.get(i).unwrap_or(&0.0));
+        }
+
+        let genes: HashMap<NodeId, &NodeGene> = self.nodes.iter().map(|n| (n.id, n)).collect();
+        for id in self.evaluation_order()
+        {
+            let node = genes[&id];
+            let mut v = node.bias;
+            for c in self.connections.iter().filter(|c| c.enabled && c.to == id)
+            {
+                v += c.weight * values.get(&c.from).cloned().unwrap_or(0.0);
+            }
+            values.insert(id, node.activation.apply(v));
+        }
+
+        self.outputs.iter().map(|id|
+                                {
+                                    let v = values.get(id).cloned().unwrap_or(0.0);
+                                    genes.get(id).map(|n| n.activation.center(v)).unwrap_or(v)
+                                }).collect()
+    }
+
+    // Split an enabled connection in two with a new node in between
+    fn add_node(&mut self, config: &ControlConfig, random_ctx: &mut PolyminiRandomCtx)
+    {
+        let enabled: Vec<usize> = (0..self.connections.len()).filter(|&i| self.connections[i].enabled).collect();
+        if enabled.len() == 0
+        {
+            return
+        }
+        let inx = enabled[random_ctx.gen_range(0, enabled.len())];
+        self.connections[inx].enabled = false;
+        let split = self.connections[inx].clone();
+
+        let mut n = 0;
+        let mut id = IdHasher::new().str("node").u64(split.innovation).u64(n).finish();
+        while self.has_node(id)
+        {
+            n += 1;
+            id = IdHasher::new().str("node").u64(split.innovation).u64(n).finish();
+        }
+
+        self.nodes.push(NodeGene::new(id, NodeKind::Hidden, 0.0, config.hidden_activation));
+        self.add_connection_gene(ConnectionGene::new(split.from, id, 1.0));
+        self.add_connection_gene(ConnectionGene::new(id, split.to, split.weight));
+    }
+
+    // Connect two nodes that weren't, without closing a loop
+    fn add_connection(&mut self, random_ctx: &mut PolyminiRandomCtx)
+    {
+        let sources: Vec<NodeId> = self.nodes.iter().filter(|n| n.kind != NodeKind::Output).map(|n| n.id).collect();
+        let targets: Vec<NodeId> = self.nodes.iter().filter(|n| n.kind != NodeKind::Input).map(|n| n.id).collect();
+        if sources.len() == 0 || targets.len() == 0
+        {
+            return
+        }
+
+        for _ in 0..ADD_CONNECTION_TRIES
+        {
+            let from = sources[random_ctx.gen_range(0, sources.len())];
+            let to = targets[random_ctx.gen_range(0, targets.len())];
+            let gene = ConnectionGene::new(from, to, random_ctx.gen_range(-0.5, 0.5));
+
+            if from == to || self.has_connection(gene.innovation) || self.reaches(to, from)
+            {
+                continue
+            }
+            self.add_connection_gene(gene);
+            return
+        }
+    }
+
+    pub fn mutate(&mut self, random_ctx: &mut PolyminiRandomCtx, config: &ControlConfig, sensors: &Vec<Sensor>,
+                  actuators: &Vec<Actuator>)
+    {
+        self.set_interface(sensors, actuators, config, random_ctx);
+
+        if config.add_node_mutation > 0.0 && random_ctx.gen_range(0.0, 1.0) < config.add_node_mutation
+        {
+            self.add_node(config, random_ctx);
+        }
+        else if config.add_connection_mutation > 0.0 && random_ctx.gen_range(0.0, 1.0) < config.add_connection_mutation
+        {
+            self.add_connection(random_ctx);
+        }
+        else
+        {
+            // Mutate a Weight or a Bias
+            let biased: Vec<usize> = (0..self.nodes.len()).filter(|&i| self.nodes[i].kind != NodeKind::Input).collect();
+            let total = self.connections.len() + biased.len();
+            if total == 0
+            {
+                return
+            }
+            let inx = random_ctx.gen_range(0, total);
+            if inx < self.connections.len()
+            {
+                let weight = self.connections[inx].weight;
+                self.connections[inx].weight = config.weight_mutation.mutate_value(weight, random_ctx);
+            }
+            else
+            {
+                let node = biased[inx - self.connections.len()];
+                let bias = self.nodes[node].bias;
+                self.nodes[node].bias = config.weight_mutation.mutate_value(bias, random_ctx);
+            }
+        }
+    }
+
+    // Genes are lined up by innovation, matching genes come from either parent and disjoint
+    // or excess genes come from self
+    pub fn crossover(&self, other: &NeatNetwork, random_ctx: &mut PolyminiRandomCtx, config: &ControlConfig,
+                     sensors: &Vec<Sensor>, actuators: &Vec<Actuator>) -> NeatNetwork
+    {
+        let other_connections: HashMap<Innovation, &ConnectionGene> = other.connections.iter().map(|c| (c.innovation, c)).collect();
+        let other_nodes: HashMap<NodeId, &NodeGene> = other.nodes.iter().map(|n| (n.id, n)).collect();
+
+        let connections = self.connections.iter().map(|c|
+                                                      {
+                                                          match other_connections.get(&c.innovation)
+                                                          {
+                                                              Some(o) if random_ctx.gen_range(0.0, 1.0) < 0.5 => { (*o).clone() },
+                                                              _ => { c.clone() }
+                                                          }
+                                                      }).collect();
+
+        let nodes = self.nodes.iter().map(|n|
+                                          {
+                                              match other_nodes.get(&n.id)
+                                              {
+                                                  Some(o) if random_ctx.gen_range(0.0, 1.0) < 0.5 => { (*o).clone() },
+                                                  _ => { n.clone() }
+                                              }
+                                          }).collect();
+
+        let mut child = NeatNetwork { nodes: nodes, connections: connections,
+                                      inputs: self.inputs.clone(), outputs: self.outputs.clone() };
+        child.set_interface(sensors, actuators, config, random_ctx);
+        child
+    }
+}
+impl Serializable for NeatNetwork
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Inputs".to_owned(), self.inputs.to_json());
+        json_obj.insert("Outputs".to_owned(), self.outputs.to_json());
+        json_obj.insert("Nodes".to_owned(), Json::Array(self.nodes.iter().map(|n| n.serialize(ctx)).collect()));
+        json_obj.insert("Connections".to_owned(), Json::Array(self.connections.iter().map(|c| c.serialize(ctx)).collect()));
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for NeatNetwork
+{
+    fn new_from_json(json: &Json, ctx: &mut SerializationCtx) -> Option<NeatNetwork>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                if !JsonUtils::verify_has_fields(&json_obj, &vec!["Inputs".to_owned(), "Outputs".to_owned(),
+                                                                  "Nodes".to_owned(), "Connections".to_owned()])
+                {
+                    return None
+                }
+
+                let ids = |key: &str| -> Vec<NodeId>
+                {
+                    json_obj.get(key).and_then(|v| v.as_array())
+                                     .map(|arr| arr.iter().filter_map(|v| v.as_u64()).collect())
+                                     .unwrap_or(vec![])
+                };
+                let inputs = ids("Inputs");
+                let outputs = ids("Outputs");
+
+                let mut nodes = vec![];
+                for n in json_obj.get("Nodes").and_then(|v| v.as_array()).unwrap_or(&vec![])
+                {
+                    match NodeGene::new_from_json(n, ctx)
+                    {
+                        Some(node) => { nodes.push(node); },
+                        None => { return None }
+                    }
+                }
+
+                let mut connections = vec![];
+                for c in json_obj.get("Connections").and_then(|v| v.as_array()).unwrap_or(&vec![])
+                {
+                    match ConnectionGene::new_from_json(c, ctx)
+                    {
+                        Some(connection) => { connections.push(connection); },
+                        None => { return None }
+                    }
+                }
+
+                let mut network = NeatNetwork { nodes: nodes, connections: vec![], inputs: inputs, outputs: outputs };
+                for c in connections
+                {
+                    if network.reaches(c.to, c.from)
+                    {
+                        error!("NeatNetwork - Connection {} closes a loop", c.innovation);
+                        return None
+                    }
+                    network.add_connection_gene(c);
+                }
+                Some(network)
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    use ::actuators::*;
+    use ::control::*;
+    use ::genetics::*;
+    use ::serialization::*;
+
+    fn neat_config() -> ControlConfig
+    {
+        let mut config = ControlConfig::defaults();
+        config.kind = ControlKind::Neat;
+        config.add_node_mutation = 0.5;
+        config.add_connection_mutation = 0.5;
+        config
+    }
+
+    #[test]
+    fn test_ids_are_stable()
+    {
+        // FNV-1a reference value
+        assert_eq!(IdHasher::new().bytes(b"a").state, 0xAF63_DC4C_8601_EC8C);
+
+        // Saved genomes rely on these not changing
+        assert_eq!(ConnectionGene::new(1, 2, 0.0).innovation, 0x17_9803_63C8_E066);
+        assert!(IdHasher::new().str("ab").str("c").finish() != IdHasher::new().str("a").str("bc").finish());
+    }
+
+    #[test]
+    fn test_neat_innovations()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let config = neat_config();
+
+        let n1 = NeatNetwork::new(&s_list, &a_list, &config, &mut PolyminiRandomCtx::new_unseeded("Neat 1".to_string()));
+        let n2 = NeatNetwork::new(&s_list, &a_list, &config, &mut PolyminiRandomCtx::new_unseeded("Neat 2".to_string()));
+
+        // Same structure, same innovations regardless of the weights
+        let i1: Vec<Innovation> = n1.get_connections().iter().map(|c| c.innovation).collect();
+        let i2: Vec<Innovation> = n2.get_connections().iter().map(|c| c.innovation).collect();
+        assert_eq!(i1.len(), 4);
+        assert_eq!(i1, i2);
+        assert_eq!(n1.compute(&vec![0.1, 0.2]).len(), 2);
+    }
+
+    #[test]
+    fn test_neat_mutate_crossover()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let config = neat_config();
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Neat Mutation".to_string());
+
+        let mut n1 = NeatNetwork::new(&s_list, &a_list, &config, &mut ctx);
+        let mut n2 = n1.clone();
+        for _ in 0..30
+        {
+            n1.mutate(&mut ctx, &config, &s_list, &a_list);
+            n2.mutate(&mut ctx, &config, &s_list, &a_list);
+        }
+        assert!(n1.get_nodes().len() > 4);
+
+        let child = n1.crossover(&n2, &mut ctx, &config, &s_list, &a_list);
+        // Structure comes from the first parent
+        assert_eq!(child.get_connections().len(), n1.get_connections().len());
+        for c in child.get_connections()
+        {
+            assert!(!child.reaches(c.to, c.from));
+        }
+        assert_eq!(child.compute(&vec![0.1, 0.2]).len(), 2);
+
+        let mut ser_ctx = SerializationCtx::new();
+        let json = child.serialize(&mut ser_ctx);
+        let copy = NeatNetwork::new_from_json(&json, &mut ser_ctx).unwrap();
+        assert_eq!(json.pretty().to_string(), copy.serialize(&mut ser_ctx).pretty().to_string());
+        assert_eq!(child.compute(&vec![0.1, 0.2]), copy.compute(&vec![0.1, 0.2]));
+    }
+
+    #[test]
+    fn test_neat_sensor_change()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let config = neat_config();
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Neat Sensors".to_string());
+
+        let mut n1 = NeatNetwork::new(&s_list, &a_list, &config, &mut ctx);
+        let kept = n1.get_connections().iter().find(|c| c.from == input_ids(&s_list)[1]).unwrap().clone();
+
+        // Losing PositionX doesn't touch what was learned for PositionY
+        let new_s_list = vec![ Sensor::new(SensorTag::PositionY, 0), Sensor::new(SensorTag::GSensor, 1)];
+        n1.set_interface(&new_s_list, &a_list, &config, &mut ctx);
+        let after = n1.get_connections().iter().find(|c| c.innovation == kept.innovation).unwrap();
+        assert_eq!(after.weight, kept.weight);
+        assert_eq!(n1.get_connections().len(), 2);
+    }
+}
@@ -7,7 +7,7 @@ use self::nalgebra::{Isometry2,  Point2, Vector1, zero};
 use self::nalgebra::{Translation, Rotation, Rotation2, RotationTo};
 use self::nalgebra::{distance};
 
-use self::ncollide::query::{Proximity, Contact};
+use self::ncollide::query::{Proximity, Contact, Ray};
 use self::ncollide::shape::{Compound, Compound2, Cuboid, Shape2, ShapeHandle2};
 use self::ncollide::world::{CollisionWorld, CollisionWorld2,
                             CollisionGroups, CollisionObject2, GeometricQueryType};
@@ -171,8 +171,9 @@ impl PhysicsActionAccumulator
             },
             Direction::ROTATION =>
             {
-                //self.spin += impulse;
-                // Error?
+                // Only Rotate actuators produce a rotation impulse, the rest
+                // contribute through their torque
+                self.spin += impulse;
             }
             _ => panic!("Incorrect direction for impulse {:?}", dir)
         }
@@ -222,8 +223,39 @@ impl PhysicsActionAccumulator
             Action::NoAction
         }
     }
+
+    // Like to_action, but spin that loses to a translation isn't lost: it builds up
+    // on 'carried' and turns the body a quarter once it reaches QUARTER_TURN_TORQUE.
+    // Returns the action and the torque to carry into the next step
+    fn to_action_with_torque(&self, carried: f32) -> (Action, f32)
+    {
+        let action = self.to_action();
+        match action
+        {
+            Action::MoveAction(MoveAction::Move(Direction::ROTATION, _, _)) =>
+            {
+                (action, 0.0)
+            },
+            _ =>
+            {
+                let torque = carried + self.spin;
+                if torque.abs() >= QUARTER_TURN_TORQUE
+                {
+                    debug!("MoveAccum::result: Accumulated Rotation {}", torque);
+                    (Action::MoveAction(MoveAction::Move(Direction::ROTATION, torque, 0.0)), 0.0)
+                }
+                else
+                {
+                    (action, torque)
+                }
+            }
+        }
+    }
 }
 
+// Torque a body needs to build up before it turns a quarter
+const QUARTER_TURN_TORQUE: f32 = 4.0;
+
 // Helpers
 fn dimensions_sim_to_ncoll(dim: (u8, u8)) -> Vector2<f32>
 {
@@ -261,6 +293,18 @@ fn ncoll_orientation_sim_orientation(rotation: &Rotation2<f32>)-> u8
 }
 
 
+// What a ray cast from a Polymini ran into
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RayHit
+{
+    Nothing,
+    Object(PUUID),
+    Polymini(PUUID),
+}
+
+// Angle covered by the whole fan of vision rays
+const VISION_FIELD: f32 = consts::FRAC_PI_2;
+
 // Physics
 //
 pub struct Physics
@@ -272,6 +316,11 @@ pub struct Physics
     ncoll_starting_pos: Vector2<f32>,
 
     corner: (i8, i8),
+    // Corner of the body for each orientation (UP, LEFT, DOWN, RIGHT)
+    corners: [(i8, i8); 4],
+    // Cells of the body facing UP, the collision shape rotates along with the object
+    cells: Vec<(i8, i8)>,
+    torque: f32,
 
     world_dimensions: (f32, f32),
 
@@ -284,25 +333,21 @@ pub struct Physics
 impl Physics
 {
     // Private
-    // TODO:
     fn build_bounding_box(&self) -> Compound2<f32>
     {
-        // First pass, shape = box of dimensions = Physics.dimensions
+        // One unit box per cell, so the shape matches the morphology
 
         // Shapes are anchored in the center (unlike Physics which is top-left anchored)
         // so we need to correct for that
-        let c_dimensions = (self.ncoll_dimensions.x as f32 / 2.0, self.ncoll_dimensions.y as f32 / 2.0);
-
-
-        let rect = ShapeHandle2::new(Cuboid::new(Vector2::new(c_dimensions.0 as f32 - PM_PHYS_MARGIN,
-                                                              c_dimensions.1 as f32 - PM_PHYS_MARGIN)));
-
-        let disp = Vector2::new(c_dimensions.0 + self.corner.0 as f32,
-                                c_dimensions.1 + self.corner.1 as f32);
-        let iso = Isometry2::new(disp, zero());
-
-        //TODO: Create several shapes to match the morphology closely
-        Compound::new(vec![(iso, rect)])
+        let shapes = self.cells.iter().map(|c|
+                     {
+                         let rect = ShapeHandle2::new(Cuboid::new(Vector2::new(0.5 - PM_PHYS_MARGIN,
+                                                                               0.5 - PM_PHYS_MARGIN)));
+                         let disp = Vector2::new(0.5 + c.0 as f32, 0.5 + c.1 as f32);
+                         (Isometry2::new(disp, zero()), rect)
+                     }).collect();
+
+        Compound::new(shapes)
     }
 
     // Public
@@ -311,6 +356,34 @@ impl Physics
         Physics::new_with_corner(uuid, dimensions, x, y, orientation, (0, 0))
     }
     pub fn new_with_corner(uuid: PUUID, dimensions: (u8, u8), x: f32, y: f32, orientation: u8, corner: (i8, i8)) -> Physics
+    {
+        let nc_dims = dimensions_sim_to_ncoll(dimensions);
+
+        let minx = corner.0;
+        let miny = corner.1;
+        let maxx = nc_dims.x as i8 + minx;
+        let maxy = nc_dims.y as i8 + miny;
+
+        Physics::new_with_corners(uuid, dimensions, x, y, orientation,
+                                  [(minx, miny), (-1*maxy, minx), (-1*maxx, -1*maxy), (miny, -1*maxx)])
+    }
+    // 'corners' are indexed by orientation (UP, LEFT, DOWN, RIGHT), see Morphology::get_corner_for_orientation
+    pub fn new_with_corners(uuid: PUUID, dimensions: (u8, u8), x: f32, y: f32, orientation: u8, corners: [(i8, i8); 4]) -> Physics
+    {
+        // Without cells the whole box is taken to be solid
+        let mut cells = vec![];
+        for j in 0..dimensions.1 as i8
+        {
+            for i in 0..dimensions.0 as i8
+            {
+                cells.push((corners[0].0 + i, corners[0].1 + j));
+            }
+        }
+        Physics::new_with_cells(uuid, dimensions, x, y, orientation, corners, cells)
+    }
+    // 'cells' are the coordinates of the cells when facing UP, see Morphology::get_cells
+    pub fn new_with_cells(uuid: PUUID, dimensions: (u8, u8), x: f32, y: f32, orientation: u8,
+                          corners: [(i8, i8); 4], cells: Vec<(i8, i8)>) -> Physics
     {
         let nc_dims = dimensions_sim_to_ncoll(dimensions);
         let nc_pos = Vector2::new(x, y);
@@ -327,7 +400,10 @@ impl Physics
             collisions: vec![],
 
             world_dimensions: (1.0, 1.0),
-            corner: corner,
+            corner: corners[0],
+            corners: corners,
+            cells: cells,
+            torque: 0.0,
 
 
             move_succeded: true,
@@ -345,6 +421,7 @@ impl Physics
         self.ncoll_pos = n_pos;
         self.ncoll_starting_pos = n_pos;
         self.orientation = 0;
+        self.torque = 0.0;
     }
 
     pub fn get_starting_pos(&self) -> (f32, f32)
@@ -377,6 +454,16 @@ impl Physics
         directions[self.orientation as usize]
     }
 
+    pub fn get_corner(&self) -> (i8, i8)
+    {
+        self.corners[self.orientation as usize]
+    }
+
+    pub fn get_torque(&self) -> f32
+    {
+        self.torque
+    }
+
     pub fn get_move_succeded(&self) -> bool
     {
         self.move_succeded
@@ -424,7 +511,9 @@ impl Physics
                                            accum
                                        });
 
-        self.last_action = accum.to_action();
+        let (action, torque) = accum.to_action_with_torque(self.torque);
+        self.last_action = action;
+        self.torque = torque;
         physics_world.apply(self.uuid, self.last_action);
     }
 
@@ -470,6 +559,17 @@ impl Physics
         //
         self.orientation = ncoll_orientation_sim_orientation(&o.position.rotation);
 
+        // Keep the body's bounds in the world in line with the orientation
+        o.data.corner.set(self.get_corner());
+        if self.orientation % 2 == 0
+        {
+            o.data.dimensions.set(self.ncoll_dimensions);
+        }
+        else
+        {
+            o.data.dimensions.set(Vector2::new(self.ncoll_dimensions.y, self.ncoll_dimensions.x));
+        }
+
         debug!("Orientation ncoll {}", o.position.rotation.rotation());
         debug!("Orientation Inx {}", self.orientation); 
         debug!("Orientation Enum {}", self.get_orientation()); 
@@ -510,6 +610,7 @@ impl Serializable for Physics
             json_obj.insert("Collisions".to_owned(), Json::Array(ev_arr));
 
             json_obj.insert("LastAction".to_owned(), self.last_action.to_json());
+            json_obj.insert("Torque".to_owned(), self.torque.to_json());
         }
         Json::Object(json_obj)
     }
@@ -665,6 +766,65 @@ impl PhysicsWorld
         self.world.deferred_set_position(id, new_pos)
     }
 
+    // Casts a fan of 'rays' rays from the head of Polymini 'uuid' centred on where it's facing,
+    // ordered from its right to its left. Returns how far each ray went (at most 'range') and
+    // what it hit
+    pub fn cast_vision(&self, uuid: PUUID, rays: usize, range: f32) -> Vec<(f32, RayHit)>
+    {
+        let p_obj = match self.world.collision_object(uuid)
+        {
+            Some(o) => { o },
+            None =>
+            {
+                error!("Trying to cast vision for {} in physics, but not here", uuid);
+                return vec![(range, RayHit::Nothing); rays]
+            }
+        };
+
+        // The head is the cell at (0, 0), rotated with the rest of the body
+        let orientation = ncoll_orientation_sim_orientation(&p_obj.position.rotation);
+        let heads = [(0.5, 0.5), (-0.5, 0.5), (-0.5, -0.5), (0.5, -0.5)];
+        let head = heads[orientation as usize];
+        let origin = Point2::new(p_obj.position.translation.x + head.0,
+                                 p_obj.position.translation.y + head.1);
+
+        // UP faces +y, every orientation is a quarter turn counter-clockwise from the last
+        let facing = consts::FRAC_PI_2 * (1.0 + orientation as f32);
+
+        let groups = CollisionGroups::new();
+        let mut to_ret = vec![];
+        for r in 0..rays
+        {
+            let angle = if rays > 1
+            {
+                facing - VISION_FIELD / 2.0 + VISION_FIELD * (r as f32 / (rays - 1) as f32)
+            }
+            else
+            {
+                facing
+            };
+
+            let ray = Ray::new(origin, Vector2::new(angle.cos(), angle.sin()));
+            let mut closest = (range, RayHit::Nothing);
+            for (obj, intersection) in self.world.interferences_with_ray(&ray, &groups)
+            {
+                if obj.uid == uuid || intersection.toi >= closest.0
+                {
+                    continue
+                }
+
+                let hit = match obj.data.ppo_type
+                {
+                    PPOType::Polymini => { RayHit::Polymini(obj.uid) },
+                    PPOType::StaticObject => { RayHit::Object(obj.uid) },
+                };
+                closest = (intersection.toi, hit);
+            }
+            to_ret.push(closest);
+        }
+        to_ret
+    }
+
     pub fn step(&mut self) -> bool
     {
         self.step_internal(true, false)
@@ -1244,6 +1404,31 @@ mod test
         assert_eq!(physics.get_pos(), (0.0, 0.0));
     }
 
+    #[test]
+    fn test_cell_shapes()
+    {
+        let _ = env_logger::init();
+        // L shaped body:
+        // XX
+        // X.
+        let corners = [(0, 0), (-2, 0), (-2, -2), (0, -2)];
+        let cells = vec![(0, 0), (1, 0), (0, 1)];
+
+        // Something sitting in the gap doesn't push the Polymini away when placing it
+        let mut physical_world = PhysicsWorld::new();
+        physical_world.add_object(2, (11.0, 11.0), (1, 1));
+        let mut physics = Physics::new_with_cells(1, (2, 2), 10.0, 10.0, 0, corners, cells.clone());
+        assert!(physical_world.add(&mut physics));
+        assert_eq!(physics.get_starting_pos(), (10.0, 10.0));
+
+        // A box body does get pushed
+        let mut physical_world = PhysicsWorld::new();
+        physical_world.add_object(2, (11.0, 11.0), (1, 1));
+        let mut physics = Physics::new_with_corners(1, (2, 2), 10.0, 10.0, 0, corners);
+        physical_world.add(&mut physics);
+        assert!(physics.get_starting_pos() != (10.0, 10.0));
+    }
+
     #[test]
     fn test_rotate_collision()
     {
@@ -1286,7 +1471,6 @@ mod test
         {
             Action::MoveAction(MoveAction::Move(dir, impulse, _)) =>
             {
-                // TODO: ROTATION is currently disabled
                 assert_eq!(dir, expected_direction);
                 assert!( (impulse - expected_impulse) < 0.001);
             },
@@ -1356,4 +1540,98 @@ mod test
 
         test_movement_accumulator_master(actions, 3.3, Direction::ROTATION);
     }
+
+    #[test]
+    fn test_cast_vision()
+    {
+        let _ = env_logger::init();
+        let mut physical_world = PhysicsWorld::new();
+        physical_world.add_object(2, (10.0, 20.0), (2, 2));
+
+        let mut physics = Physics::new(1, (1, 1), 10.0, 10.0, 0);
+        physical_world.add(&mut physics);
+
+        let mut other = Physics::new(3, (1, 1), 25.0, 9.0, 0);
+        physical_world.add(&mut other);
+
+        // Facing UP the middle ray sees the object, the edges see nothing
+        let seen = physical_world.cast_vision(1, 3, 15.0);
+        assert_eq!(seen.len(), 3);
+        assert_eq!(seen[0].1, RayHit::Nothing);
+        assert_eq!(seen[1].1, RayHit::Object(2));
+        assert!((seen[1].0 - 9.5).abs() < 0.1);
+        assert_eq!(seen[2].1, RayHit::Nothing);
+
+        // Turned RIGHT the other Polymini is straight ahead
+        physics.act_on(0, 0, &vec![Action::MoveAction(MoveAction::Move(Direction::ROTATION, -1.0, 0.0))], &mut physical_world);
+        physical_world.step();
+        physics.update_state(&physical_world);
+        assert_eq!(physics.get_orientation(), Direction::RIGHT);
+
+        let seen = physical_world.cast_vision(1, 1, 20.0);
+        assert_eq!(seen[0].1, RayHit::Polymini(3));
+    }
+
+    #[test]
+    fn test_accum_torque_carry()
+    {
+        let ac_list = vec![ Actuator::new(ActuatorTag::MoveHorizontal, 0, (0, 1)), 
+                            Actuator::new(ActuatorTag::MoveHorizontal, 1, (1, 1)) ];
+
+        let mut accum = PhysicsActionAccumulator::new();
+        for actuator in ac_list
+        {
+            match actuator.get_action(1.0)
+            {
+                Action::MoveAction(MoveAction::Move(d, i, t)) => { accum.accumulate(d, i, t); },
+                _ => { panic!("Expected a Move Action"); }
+            }
+        }
+
+        // Translation wins the first step, the torque is carried over
+        let (action, torque) = accum.to_action_with_torque(0.0);
+        match action
+        {
+            Action::MoveAction(MoveAction::Move(dir, _, _)) => { assert_eq!(dir, Direction::HORIZONTAL); },
+            _ => { panic!("Expected a Move Action"); }
+        }
+        assert_eq!(torque, 2.0);
+
+        // Second step builds up enough to turn
+        let (action, torque) = accum.to_action_with_torque(torque);
+        match action
+        {
+            Action::MoveAction(MoveAction::Move(dir, spin, _)) =>
+            {
+                assert_eq!(dir, Direction::ROTATION);
+                assert!(spin > 0.0);
+            },
+            _ => { panic!("Expected a Move Action"); }
+        }
+        assert_eq!(torque, 0.0);
+    }
+
+    #[test]
+    fn test_rotate_actuator()
+    {
+        let _ = env_logger::init();
+        let mut physical_world = PhysicsWorld::new();
+        let mut physics = Physics::new_with_corner(1, (4, 2), 10.0, 10.0, 0, (0, 0)); 
+        physical_world.add(&mut physics);
+
+        let rotate = Actuator::new(ActuatorTag::Rotate, 0, (0, 0));
+        physics.act_on(0, 0, &vec![rotate.get_action(1.0)], &mut physical_world);
+        physical_world.step();
+        physics.update_state(&physical_world);
+
+        assert_eq!(physics.get_orientation(), Direction::LEFT);
+        assert_eq!(physics.get_corner(), (-2, 0));
+
+        physics.act_on(0, 0, &vec![rotate.get_action(-1.0)], &mut physical_world);
+        physical_world.step();
+        physics.update_state(&physical_world);
+
+        assert_eq!(physics.get_orientation(), Direction::UP);
+        assert_eq!(physics.get_corner(), (0, 0));
+    }
 }
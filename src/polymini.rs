@@ -5,6 +5,8 @@ use ::instincts::*;
 use ::morphology::*;
 use ::ph::*;
 use ::physics::*;
+use ::scent::*;
+use ::scripted::*;
 use ::serialization::*;
 use ::thermal::*;
 use ::traits::*;
@@ -42,6 +44,13 @@ pub struct Stats
     eval_stats: EvaluationStats,
 }
 const BASE_LINE_TMP: (f32, f32) = (0.0, 1.0);
+// NOTE: Energy used to always start at 0, every Polymini now starts with some per cell so
+//       Heat and Cool can be used at all
+const ENERGY_PER_CELL: i32 = 20;
+// Energy it takes to change the temperature by THERMO_ACTUATOR_DELTA
+const THERMO_ENERGY_COST: f32 = 1.0;
+// THERMO_ACTUATOR_DELTA isn't exact in f32, whole units shouldn't be rounded up to the next one
+const THERMO_ENERGY_EPSILON: f32 = 0.001;
 const BASE_LINE_PH:  (f32, f32) = (0.0, 1.0);
 impl Stats
 {
@@ -64,7 +73,12 @@ impl Stats
     }
     fn calculate_energy_from(morph: &Morphology) -> i32
     {
-        0
+        morph.get_total_cells() as i32 * ENERGY_PER_CELL
+    }
+    // Energy spent on the temperature change returned by Thermo::act_on
+    fn calculate_thermo_cost(thermo_activity: f32) -> i32
+    {
+        (THERMO_ENERGY_COST * thermo_activity / THERMO_ACTUATOR_DELTA - THERMO_ENERGY_EPSILON).ceil().max(0.0) as i32
     }
     fn calculate_temperature_range(morph: &Morphology) -> (f32, f32)
     {
@@ -98,6 +112,28 @@ impl Stats
         (min_ph, max_ph)
     }
 
+    fn fraction(current: i32, max: i32) -> f32
+    {
+        if max <= 0
+        {
+            0.0
+        }
+        else
+        {
+            (current as f32 / max as f32).min(1.0).max(0.0)
+        }
+    }
+
+    pub fn get_hp_fraction(&self) -> f32
+    {
+        Stats::fraction(self.current_hp, self.max_hp)
+    }
+
+    pub fn get_energy_fraction(&self) -> f32
+    {
+        Stats::fraction(self.current_energy, self.max_energy)
+    }
+
     fn add_eval_stats(&mut self, stats: &EvaluationStats, restarts: u32)
     {
         //
@@ -137,7 +173,7 @@ pub struct Polymini
     dead: bool,
 
     morph: Morphology,
-    control: Control,
+    control: Box<Controller>,
     physics: Physics,
     thermo: Thermo,
     ph: Ph,
@@ -148,6 +184,10 @@ pub struct Polymini
     restarts: u32,
     // Historical data of the creature across restarts (Reset still whipes it)
     fitness_statistics_historic: HashMap<u32, Vec<FitnessStatistic>>,
+    // Contribution of every FitnessEvaluator per scenario
+    evaluation_breakdown: HashMap<u32, Vec<EvaluatorScore>>,
+    // Score obtained in each scenario, aggregated when the evaluation accumulates
+    scenario_scores: HashMap<u32, ScenarioScore>,
 
     // Species-Agnostic Score
     raw_score: f32,
@@ -157,6 +197,9 @@ pub struct Polymini
 
     // Species ID
     species_uuid: PUUID,
+
+    // What was sensed on the last substeps, for sensors with delay
+    sensory_history: SensoryHistory,
 }
 impl Polymini
 {
@@ -178,11 +221,14 @@ impl Polymini
          
         Polymini::new_with_control(pos, morphology, control)
     }
-    pub fn new_with_control(pos: (f32, f32), morphology: Morphology, control: Control) -> Polymini
+    pub fn new_with_control<C>(pos: (f32, f32), morphology: Morphology, control: C) -> Polymini where C: Controller + 'static
+    {
+        Polymini::new_with_controller(pos, morphology, Box::new(control))
+    }
+    pub fn new_with_controller(pos: (f32, f32), morphology: Morphology, control: Box<Controller>) -> Polymini
     {
         let uuid = PolyminiUUIDCtx::next();
-        let dim = morphology.get_dimensions();
-        let corner = morphology.get_corner();
+        let physics = Polymini::build_physics(uuid, &morphology, pos);
 
         let stats = Stats::new(&morphology);
 
@@ -193,17 +239,30 @@ impl Polymini
                    dead: false,
                    morph: morphology,
                    control: control,
-                   physics: Physics::new_with_corner(uuid, dim, pos.0, pos.1, 0, corner),
+                   physics: physics,
                    thermo: Thermo::new(uuid, temp_range.0, temp_range.1),
                    ph: Ph::new(uuid, ph_range.0, ph_range.1),
                    stats: stats,
                    fitness_statistics: vec![],
                    restarts: 0,
                    fitness_statistics_historic: HashMap::new(),
+                   evaluation_breakdown: HashMap::new(),
+                   scenario_scores: HashMap::new(),
                    raw_score: 0.0,
                    species_weighted_fitness: 0.0,
-                   species_uuid: 0 }
+                   species_uuid: 0,
+                   sensory_history: SensoryHistory::new() }
+
+    }
 
+    // Physics whose collision shape matches the body in 'morphology'
+    fn build_physics(uuid: PUUID, morphology: &Morphology, pos: (f32, f32)) -> Physics
+    {
+        let corners = [morphology.get_corner_for_orientation(Direction::UP),
+                       morphology.get_corner_for_orientation(Direction::LEFT),
+                       morphology.get_corner_for_orientation(Direction::DOWN),
+                       morphology.get_corner_for_orientation(Direction::RIGHT)];
+        Physics::new_with_cells(uuid, morphology.get_dimensions(), pos.0, pos.1, 0, corners, morphology.get_cells())
     }
 
     pub fn new_from_json(json:&Json, tt: &TranslationTable, default_sensors: &Vec<Sensor>) -> Option<Polymini>
@@ -216,9 +275,16 @@ impl Polymini
                 let morph =  Morphology::new_from_json(&json_obj.get("Morphology").unwrap(), tt).unwrap();
                 let mut sensor_list = default_sensors.clone();
                 sensor_list.append(&mut morph.get_sensor_list());
-                let control = Control::new_from_json(&json_obj.get("Control").unwrap(), sensor_list,
-                                                     morph.get_actuator_list()).unwrap();
-                let mut pmini = Polymini::new_with_control((0.0,0.0), morph, control);
+                let control_json = json_obj.get("Control").unwrap();
+                let control: Box<Controller> = if ScriptedControl::is_scripted(control_json)
+                {
+                    Box::new(ScriptedControl::new_from_json(control_json, sensor_list, morph.get_actuator_list()).unwrap())
+                }
+                else
+                {
+                    Box::new(Control::new_from_json(control_json, sensor_list, morph.get_actuator_list()).unwrap())
+                };
+                let mut pmini = Polymini::new_with_controller((0.0,0.0), morph, control);
 
 
                 let raw = json_obj.get("Raw").unwrap_or(&Json::Null).as_f64().unwrap_or(0.0) as f32;
@@ -263,10 +329,22 @@ impl Polymini
 
     pub fn get_perspective(&self) -> Perspective
     {
-        Perspective::new(self.uuid,
-                         self.physics.get_normalized_pos(),
-                         self.physics.get_orientation(),
-                         self.physics.get_move_succeded())
+        let mut perspective = Perspective::new_for_species(self.uuid,
+                                                           self.species_uuid,
+                                                           self.physics.get_normalized_pos(),
+                                                           self.physics.get_pos(),
+                                                           self.physics.get_orientation(),
+                                                           self.physics.get_move_succeded());
+        // Dead Polyminis are out of the PhysicsWorld, nothing to see
+        if self.is_alive() && self.morph.has_sensor(SensorTag::Vision)
+        {
+            perspective.vision_rays = self.morph.get_vision_rays();
+        }
+        perspective.hp = self.stats.get_hp_fraction();
+        perspective.energy = self.stats.get_energy_fraction();
+        perspective.temperature = self.thermo.get_relative();
+        perspective.ph = self.ph.get_relative();
+        perspective
     }
     pub fn sense_phase(&mut self, sp: &SensoryPayload)
     {
@@ -276,15 +354,27 @@ impl Polymini
         }
         self.control.sense(sp);
     }
-    pub fn think_phase(&mut self)
+    pub fn sense_phase_with_noise(&mut self, sp: &SensoryPayload, noise: &SensorNoiseConfig, random_ctx: &mut PolyminiRandomCtx)
+    {
+        if self.dead
+        {
+            return
+        }
+        let noisy = self.sensory_history.apply_noise(sp, noise, random_ctx);
+        self.control.sense(&noisy);
+    }
+    // 'step' is the simulation substep
+    pub fn think_phase(&mut self, step: usize)
     {
         if self.dead
         {
             return
         }
+        self.control.set_step(step);
         self.control.think();
     }
-    pub fn act_phase(&mut self, substep: usize, phys_world: &mut PhysicsWorld, thermo_world: &mut ThermoWorld, ph_world: &mut PhWorld)
+    pub fn act_phase(&mut self, substep: usize, phys_world: &mut PhysicsWorld, thermo_world: &mut ThermoWorld, ph_world: &mut PhWorld,
+                     scent_world: &mut ScentWorld)
     {
         if self.dead
         {
@@ -298,20 +388,38 @@ impl Polymini
         let speed = self.get_speed();
         self.physics.act_on(substep, speed, &actions, phys_world);
 
-        self.thermo.act_on(self.physics.get_pos(), &actions, thermo_world);
-        self.ph.act_on(self.physics.get_pos(), &actions, ph_world);
+        // Thermoregulation costs energy, once it runs out Heat and Cool do nothing
+        let thermo_actions = if self.stats.current_energy > 0
+        {
+            actions.clone()
+        }
+        else
+        {
+            vec![]
+        };
+        let thermo_activity = self.thermo.act_on(self.physics.get_pos(), &thermo_actions, thermo_world);
+        self.stats.current_energy -= Stats::calculate_thermo_cost(thermo_activity);
+        self.stats.current_energy = self.stats.current_energy.max(0);
+
+        // Ph shares Thermo's implementation, but there are no actuators for it yet
+        self.ph.act_on(self.physics.get_pos(), &vec![], ph_world);
+
+        scent_world.act_on(self.species_uuid, self.physics.get_pos(), &actions);
     }
     
     pub fn restart(&mut self, random_ctx: &mut PolyminiRandomCtx, placement_func: &PlacementFunction)
     {
         info!("Restarting {} - Had Fitness {}", self.uuid, self.fitness());
         self.physics.reset(random_ctx, placement_func);
+        self.control.reset_memory();
+        self.sensory_history.clear();
         self.fitness_statistics.push(FitnessStatistic::DistanceTravelled(self.physics.get_distance_moved() as u32));
 
         let norm_pos = self.physics.get_normalized_pos();
         self.fitness_statistics.push(FitnessStatistic::FinalPosition((255.0*norm_pos.0) as u8,
                                                                      (255.0*norm_pos.1) as u8));
 
+        self.stats.current_energy = self.stats.max_energy;
         self.restarts += 1;
         self.dead = false;
     }
@@ -319,11 +427,14 @@ impl Polymini
     pub fn reset(&mut self, random_ctx: &mut PolyminiRandomCtx, placement_func: &PlacementFunction)
     {
         info!("Reseting {} - Had Fitness {}", self.uuid, self.fitness());
+        self.control.end_of_life();
         self.restart(random_ctx, placement_func);
         self.set_fitness(0.0);
         self.set_raw(0.0);
         self.fitness_statistics.clear();
         self.fitness_statistics_historic.clear();
+        self.evaluation_breakdown.clear();
+        self.scenario_scores.clear();
         self.stats.eval_stats = HashMap::new();
         self.restarts = 0;
     }
@@ -339,6 +450,16 @@ impl Polymini
         self.uuid
     }
 
+    pub fn get_evaluation_breakdown(&self) -> &HashMap<u32, Vec<EvaluatorScore>>
+    {
+        &self.evaluation_breakdown
+    }
+
+    pub fn get_scenario_scores(&self) -> &HashMap<u32, ScenarioScore>
+    {
+        &self.scenario_scores
+    }
+
     pub fn consequence(&mut self, physicsworld: &PhysicsWorld, tworld: &ThermoWorld, phworld: &PhWorld, substep: usize)
     {
         if self.dead
@@ -412,9 +533,68 @@ impl Polymini
         self.stats.current_hp
     }
 
-    pub fn get_control(&self) -> &Control
+    pub fn get_species_uuid(&self) -> PUUID
+    {
+        self.species_uuid
+    }
+
+    pub fn set_species_uuid(&mut self, species_uuid: PUUID)
+    {
+        self.species_uuid = species_uuid;
+    }
+
+    pub fn get_energy(&self) -> i32
     {
-        &self.control
+        self.stats.current_energy
+    }
+
+    pub fn get_controller(&self) -> &Controller
+    {
+        &*self.control
+    }
+
+    pub fn get_controller_mut(&mut self) -> &mut Controller
+    {
+        &mut *self.control
+    }
+
+    // The evolved NN, None if something else (e.g. a ScriptedControl) drives this Polymini
+    pub fn get_control(&self) -> Option<&Control>
+    {
+        self.control.as_any().downcast_ref::<Control>()
+    }
+
+    pub fn get_control_mut(&mut self) -> Option<&mut Control>
+    {
+        self.control.as_any_mut().downcast_mut::<Control>()
+    }
+
+    // Tracing is only available for Polyminis driven by a Control, returns whether it started
+    pub fn start_trace(&mut self) -> bool
+    {
+        match self.get_control_mut()
+        {
+            Some(control) =>
+            {
+                control.start_trace();
+                true
+            },
+            None =>
+            {
+                warn!("Polymini {} can't be traced, it isn't driven by a Control", self.uuid);
+                false
+            }
+        }
+    }
+
+    pub fn stop_trace(&mut self) -> Option<ControlTrace>
+    {
+        self.get_control_mut().and_then(|c| c.stop_trace())
+    }
+
+    pub fn get_trace(&self) -> Option<&ControlTrace>
+    {
+        self.get_control().and_then(|c| c.get_trace())
     }
 
     pub fn get_speed(&self) -> usize
@@ -457,7 +637,7 @@ impl Serializable for Polymini
             json_obj.insert("Energy".to_owned(), Json::I64(self.stats.current_energy as i64));
         }
 
-        json_obj.insert("Control".to_owned(), self.get_control().serialize(ctx));
+        json_obj.insert("Control".to_owned(), self.get_controller().serialize(ctx));
 
         if !ctx.has_flag(PolyminiSerializationFlags::PM_SF_DB)
         {
@@ -514,6 +694,21 @@ impl Serializable for Polymini
                 scenario_json_obj.insert(format!("{}", scenario), Json::Object(stats_json_obj));
             }
             json_obj.insert("FitnessStatistics".to_owned(), Json::Object(scenario_json_obj));
+
+            let mut breakdown_json_obj = pmJsonObject::new();
+            for (scenario, scores) in self.evaluation_breakdown.iter()
+            {
+                breakdown_json_obj.insert(format!("{}", scenario),
+                                          Json::Array(scores.iter().map(|s| s.serialize(ctx)).collect()));
+            }
+            json_obj.insert("EvaluationBreakdown".to_owned(), Json::Object(breakdown_json_obj));
+
+            let mut scores_json_obj = pmJsonObject::new();
+            for (scenario, score) in self.scenario_scores.iter()
+            {
+                scores_json_obj.insert(format!("{}", scenario), score.serialize(ctx));
+            }
+            json_obj.insert("ScenarioScores".to_owned(), Json::Object(scores_json_obj));
         }
 
         Json::Object(json_obj)
@@ -536,9 +731,10 @@ impl PolyminiGAIndividual for Polymini
                 let mut sensor_list = creation_ctx.default_sensors.clone();
                 sensor_list.append(&mut new_morphology.get_sensor_list());
 
-                let new_control = self.get_control().crossover(&other.get_control(), &mut creation_ctx.random_context,
+                let new_control = self.get_controller().crossover(other.get_controller(), &mut creation_ctx.random_context,
+                                                               &creation_ctx.control_config,
                                                                sensor_list, new_morphology.get_actuator_list());
-                Box::new(Polymini::new_with_control((0.0, 0.0), new_morphology, new_control))
+                Box::new(Polymini::new_with_controller((0.0, 0.0), new_morphology, new_control))
             },
             None =>
             {
@@ -558,9 +754,13 @@ impl PolyminiGAIndividual for Polymini
                 self.morph.mutate(&mut creation_ctx.random_context, &creation_ctx.trans_table);
                 let mut sensor_list = creation_ctx.default_sensors.clone();
                 sensor_list.append(&mut self.morph.get_sensor_list());
-                self.control.mutate(&mut creation_ctx.random_context, 
+                self.control.mutate(&mut creation_ctx.random_context, &creation_ctx.control_config,
                                     sensor_list, self.morph.get_actuator_list());
                 self.stats = Stats::new(&self.morph);
+
+                // The body might have changed shape
+                let pos = self.physics.get_pos();
+                self.physics = Polymini::build_physics(self.uuid, &self.morph, pos);
             },
             None =>
             {
@@ -578,39 +778,51 @@ impl PolyminiGAIndividual for Polymini
             {
                 debug!(" using {} statistics", self.fitness_statistics.len());
 
-                self.fitness_statistics.push(FitnessStatistic::TotalCells(self.stats.total_cells));
+                let mut statistics = self.fitness_statistics.clone();
+                statistics.push(FitnessStatistic::TotalCells(self.stats.total_cells));
 
                 let norm_pos = self.physics.get_normalized_pos();
-                self.fitness_statistics.push(FitnessStatistic::FinalPosition((255.0*norm_pos.0) as u8,
-                                                                             (255.0*norm_pos.1) as u8));
+                statistics.push(FitnessStatistic::FinalPosition((255.0*norm_pos.0) as u8,
+                                                                (255.0*norm_pos.1) as u8));
 
                 if (!self.dead)
                 {
-                    self.fitness_statistics.push(FitnessStatistic::DistanceTravelled(
+                    statistics.push(FitnessStatistic::DistanceTravelled(
                                                     self.physics.get_distance_moved() as u32));
                 }
 
+                // Collection pass for normalisation, the individual is scored later
+                if ctx.is_collecting()
+                {
+                    ctx.collect(&statistics);
+                    return
+                }
+
+                self.fitness_statistics = statistics;
                 ctx.evaluate(&self.fitness_statistics);
 
                 let mut raw = ctx.get_raw();
                 let mut fitness = ctx.get_fitness();
 
+                self.scenario_scores.insert(self.restarts, ScenarioScore { raw: raw,
+                                                                           fitness: fitness,
+                                                                           importance: ctx.get_importance() });
+
                 if ctx.accumulates_over()
                 {
-                    let restarts_inv = 1.0 / (self.restarts + 1) as f32;
-                    let one_minus = 1.0 - restarts_inv;
-
-                    raw     *= restarts_inv;
-                    fitness *= restarts_inv;
+                    let aggregation = ctx.get_aggregation();
+                    let raws: Vec<(f32, f32)> = self.scenario_scores.values().map(|s| (s.raw, s.importance)).collect();
+                    let fitnesses: Vec<(f32, f32)> = self.scenario_scores.values().map(|s| (s.fitness, s.importance)).collect();
 
-                    raw += ( self.raw() * one_minus);
-                    fitness += ( self.fitness() * one_minus);
+                    raw = aggregation.aggregate(&raws);
+                    fitness = aggregation.aggregate(&fitnesses);
                 }
 
                 // Save the fitness stats into the Historic Performance and clear the
                 // 'Per-Scenario' list
                 self.fitness_statistics_historic.insert(self.restarts, self.fitness_statistics.clone());
                 self.fitness_statistics.clear();
+                self.evaluation_breakdown.insert(self.restarts, ctx.get_breakdown().clone());
 
                 self.stats.add_eval_stats(ctx.get_per_instinct(), self.restarts + 1);
 
@@ -642,3 +854,36 @@ impl PolyminiGAIndividual for Polymini
         self.raw_score = r;
     }
 }
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn test_energy_from_cells()
+    {
+        let chromosomes = vec![[0, 0x09, 0x6A, 0xAD],
+                               [0, 0x0B, 0xBE, 0xDA],
+                               [0,    0, 0xBE, 0xEF],
+                               [0,    0, 0xDB, 0xAD]];
+        let morph = Morphology::new(&chromosomes, &TranslationTable::new());
+        let cells = morph.get_total_cells() as i32;
+        assert!(cells > 0);
+
+        let p = Polymini::new_at((21.0, 20.0), morph);
+        assert_eq!(p.get_energy(), cells * ENERGY_PER_CELL);
+        assert_eq!(p.get_perspective().energy, 1.0);
+    }
+
+    #[test]
+    fn test_thermo_cost()
+    {
+        assert_eq!(Stats::calculate_thermo_cost(0.0), 0);
+        assert_eq!(Stats::calculate_thermo_cost(0.5 * THERMO_ACTUATOR_DELTA), 1);
+        assert_eq!(Stats::calculate_thermo_cost(THERMO_ACTUATOR_DELTA), 1);
+        // 0.1 / 0.05 lands just above 2 in f32
+        assert_eq!(Stats::calculate_thermo_cost(0.1), 2);
+        assert_eq!(Stats::calculate_thermo_cost(2.0 * THERMO_ACTUATOR_DELTA + 0.01), 3);
+    }
+}
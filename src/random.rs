@@ -4,3 +4,11 @@
 // Alias GARandomCtx
 extern crate rust_monster;
 pub use self::rust_monster::ga::ga_random::GARandomCtx as PolyminiRandomCtx;
+
+// Normally distributed value (Box-Muller)
+pub fn gen_gaussian(ctx: &mut PolyminiRandomCtx, mean: f32, sigma: f32) -> f32
+{
+    let u1: f32 = ctx.gen_range(::std::f32::EPSILON, 1.0);
+    let u2: f32 = ctx.gen_range(0.0, 1.0);
+    mean + sigma * (-2.0 * u1.ln()).sqrt() * (2.0 * ::std::f32::consts::PI * u2).cos()
+}
@@ -0,0 +1,320 @@
+use ::actuators::*;
+use ::serialization::*;
+use ::uuid::PUUID;
+
+use std::cmp::min;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+
+// Pheromones left by Polyminis, every species has its own grid so
+// creatures only smell their own kind: herding and trail-following work within
+// a species, a Polymini can't follow (or avoid) the trail of another species
+
+const SCENT_GRID_SQUARE_SIZE: f32 = 2.0;
+
+struct ScentActionAccum
+{
+    amount: f32,
+}
+impl ScentActionAccum
+{
+    fn new() -> ScentActionAccum
+    {
+        ScentActionAccum { amount: 0.0 }
+    }
+
+    fn accumulate(&mut self, amount: f32)
+    {
+        self.amount += amount;
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ScentConfig
+{
+    // Fraction of a square's scent that is shared with its neighbours each step
+    pub diffusion: f32,
+    // Fraction of the scent that evaporates each step
+    pub decay: f32,
+}
+impl ScentConfig
+{
+    pub fn defaults() -> ScentConfig
+    {
+        ScentConfig { diffusion: 0.2, decay: 0.05 }
+    }
+}
+impl Serializable for ScentConfig
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Diffusion".to_owned(), self.diffusion.to_json());
+        json_obj.insert("Decay".to_owned(), self.decay.to_json());
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for ScentConfig
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<ScentConfig>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let defaults = ScentConfig::defaults();
+                let diffusion = json_obj.get("Diffusion").and_then(|d| d.as_f64()).map(|d| d as f32).unwrap_or(defaults.diffusion);
+                let decay = json_obj.get("Decay").and_then(|d| d.as_f64()).map(|d| d as f32).unwrap_or(defaults.decay);
+                Some(ScentConfig { diffusion: diffusion.min(1.0).max(0.0), decay: decay.min(1.0).max(0.0) })
+            },
+            _ =>
+            {
+                error!("Incorrect type passed - {:?}", json);
+                None
+            }
+        }
+    }
+}
+
+pub struct ScentWorld
+{
+    // Hash - species uuid | Grid of scent concentration
+    scent_grids: HashMap<PUUID, Vec<Vec<f32>>>,
+    // Same size as the grids, step diffuses into them and swaps so it doesn't allocate
+    back_grids: HashMap<PUUID, Vec<Vec<f32>>>,
+    grid_size: (usize, usize),
+
+    // World dimensions
+    dimensions: (f32, f32),
+
+    config: ScentConfig,
+}
+impl ScentWorld
+{
+    pub fn new() -> ScentWorld
+    {
+        ScentWorld::new_with_dimensions((100.0, 100.0), ScentConfig::defaults())
+    }
+
+    pub fn new_with_dimensions(dims: (f32, f32), config: ScentConfig) -> ScentWorld
+    {
+        let grid_sqs_x = ((dims.0 / SCENT_GRID_SQUARE_SIZE) as usize).max(1);
+        let grid_sqs_y = ((dims.1 / SCENT_GRID_SQUARE_SIZE) as usize).max(1);
+
+        ScentWorld
+        {
+            scent_grids: HashMap::new(),
+            back_grids: HashMap::new(),
+            grid_size: (grid_sqs_x, grid_sqs_y),
+            dimensions: dims,
+            config: config,
+        }
+    }
+
+    // Same world, no scent in it
+    pub fn restart(&self) -> ScentWorld
+    {
+        ScentWorld::new_with_dimensions(self.dimensions, self.config)
+    }
+
+    pub fn get_config(&self) -> &ScentConfig
+    {
+        &self.config
+    }
+
+    pub fn act_on(&mut self, species: PUUID, position: (f32, f32), actions: &ActionList)
+    {
+        let accum = actions.iter().fold(ScentActionAccum::new(),
+                                        |mut accum, action|
+                                        {
+                                            match action
+                                            {
+                                                &Action::ScentAction(amount) =>
+                                                {
+                                                    accum.accumulate(amount);
+                                                },
+                                                _ =>
+                                                {
+                                                    //Ignore
+                                                }
+                                            }
+                                            accum
+                                        });
+
+        if accum.amount > 0.0
+        {
+            self.emit(species, position, accum.amount);
+        }
+    }
+
+    pub fn emit(&mut self, species: PUUID, position: (f32, f32), amount: f32)
+    {
+        let (x_len, y_len) = self.grid_size;
+        let (x, y) = ScentWorld::coord_to_grid_position(position, self.dimensions, x_len, y_len);
+        if !self.scent_grids.contains_key(&species)
+        {
+            self.scent_grids.insert(species, vec![vec![0.0; y_len]; x_len]);
+            self.back_grids.insert(species, vec![vec![0.0; y_len]; x_len]);
+        }
+        let grid = self.scent_grids.get_mut(&species).unwrap();
+        grid[x][y] = (grid[x][y] + amount).min(1.0);
+    }
+
+    // Spread and evaporate the scent of every species
+    pub fn step(&mut self)
+    {
+        let (x_len, y_len) = self.grid_size;
+        let diffusion = self.config.diffusion;
+        let retained = 1.0 - self.config.decay;
+
+        for (species, grid) in self.scent_grids.iter_mut()
+        {
+            let n_grid = self.back_grids.get_mut(species).unwrap();
+            for x in 0..x_len
+            {
+                for y in 0..y_len
+                {
+                    let mut sum = 0.0;
+                    let mut count = 0;
+                    if x > 0 { sum += grid[x - 1][y]; count += 1; }
+                    if x + 1 < x_len { sum += grid[x + 1][y]; count += 1; }
+                    if y > 0 { sum += grid[x][y - 1]; count += 1; }
+                    if y + 1 < y_len { sum += grid[x][y + 1]; count += 1; }
+
+                    let mut v = grid[x][y];
+                    if count > 0
+                    {
+                        let avg = sum / count as f32;
+                        v += diffusion * (avg - v);
+                    }
+                    n_grid[x][y] = v * retained;
+                }
+            }
+            mem::swap(grid, n_grid);
+        }
+    }
+
+    pub fn get_concentration(&self, species: PUUID, position: (f32, f32)) -> f32
+    {
+        let (x_len, y_len) = self.grid_size;
+        let (x, y) = ScentWorld::coord_to_grid_position(position, self.dimensions, x_len, y_len);
+        match self.scent_grids.get(&species)
+        {
+            Some(grid) => { grid[x][y] },
+            None => { 0.0 }
+        }
+    }
+
+    // Concentration at position and how it changes towards +x and +y, the gradients are in
+    // [0..1] with 0.5 meaning no change (same layout as SensorTag::Scent)
+    pub fn get_scent(&self, species: PUUID, position: (f32, f32)) -> Vec<f32>
+    {
+        let (x_len, y_len) = self.grid_size;
+        let (x, y) = ScentWorld::coord_to_grid_position(position, self.dimensions, x_len, y_len);
+        match self.scent_grids.get(&species)
+        {
+            Some(grid) =>
+            {
+                let gradient_x = grid[min(x + 1, x_len - 1)][y] - grid[x.saturating_sub(1)][y];
+                let gradient_y = grid[x][min(y + 1, y_len - 1)] - grid[x][y.saturating_sub(1)];
+                vec![grid[x][y],
+                     (0.5 + gradient_x / 2.0).min(1.0).max(0.0),
+                     (0.5 + gradient_y / 2.0).min(1.0).max(0.0)]
+            },
+            None =>
+            {
+                vec![0.0, 0.5, 0.5]
+            }
+        }
+    }
+
+    fn coord_to_grid_position(position: (f32, f32), dims: (f32, f32), x_len: usize, y_len: usize) -> (usize, usize)
+    {
+        let x = ((position.0.max(0.0) / dims.0) * x_len as f32).floor() as usize;
+        let y = ((position.1.max(0.0) / dims.1) * y_len as f32).floor() as usize;
+        (min(x, x_len - 1), min(y, y_len - 1))
+    }
+}
+impl fmt::Debug for ScentWorld
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        for (species, grid) in &self.scent_grids
+        {
+            let _ = write!(f, "\nSpecies {}", species);
+            for t_x in 0..grid.len()
+            {
+                let _ = write!(f, "\n");
+                for t_y in 0..grid[t_x].len()
+                {
+                    let _ = write!(f, "{:.*} ", 2, grid[t_x][t_y]);
+                }
+            }
+        }
+        write!(f, "\n")
+    }
+}
+
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use ::actuators::*;
+    use ::sensors::*;
+
+    #[test]
+    fn test_scent_emit()
+    {
+        let mut s_world = ScentWorld::new_with_dimensions((20.0, 20.0), ScentConfig::defaults());
+        let emitter = Actuator::new(ActuatorTag::EmitScent, 0, (0, 0));
+
+        // Negative stimulus doesn't leave any scent
+        s_world.act_on(1, (10.0, 10.0), &vec![emitter.get_action(-1.0)]);
+        assert_eq!(s_world.get_concentration(1, (10.0, 10.0)), 0.0);
+
+        s_world.act_on(1, (10.0, 10.0), &vec![emitter.get_action(1.0)]);
+        assert_eq!(s_world.get_concentration(1, (10.0, 10.0)), SCENT_EMIT_AMOUNT);
+
+        // Other species can't smell it
+        assert_eq!(s_world.get_concentration(2, (10.0, 10.0)), 0.0);
+        assert_eq!(s_world.get_scent(2, (10.0, 10.0)), vec![0.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_scent_diffusion_and_decay()
+    {
+        let mut s_world = ScentWorld::new_with_dimensions((20.0, 20.0), ScentConfig { diffusion: 0.5, decay: 0.1 });
+        s_world.emit(1, (10.0, 10.0), 1.0);
+
+        s_world.step();
+        let centre = s_world.get_concentration(1, (10.0, 10.0));
+        let neighbour = s_world.get_concentration(1, (12.0, 10.0));
+        assert!(centre < 1.0);
+        assert!(neighbour > 0.0);
+        assert!(centre > neighbour);
+
+        // Standing next to the source, the scent gets stronger towards -x
+        let scent = s_world.get_scent(1, (12.0, 10.0));
+        assert_eq!(scent.len(), SensorTag::Scent.get_cardinality());
+        assert!(scent[1] < 0.5);
+        assert_eq!(scent[2], 0.5);
+
+        for _ in 0..200
+        {
+            s_world.step();
+        }
+        assert!(s_world.get_concentration(1, (10.0, 10.0)) < 0.001);
+    }
+
+    #[test]
+    fn test_scent_config_serialization()
+    {
+        let mut ctx = SerializationCtx::new();
+        let config = ScentConfig { diffusion: 0.3, decay: 0.25 };
+        let config_p = ScentConfig::new_from_json(&config.serialize(&mut ctx), &mut ctx).unwrap();
+        assert_eq!(config_p.diffusion, 0.3);
+        assert_eq!(config_p.decay, 0.25);
+    }
+}
@@ -0,0 +1,543 @@
+//
+// Hand written brains - A list of rules checked in order, the first one whose conditions all
+// hold decides what the actuators do (actuators it doesn't mention get the Default).
+//
+// { "Rules": [ { "When": [ { "Sensor": "positionx", "Slot": 0, "Comparison": "below", "Value": 0.2 } ],
+//                "Then": { "movehorizontal": 1.0 } } ],
+//   "Default": { "movehorizontal": -1.0 } }
+//
+// Rules never evolve, crossover and mutation only fit them to the new sensors and actuators
+use ::control::*;
+use ::genetics::*;
+use ::serialization::*;
+
+use std::any::Any;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison
+{
+    Below,
+    Above,
+}
+impl Serializable for Comparison
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        self.to_string().to_lowercase().to_json()
+    }
+}
+impl Deserializable for Comparison
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<Comparison>
+    {
+        match *json
+        {
+            Json::String(ref json_string) =>
+            {
+                match json_string.to_lowercase().as_str()
+                {
+                    "below" => { Some(Comparison::Below) },
+                    "above" => { Some(Comparison::Above) },
+                    _ =>
+                    {
+                        error!("Unknown Comparison {}", json_string);
+                        None
+                    }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+impl fmt::Display for Comparison
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+// Value sensed in 'slot' of the first sensor with 'tag' compared against 'value'
+#[derive(Clone, Debug)]
+pub struct Condition
+{
+    pub tag: SensorTag,
+    pub slot: usize,
+    pub comparison: Comparison,
+    pub value: f32,
+}
+impl Condition
+{
+    pub fn holds(&self, sensed: f32) -> bool
+    {
+        match self.comparison
+        {
+            Comparison::Below => { sensed < self.value },
+            Comparison::Above => { sensed > self.value },
+        }
+    }
+}
+impl Serializable for Condition
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Sensor".to_owned(), self.tag.to_string().to_lowercase().to_json());
+        json_obj.insert("Slot".to_owned(), self.slot.to_json());
+        json_obj.insert("Comparison".to_owned(), self.comparison.serialize(ctx));
+        json_obj.insert("Value".to_owned(), self.value.to_json());
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for Condition
+{
+    fn new_from_json(json: &Json, ctx: &mut SerializationCtx) -> Option<Condition>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let tag = match json_obj.get("Sensor").and_then(|s| SensorTag::new_from_json(s, ctx))
+                {
+                    Some(t) => { t },
+                    None =>
+                    {
+                        error!("Condition with an invalid Sensor - {:?}", json_obj.get("Sensor"));
+                        return None
+                    }
+                };
+                let comparison = match json_obj.get("Comparison").and_then(|c| Comparison::new_from_json(c, ctx))
+                {
+                    Some(c) => { c },
+                    None =>
+                    {
+                        error!("Condition with an invalid Comparison - {:?}", json_obj.get("Comparison"));
+                        return None
+                    }
+                };
+                let value = match json_obj.get("Value").and_then(|v| v.as_f64())
+                {
+                    Some(v) => { v as f32 },
+                    None =>
+                    {
+                        error!("Condition without a Value");
+                        return None
+                    }
+                };
+                let slot = json_obj.get("Slot").and_then(|s| s.as_u64()).unwrap_or(0) as usize;
+
+                Some(Condition { tag: tag, slot: slot, comparison: comparison, value: value })
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+
+fn serialize_outputs(outputs: &Vec<(ActuatorTag, f32)>) -> Json
+{
+    let mut json_obj = pmJsonObject::new();
+    for &(tag, v) in outputs
+    {
+        json_obj.insert(tag.to_string().to_lowercase(), v.to_json());
+    }
+    Json::Object(json_obj)
+}
+
+fn outputs_from_json(json: Option<&Json>, ctx: &mut SerializationCtx) -> Option<Vec<(ActuatorTag, f32)>>
+{
+    match json
+    {
+        Some(&Json::Object(ref json_obj)) =>
+        {
+            let mut outputs = vec![];
+            for (k, v) in json_obj.iter()
+            {
+                match (ActuatorTag::new_from_json(&k.to_json(), ctx), v.as_f64())
+                {
+                    (Some(tag), Some(stimulus)) => { outputs.push((tag, stimulus as f32)); },
+                    _ =>
+                    {
+                        error!("Invalid rule output {} - {:?}", k, v);
+                        return None
+                    }
+                }
+            }
+            Some(outputs)
+        },
+        None =>
+        {
+            Some(vec![])
+        },
+        _ =>
+        {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule
+{
+    pub conditions: Vec<Condition>,
+    // Stimulus ([-1, 1]) for every actuator with that tag
+    pub outputs: Vec<(ActuatorTag, f32)>,
+}
+impl Serializable for Rule
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("When".to_owned(), Json::Array(self.conditions.iter().map(|c| c.serialize(ctx)).collect()));
+        json_obj.insert("Then".to_owned(), serialize_outputs(&self.outputs));
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for Rule
+{
+    fn new_from_json(json: &Json, ctx: &mut SerializationCtx) -> Option<Rule>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let mut conditions = vec![];
+                match json_obj.get("When")
+                {
+                    Some(&Json::Array(ref conditions_json)) =>
+                    {
+                        for c in conditions_json
+                        {
+                            match Condition::new_from_json(c, ctx)
+                            {
+                                Some(condition) => { conditions.push(condition); },
+                                None => { return None }
+                            }
+                        }
+                    },
+                    // No conditions, the rule always fires
+                    _ => {}
+                }
+
+                match outputs_from_json(json_obj.get("Then"), ctx)
+                {
+                    Some(outputs) => { Some(Rule { conditions: conditions, outputs: outputs }) },
+                    None => { None }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ScriptedControl
+{
+    sensor_list: Vec<Sensor>,
+    actuator_list: Vec<Actuator>,
+
+    rules: Vec<Rule>,
+    default_outputs: Vec<(ActuatorTag, f32)>,
+
+    // Only what the sensors of the Polymini can sense
+    sensed: SensoryPayload,
+    fired_rule: Option<usize>,
+    outputs: Vec<f32>,
+}
+impl ScriptedControl
+{
+    pub fn new(sensor_list: Vec<Sensor>, actuator_list: Vec<Actuator>, rules: Vec<Rule>,
+               default_outputs: Vec<(ActuatorTag, f32)>) -> ScriptedControl
+    {
+        let out_len = actuator_list.len();
+        ScriptedControl { sensor_list: sensor_list,
+                          actuator_list: actuator_list,
+                          rules: rules,
+                          default_outputs: default_outputs,
+                          sensed: SensoryPayload::new(),
+                          fired_rule: None,
+                          outputs: vec![0.0; out_len] }
+    }
+
+    // A Control JSON describes a ScriptedControl if it has Rules
+    pub fn is_scripted(json: &Json) -> bool
+    {
+        json.find("Rules").is_some()
+    }
+
+    pub fn new_from_json(json: &Json, sensor_list: Vec<Sensor>, actuator_list: Vec<Actuator>) -> Option<ScriptedControl>
+    {
+        let ctx = &mut SerializationCtx::new();
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let mut rules = vec![];
+                match json_obj.get("Rules")
+                {
+                    Some(&Json::Array(ref rules_json)) =>
+                    {
+                        for r in rules_json
+                        {
+                            match Rule::new_from_json(r, ctx)
+                            {
+                                Some(rule) => { rules.push(rule); },
+                                None =>
+                                {
+                                    error!("Invalid Rule {:?}", r);
+                                    return None
+                                }
+                            }
+                        }
+                    },
+                    _ =>
+                    {
+                        error!("ScriptedControl without Rules");
+                        return None
+                    }
+                }
+
+                match outputs_from_json(json_obj.get("Default"), ctx)
+                {
+                    Some(default_outputs) =>
+                    {
+                        Some(ScriptedControl::new(sensor_list, actuator_list, rules, default_outputs))
+                    },
+                    None => { None }
+                }
+            },
+            _ =>
+            {
+                None
+            }
+        }
+    }
+
+    pub fn get_rules(&self) -> &Vec<Rule>
+    {
+        &self.rules
+    }
+
+    // Rule that decided the last think, None if it was the Default
+    pub fn get_fired_rule(&self) -> Option<usize>
+    {
+        self.fired_rule
+    }
+
+    fn get_sensed(&self, tag: SensorTag, slot: usize) -> f32
+    {
+        self.sensed.get(&tag).and_then(|v| v.get(slot).cloned()).unwrap_or(NEUTRAL_INPUT)
+    }
+
+    fn refit(&mut self, new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>)
+    {
+        self.outputs = vec![0.0; new_actuator_list.len()];
+        self.sensor_list = new_sensor_list;
+        self.actuator_list = new_actuator_list;
+        self.sensed.clear();
+        self.fired_rule = None;
+    }
+}
+impl Controller for ScriptedControl
+{
+    fn sense(&mut self, sensed: &SensoryPayload)
+    {
+        self.sensed.clear();
+        for sensor in &self.sensor_list
+        {
+            if let Some(payload) = sensed.get(&sensor.tag)
+            {
+                self.sensed.entry(sensor.tag).or_insert(payload.clone());
+            }
+        }
+    }
+
+    fn think(&mut self)
+    {
+        let mut fired = None;
+        for (i, rule) in self.rules.iter().enumerate()
+        {
+            if rule.conditions.iter().all(|c| c.holds(self.get_sensed(c.tag, c.slot)))
+            {
+                fired = Some(i);
+                break;
+            }
+        }
+        self.fired_rule = fired;
+
+        let outputs = match fired
+        {
+            Some(i) => { &self.rules[i].outputs },
+            None => { &self.default_outputs }
+        };
+        for (i, actuator) in self.actuator_list.iter().enumerate()
+        {
+            let tag = actuator.get_tag();
+            let stimulus = outputs.iter().find(|&&(t, _)| t == tag)
+                                  .or(self.default_outputs.iter().find(|&&(t, _)| t == tag))
+                                  .map_or(0.0, |&(_, v)| v);
+            self.outputs[i] = stimulus;
+        }
+        debug!("Scripted Think - Rule: {:?} Outputs: {:?}", self.fired_rule, self.outputs);
+    }
+
+    fn get_actions(&self) -> ActionList
+    {
+        self.actuator_list.iter().zip(self.outputs.iter()).map(|(a, &v)| a.get_action(v)).collect()
+    }
+
+    fn crossover(&self, _: &Controller, _: &mut PolyminiRandomCtx, _: &ControlConfig,
+                 new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>) -> Box<Controller>
+    {
+        let mut child = self.clone();
+        child.refit(new_sensor_list, new_actuator_list);
+        Box::new(child)
+    }
+
+    fn mutate(&mut self, _: &mut PolyminiRandomCtx, _: &ControlConfig,
+              new_sensor_list: Vec<Sensor>, new_actuator_list: Vec<Actuator>)
+    {
+        self.refit(new_sensor_list, new_actuator_list);
+    }
+
+    fn reset_memory(&mut self)
+    {
+        self.fired_rule = None;
+    }
+
+    fn as_any(&self) -> &Any
+    {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut Any
+    {
+        self
+    }
+}
+impl Serializable for ScriptedControl
+{
+    fn serialize(&self, ctx: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        if ctx.has_flag(PolyminiSerializationFlags::PM_SF_STATIC)
+        {
+            json_obj.insert("Rules".to_owned(), Json::Array(self.rules.iter().map(|r| r.serialize(ctx)).collect()));
+            json_obj.insert("Default".to_owned(), serialize_outputs(&self.default_outputs));
+        }
+
+        if ctx.has_flag(PolyminiSerializationFlags::PM_SF_DYNAMIC)
+        {
+            json_obj.insert("FiredRule".to_owned(), self.fired_rule.to_json());
+            json_obj.insert("Outputs".to_owned(), self.outputs.to_json());
+        }
+        Json::Object(json_obj)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use ::control::*;
+    use ::genetics::*;
+    use ::scripted::*;
+    use ::serialization::*;
+
+    fn wall_follower() -> Json
+    {
+        Json::from_str("{ \"Rules\": [ { \"When\": [ { \"Sensor\": \"positionx\", \"Comparison\": \"below\", \"Value\": 0.2 } ],
+                                          \"Then\": { \"movehorizontal\": 1.0 } },
+                                        { \"When\": [ { \"Sensor\": \"positionx\", \"Comparison\": \"above\", \"Value\": 0.8 },
+                                                      { \"Sensor\": \"positiony\", \"Slot\": 0, \"Comparison\": \"above\", \"Value\": 0.5 } ],
+                                          \"Then\": { \"hormov\": -1.0, \"vermov\": -0.5 } } ],
+                           \"Default\": { \"movevertical\": 0.25 } }").unwrap()
+    }
+
+    fn moves(c: &ScriptedControl) -> Vec<f32>
+    {
+        c.get_actions().iter().map(|a|
+        {
+            match *a
+            {
+                Action::MoveAction(MoveAction::Move(_, impulse, _)) => { impulse },
+                _ => { panic!("Expected a MoveAction") }
+            }
+        }).collect()
+    }
+
+    fn think_at(c: &mut ScriptedControl, x: f32, y: f32)
+    {
+        let mut payload = SensoryPayload::new();
+        payload.insert(SensorTag::PositionX, vec![x]);
+        payload.insert(SensorTag::PositionY, vec![y]);
+        c.sense(&payload);
+        c.think();
+    }
+
+    #[test]
+    fn test_scripted_rules()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveHorizontal, 0, (0, 0)), Actuator::new(ActuatorTag::MoveVertical, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let json = wall_follower();
+        assert!(ScriptedControl::is_scripted(&json));
+
+        let mut c = ScriptedControl::new_from_json(&json, s_list.clone(), a_list.clone()).unwrap();
+        assert_eq!(c.get_rules().len(), 2);
+
+        think_at(&mut c, 0.1, 0.9);
+        assert_eq!(c.get_fired_rule(), Some(0));
+        assert_eq!(moves(&c), vec![1.0, 0.25]);
+
+        think_at(&mut c, 0.9, 0.9);
+        assert_eq!(c.get_fired_rule(), Some(1));
+        assert_eq!(moves(&c), vec![-1.0, -0.5]);
+
+        think_at(&mut c, 0.9, 0.1);
+        assert_eq!(c.get_fired_rule(), None);
+        assert_eq!(moves(&c), vec![0.0, 0.25]);
+
+        // Without a PositionY sensor the second rule sees a neutral value
+        let mut blind = ScriptedControl::new_from_json(&json, vec![Sensor::new(SensorTag::PositionX, 0)], a_list.clone()).unwrap();
+        think_at(&mut blind, 0.9, 0.9);
+        assert_eq!(blind.get_fired_rule(), None);
+
+        let mut ser_ctx = SerializationCtx::new_from_flags(PolyminiSerializationFlags::PM_SF_STATIC);
+        let json_1 = c.serialize(&mut ser_ctx);
+        let c2 = ScriptedControl::new_from_json(&json_1, s_list.clone(), a_list.clone()).unwrap();
+        assert_eq!(json_1.pretty().to_string(), c2.serialize(&mut ser_ctx).pretty().to_string());
+    }
+
+    #[test]
+    fn test_scripted_as_controller()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveHorizontal, 0, (0, 0)), Actuator::new(ActuatorTag::MoveVertical, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0), Sensor::new(SensorTag::PositionY, 1)];
+        let mut ctx = PolyminiRandomCtx::new_unseeded("Scripted Tests".to_string());
+        let config = ControlConfig::defaults();
+
+        let scripted: Box<Controller> = Box::new(ScriptedControl::new_from_json(&wall_follower(), s_list.clone(), a_list.clone()).unwrap());
+        let neural: Box<Controller> = Box::new(Control::new_from_config(s_list.clone(), a_list.clone(), &config, &mut ctx));
+
+        // Scripted parents pass their rules on, evolved ones ignore them
+        let child = scripted.crossover(&*neural, &mut ctx, &config, s_list.clone(), vec![a_list[0].clone()]);
+        let child_rules = child.as_any().downcast_ref::<ScriptedControl>().unwrap();
+        assert_eq!(child_rules.get_rules().len(), 2);
+        assert_eq!(child.get_actions().len(), 1);
+
+        let mut child = neural.crossover(&*scripted, &mut ctx, &config, s_list.clone(), a_list.clone());
+        assert!(child.as_any().downcast_ref::<Control>().is_some());
+        child.mutate(&mut ctx, &config, s_list.clone(), a_list.clone());
+        child.think();
+        assert_eq!(child.get_actions().len(), 2);
+    }
+}
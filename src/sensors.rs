@@ -1,9 +1,12 @@
+use ::random::*;
 use ::serialization::*;
 
-use std::collections::HashMap;
+use std::cmp::min;
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
 
-pub type SensoryPayload = HashMap<SensorTag, f32>;
+// Each sensor gets as many values as its cardinality
+pub type SensoryPayload = HashMap<SensorTag, Vec<f32>>;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum SensorTag
@@ -17,18 +20,35 @@ pub enum SensorTag
     // --Time
     TimeGlobal,
     TimeSubStep,
+    // -- Own condition
+    Hp,
+    Energy,
+    Temperature,
+    Ph,
 
     // Evolvable Sensors
     // -- FoodSources
     GSensor,
+    // -- Temperature
+    TemperatureGradient,
+    // -- Pheromones
+    Scent,
+    // -- Eyes
+    Vision,
 
 }
 impl SensorTag
 {
     pub fn get_cardinality(&self) -> usize
     {
-        match self
+        match *self
         {
+            // Towards +x, -x, +y and -y
+            SensorTag::TemperatureGradient => { 4 },
+            // Concentration, change towards +x and towards +y
+            SensorTag::Scent => { 3 },
+            // Distance and what was seen, for each ray
+            SensorTag::Vision => { VISION_VALUES_PER_RAY * get_vision_rays(0) },
             _ => { 1 },
         }
     }
@@ -57,8 +77,15 @@ impl Deserializable for SensorTag
                     "orientation"      => { to_ret = SensorTag::Orientation },
                     "lastmovesucceded" => { to_ret = SensorTag::LastMoveSucceded },
                     "gsensor"          => { to_ret = SensorTag::GSensor },
+                    "temperaturegradient" => { to_ret = SensorTag::TemperatureGradient },
+                    "scent"            => { to_ret = SensorTag::Scent },
+                    "vision"           => { to_ret = SensorTag::Vision },
                     "timeglobal"       => { to_ret = SensorTag::TimeGlobal },
                     "timesubstep"      => { to_ret = SensorTag::TimeSubStep },
+                    "hp"               => { to_ret = SensorTag::Hp },
+                    "energy"           => { to_ret = SensorTag::Energy },
+                    "temperature"      => { to_ret = SensorTag::Temperature },
+                    "ph"               => { to_ret = SensorTag::Ph },
 
                     //Default
                     _                  => { return None },
@@ -80,6 +107,44 @@ impl fmt::Display for SensorTag
     }
 }
 
+// Vision
+//
+pub const VISION_VALUES_PER_RAY: usize = 2;
+// How far eyes see, in world units
+pub const VISION_RANGE: f32 = 20.0;
+const VISION_BASE_RAYS: usize = 3;
+const VISION_MAX_RAYS: usize = 9;
+
+// Every point of acuity widens the fan of rays by one on each side
+pub fn get_vision_rays(acuity: usize) -> usize
+{
+    min(VISION_BASE_RAYS + 2 * acuity, VISION_MAX_RAYS)
+}
+
+// What a ray hit, as fed to the Vision sensor
+// NOTE: There are no food sources in the world yet, seeing food is left for when there are
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VisionHit
+{
+    Nothing,
+    Wall,
+    OtherSpecies,
+    SameSpecies,
+}
+impl VisionHit
+{
+    pub fn to_float(&self) -> f32
+    {
+        match *self
+        {
+            VisionHit::Nothing      => { 0.0 },
+            VisionHit::Wall         => { 0.25 },
+            VisionHit::OtherSpecies => { 0.5 },
+            VisionHit::SameSpecies  => { 0.75 },
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Sensor
 {
@@ -91,10 +156,15 @@ impl Sensor
 {
     pub fn new(tag: SensorTag, index: usize) -> Sensor
     {
-        //TODO: Cardinality
         Sensor { tag: tag, cardinality: tag.get_cardinality(), index: index }
     }
 
+    // For sensors whose size depends on the traits of the Polymini (eg. Vision)
+    pub fn new_with_cardinality(tag: SensorTag, index: usize, cardinality: usize) -> Sensor
+    {
+        Sensor { tag: tag, cardinality: cardinality, index: index }
+    }
+
     pub fn get_total_cardinality(sensors: &Vec<Sensor>) -> usize
     {
         let mut in_len = 0;
@@ -104,4 +174,295 @@ impl Sensor
         }
         in_len
     }
+
+    // What feeds each input of the NN - (Tag, Nth sensor with that tag, Slot within the sensor)
+    pub fn get_input_keys(sensors: &Vec<Sensor>) -> Vec<(SensorTag, usize, usize)>
+    {
+        let mut occurrences = HashMap::new();
+        let mut keys = vec![];
+        for s in sensors
+        {
+            let occurrence = {
+                let o = occurrences.entry(s.tag).or_insert(0);
+                *o += 1;
+                *o - 1
+            };
+            for slot in 0..s.cardinality
+            {
+                keys.push((s.tag, occurrence, slot));
+            }
+        }
+        keys
+    }
+
+    // For each input of 'new_sensors' the input it was when the sensors were 'old_sensors'
+    pub fn get_input_mapping(old_sensors: &Vec<Sensor>, new_sensors: &Vec<Sensor>) -> Vec<Option<usize>>
+    {
+        let old_keys = Sensor::get_input_keys(old_sensors);
+        Sensor::get_input_keys(new_sensors).iter().map(|k| old_keys.iter().position(|o| o == k)).collect()
+    }
+}
+
+// Input for sensors with nothing to sense, it becomes 0 once centered in Control::think
+pub const NEUTRAL_INPUT: f32 = 0.5;
+
+// Noise
+//
+
+// How much a sensor deviates from the exact value, applied to every slot of the sensor.
+// Values aren't clamped afterwards, sensors don't share a range so a noisy reading can
+// fall outside of the exact one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SensorNoise
+{
+    // Standard deviation of the Gaussian noise added to the value
+    pub sigma: f32,
+    // Values are rounded to multiples of it, 0.0 to keep them continuous
+    pub quantisation: f32,
+    // Probability of the sensor failing on a substep, all of its slots fail together
+    pub dropout: f32,
+    // The sensor reads what was there this many substeps ago
+    pub delay: usize,
+}
+impl SensorNoise
+{
+    pub fn defaults() -> SensorNoise
+    {
+        SensorNoise { sigma: 0.0, quantisation: 0.0, dropout: 0.0, delay: 0 }
+    }
+
+    pub fn apply(&self, values: &Vec<f32>, random_ctx: &mut PolyminiRandomCtx) -> Vec<f32>
+    {
+        if self.dropout > 0.0 && random_ctx.gen_range(0.0, 1.0) < self.dropout
+        {
+            // A failed sensor reads the same as having nothing to sense
+            return vec![NEUTRAL_INPUT; values.len()]
+        }
+
+        values.iter().map(|v|
+        {
+            let mut n_v = *v;
+            if self.sigma > 0.0
+            {
+                n_v = gen_gaussian(random_ctx, n_v, self.sigma);
+            }
+            if self.quantisation > 0.0
+            {
+                n_v = (n_v / self.quantisation).round() * self.quantisation;
+            }
+            n_v
+        }).collect()
+    }
+}
+impl Serializable for SensorNoise
+{
+    fn serialize(&self, _: &mut SerializationCtx) -> Json
+    {
+        let mut json_obj = pmJsonObject::new();
+        json_obj.insert("Sigma".to_owned(), self.sigma.to_json());
+        json_obj.insert("Quantisation".to_owned(), self.quantisation.to_json());
+        json_obj.insert("Dropout".to_owned(), self.dropout.to_json());
+        json_obj.insert("Delay".to_owned(), self.delay.to_json());
+        Json::Object(json_obj)
+    }
+}
+impl Deserializable for SensorNoise
+{
+    fn new_from_json(json: &Json, _: &mut SerializationCtx) -> Option<SensorNoise>
+    {
+        match *json
+        {
+            Json::Object(ref json_obj) =>
+            {
+                let get_f32 = |k: &str| { json_obj.get(k).and_then(|v| v.as_f64()).unwrap_or(0.0) as f32 };
+                Some(SensorNoise { sigma: get_f32("Sigma").max(0.0),
+                                   quantisation: get_f32("Quantisation").max(0.0),
+                                   dropout: get_f32("Dropout").min(1.0).max(0.0),
+                                   delay: json_obj.get("Delay").and_then(|v| v.as_u64()).unwrap_or(0) as usize })
+            },
+            _ =>
+            {
+                error!("Incorrect type passed - {:?}", json);
+                None
+            }
+        }
+    }
+}
+
+// Noise for each SensorTag, sensors not in it are exact. It is per tag, not per sensor:
+// every sensor of the same tag (e.g. two Orientation sensors) uses the same SensorNoise
+pub type SensorNoiseConfig = HashMap<SensorTag, SensorNoise>;
+
+pub fn sensor_noise_config_from_json(json: &Json) -> SensorNoiseConfig
+{
+    let mut config = SensorNoiseConfig::new();
+    match *json
+    {
+        Json::Object(ref json_obj) =>
+        {
+            for (k, v) in json_obj.iter()
+            {
+                match (SensorTag::new_from_json(&k.to_json(), &mut SerializationCtx::new()),
+                       SensorNoise::new_from_json(v, &mut SerializationCtx::new()))
+                {
+                    (Some(tag), Some(noise)) =>
+                    {
+                        config.insert(tag, noise);
+                    },
+                    _ =>
+                    {
+                        warn!("Ignoring Sensor Noise for {}", k);
+                    }
+                }
+            }
+        },
+        _ =>
+        {
+            error!("Incorrect type passed - {:?}", json);
+        }
+    }
+    config
+}
+
+pub fn sensor_noise_config_to_json(config: &SensorNoiseConfig, ctx: &mut SerializationCtx) -> Json
+{
+    let mut json_obj = pmJsonObject::new();
+    for (tag, noise) in config.iter()
+    {
+        json_obj.insert(tag.to_string().to_lowercase(), noise.serialize(ctx));
+    }
+    Json::Object(json_obj)
+}
+
+// Keeps the last payloads sensed so delayed sensors can read them
+pub struct SensoryHistory
+{
+    payloads: VecDeque<SensoryPayload>,
+}
+impl SensoryHistory
+{
+    pub fn new() -> SensoryHistory
+    {
+        SensoryHistory { payloads: VecDeque::new() }
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.payloads.clear();
+    }
+
+    // Records 'sensed' and returns what the sensors read once 'config' is applied
+    pub fn apply_noise(&mut self, sensed: &SensoryPayload, config: &SensorNoiseConfig,
+                       random_ctx: &mut PolyminiRandomCtx) -> SensoryPayload
+    {
+        let max_delay = config.values().fold(0, |m, n| { if n.delay > m { n.delay } else { m } });
+        if max_delay > 0
+        {
+            self.payloads.push_front(sensed.clone());
+            self.payloads.truncate(max_delay + 1);
+        }
+
+        let mut to_ret = SensoryPayload::new();
+        for (tag, values) in sensed.iter()
+        {
+            match config.get(tag)
+            {
+                Some(noise) =>
+                {
+                    // Until there's enough history the oldest payload is read
+                    let delayed = if noise.delay > 0
+                    {
+                        let inx = min(noise.delay, self.payloads.len() - 1);
+                        self.payloads[inx].get(tag).unwrap_or(values)
+                    }
+                    else
+                    {
+                        values
+                    };
+                    to_ret.insert(*tag, noise.apply(delayed, random_ctx));
+                },
+                None =>
+                {
+                    to_ret.insert(*tag, values.clone());
+                }
+            }
+        }
+        to_ret
+    }
+}
+
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use ::random::*;
+    use ::serialization::*;
+
+    #[test]
+    fn test_sensor_noise()
+    {
+        let mut random_ctx = PolyminiRandomCtx::from_seed([1, 2, 3, 4], "Sensor Noise".to_owned());
+        let values = vec![0.1, 0.42, 0.9];
+
+        assert_eq!(SensorNoise::defaults().apply(&values, &mut random_ctx), values);
+
+        let quantised = SensorNoise { sigma: 0.0, quantisation: 0.25, dropout: 0.0, delay: 0 };
+        assert_eq!(quantised.apply(&values, &mut random_ctx), vec![0.0, 0.5, 1.0]);
+
+        let dropped = SensorNoise { sigma: 0.0, quantisation: 0.0, dropout: 1.0, delay: 0 };
+        assert_eq!(dropped.apply(&values, &mut random_ctx), vec![NEUTRAL_INPUT; 3]);
+
+        // A sensor fails as a whole, never just some of its slots
+        let flaky = SensorNoise { sigma: 0.0, quantisation: 0.0, dropout: 0.5, delay: 0 };
+        for _ in 0..20
+        {
+            let f_values = flaky.apply(&values, &mut random_ctx);
+            assert!(f_values == values || f_values == vec![NEUTRAL_INPUT; 3]);
+        }
+
+        let noisy = SensorNoise { sigma: 0.1, quantisation: 0.0, dropout: 0.0, delay: 0 };
+        let n_values = noisy.apply(&values, &mut random_ctx);
+        assert!(n_values != values);
+
+        // Values outside of [0..1] are left alone
+        let wide = vec![-2.0, 3.4];
+        assert_eq!(SensorNoise::defaults().apply(&wide, &mut random_ctx), wide);
+        assert_eq!(quantised.apply(&wide, &mut random_ctx), vec![-2.0, 3.5]);
+    }
+
+    #[test]
+    fn test_sensor_delay()
+    {
+        let mut random_ctx = PolyminiRandomCtx::from_seed([1, 2, 3, 4], "Sensor Delay".to_owned());
+        let mut config = SensorNoiseConfig::new();
+        config.insert(SensorTag::PositionX, SensorNoise { sigma: 0.0, quantisation: 0.0, dropout: 0.0, delay: 2 });
+
+        let mut history = SensoryHistory::new();
+        let mut read = vec![];
+        for step in 0..4
+        {
+            let mut sp = SensoryPayload::new();
+            sp.insert(SensorTag::PositionX, vec![step as f32 / 10.0]);
+            sp.insert(SensorTag::PositionY, vec![step as f32 / 10.0]);
+            let noisy = history.apply_noise(&sp, &config, &mut random_ctx);
+
+            // Sensors without noise are untouched
+            assert_eq!(noisy.get(&SensorTag::PositionY), sp.get(&SensorTag::PositionY));
+            read.push(noisy.get(&SensorTag::PositionX).unwrap()[0]);
+        }
+        assert_eq!(read, vec![0.0, 0.0, 0.0, 0.1]);
+    }
+
+    #[test]
+    fn test_sensor_noise_serialization()
+    {
+        let mut ctx = SerializationCtx::new();
+        let mut config = SensorNoiseConfig::new();
+        config.insert(SensorTag::Vision, SensorNoise { sigma: 0.5, quantisation: 0.25, dropout: 0.125, delay: 3 });
+
+        let json = sensor_noise_config_to_json(&config, &mut ctx);
+        assert!(json.as_object().unwrap().contains_key("vision"));
+        assert_eq!(sensor_noise_config_from_json(&json), config);
+    }
 }
@@ -12,6 +12,21 @@ use ::uuid::*;
 
 use std::collections::{ HashMap, VecDeque };
 
+// Individuals of the epoch that think together, see ControlBatch
+struct InferenceGroup
+{
+    batch: ControlBatch,
+    // (Species, Individual) of each member of the batch
+    members: Vec<(usize, usize)>,
+}
+
+struct InferencePlan
+{
+    groups: Vec<InferenceGroup>,
+    // Individuals that can't be batched think on their own
+    individual: Vec<(usize, usize)>,
+}
+
 // NOTE:
 // Simulation vs Simulation Epoch
 //
@@ -193,12 +208,18 @@ pub struct SimulationEpoch
     max_steps: usize,
     restarts: usize,
     restarts_left: usize,
+
+    // Think a whole generation at once, the plan is built on the first think and thrown away
+    // whenever the individuals might have changed
+    batched_inference: bool,
+    inference_plan: Option<InferencePlan>,
 }
 impl SimulationEpoch
 {
     pub fn new() -> SimulationEpoch
     {
-        SimulationEpoch { environment: Environment::new(2, vec![]), species: vec![], proportions: vec![], steps: 0, max_steps: 100, substeps: 4, restarts: 0, restarts_left: 0 }
+        SimulationEpoch { environment: Environment::new(2, vec![]), species: vec![], proportions: vec![], steps: 0, max_steps: 100, substeps: 4, restarts: 0, restarts_left: 0,
+                          batched_inference: false, inference_plan: None }
     }
 
     pub fn new_from_json(json: &Json, placement_funcs: &mut VecDeque<Box<PlacementFunction>>, master_table: &HashMap<(TraitTier, u8), PolyminiTrait>) -> Option<SimulationEpoch>
@@ -225,6 +246,7 @@ impl SimulationEpoch
                 let subs = json_obj.get("Substeps").unwrap().as_u64().unwrap() as usize;
 
                 let proportions = json_obj.get("Proportions").unwrap().as_array().unwrap().iter().map( |x| { x.as_f64().unwrap() as f32 }).collect();
+                let batched = json_obj.get("BatchedInference").and_then(|b| b.as_boolean()).unwrap_or(false);
 
                 Some(SimulationEpoch { 
                     environment: env,
@@ -235,6 +257,8 @@ impl SimulationEpoch
                     max_steps: m_s,
                     restarts: r,
                     restarts_left: r,
+                    batched_inference: batched,
+                    inference_plan: None,
                 })
             },
             _ => 
@@ -254,7 +278,19 @@ impl SimulationEpoch
     pub fn new_restartable(environment: Environment, max_steps: usize, restarts: usize) -> SimulationEpoch
     {
         SimulationEpoch { environment: environment, species: vec![], proportions: vec![], steps: 0,
-                          max_steps: max_steps, substeps:4, restarts: restarts, restarts_left: restarts }
+                          max_steps: max_steps, substeps:4, restarts: restarts, restarts_left: restarts,
+                          batched_inference: false, inference_plan: None }
+    }
+
+    pub fn set_batched_inference(&mut self, batched: bool)
+    {
+        self.batched_inference = batched;
+        self.inference_plan = None;
+    }
+
+    pub fn is_batched_inference(&self) -> bool
+    {
+        self.batched_inference
     }
 
     pub fn is_full(&self) -> bool
@@ -276,6 +312,8 @@ impl SimulationEpoch
             return;
         }
 
+        self.inference_plan = None;
+
         let mut sp = species;
         // Environment Registration
         debug!("Adding Species - Start Loop");
@@ -328,11 +366,13 @@ impl SimulationEpoch
 
     pub fn get_species_mut(&mut self) -> &mut Vec<Species>
     {
+        self.inference_plan = None;
         &mut self.species
     }
 
     pub fn evaluate_species(&mut self)
     {
+        self.inference_plan = None;
         for species in &mut self.species
         {
             species.evaluate();
@@ -374,6 +414,7 @@ impl SimulationEpoch
 
     pub fn restart(&mut self)
     {
+        self.inference_plan = None;
         self.environment = self.environment.restart();
         for species in &mut self.species
         {
@@ -403,6 +444,7 @@ impl SimulationEpoch
 
         // TODO: Advance the Environment's epoch and copy it over
         let mut new_epoch = SimulationEpoch::new_restartable(self.environment.advance_epoch(), self.max_steps, self.restarts);
+        new_epoch.set_batched_inference(self.batched_inference);
 
         println!("Advancing Epoch - Reinserting Species");
         for n_s in new_epoch_species
@@ -443,6 +485,7 @@ impl SimulationEpoch
     }
     fn sense_phase(&mut self)
     {
+        let owners = self.get_species_of_individuals();
         for s in 0..self.species.len()
         {
             let gen_size = self.species[s].get_generation().size();
@@ -454,24 +497,123 @@ impl SimulationEpoch
                     perspective = polymini.get_perspective();
                 }
     
-                let sensed = self.sense_for(&perspective);
-                let mut p = self.species[s].get_generation_mut().get_individual_mut(i);
-                p.sense_phase(&sensed);
+                let sensed = self.sense_for(&perspective, &owners);
+                if self.environment.sensor_noise.len() > 0
+                {
+                    self.species[s].sense_with_noise(i, &sensed, &self.environment.sensor_noise);
+                }
+                else
+                {
+                    let mut p = self.species[s].get_generation_mut().get_individual_mut(i);
+                    p.sense_phase(&sensed);
+                }
+            }
+        }
+    }
+    // Polymini uuid | Species uuid
+    fn get_species_of_individuals(&self) -> HashMap<PUUID, PUUID>
+    {
+        let mut owners = HashMap::new();
+        for s in &self.species
+        {
+            for ind in s.get_generation().iter()
+            {
+                owners.insert(ind.get_id(), s.get_uuid());
             }
         }
+        owners
     }
     fn think_phase(&mut self)
     {
+        if self.batched_inference
+        {
+            self.batched_think_phase();
+            return
+        }
+
+        let step = self.steps;
         for s in &mut self.species
         {
             let generation = s.get_generation_mut();
             for i in 0..generation.size()
             {
                 let mut polymini = generation.get_individual_mut(i);
-                polymini.think_phase();
+                polymini.think_phase(step);
             }
         }
     }
+    fn batched_think_phase(&mut self)
+    {
+        let plan = match self.inference_plan.take()
+        {
+            Some(plan) => { plan },
+            None => { self.build_inference_plan() }
+        };
+
+        for group in &plan.groups
+        {
+            let output =
+            {
+                let controls = group.members.iter().map(|&(s, i)|
+                               {
+                                   self.species[s].get_generation().get_individual(i).get_control().unwrap()
+                               }).collect();
+                group.batch.think(&controls)
+            };
+
+            for (m, &(s, i)) in group.members.iter().enumerate()
+            {
+                let polymini = self.species[s].get_generation_mut().get_individual_mut(i);
+                if polymini.is_alive()
+                {
+                    let control = polymini.get_control_mut().unwrap();
+                    control.set_step(self.steps);
+                    output.apply(m, control);
+                }
+            }
+        }
+
+        for &(s, i) in &plan.individual
+        {
+            self.species[s].get_generation_mut().get_individual_mut(i).think_phase(self.steps);
+        }
+
+        self.inference_plan = Some(plan);
+    }
+
+    fn build_inference_plan(&self) -> InferencePlan
+    {
+        let mut by_topology = HashMap::new();
+        let mut individual = vec![];
+        for s in 0..self.species.len()
+        {
+            let generation = self.species[s].get_generation();
+            for i in 0..generation.size()
+            {
+                match generation.get_individual(i).get_control().and_then(|c| c.get_topology())
+                {
+                    Some(topology) => { by_topology.entry(topology).or_insert(vec![]).push((s, i)); },
+                    None => { individual.push((s, i)); }
+                }
+            }
+        }
+
+        let groups = by_topology.into_iter().map(|(topology, members): (ControlTopology, Vec<(usize, usize)>)|
+                     {
+                         let batch =
+                         {
+                             let controls = members.iter().map(|&(s, i)|
+                                            {
+                                                self.species[s].get_generation().get_individual(i).get_control().unwrap()
+                                            }).collect();
+                             ControlBatch::new(topology, &controls)
+                         };
+                         InferenceGroup { batch: batch, members: members }
+                     }).collect();
+
+        InferencePlan { groups: groups, individual: individual }
+    }
+
     fn act_phase(&mut self, substep: usize)
     {
         for s in &mut self.species
@@ -480,7 +622,8 @@ impl SimulationEpoch
             for i in 0..generation.size()
             {
                 let mut polymini = generation.get_individual_mut(i);
-                polymini.act_phase(substep, &mut self.environment.physical_world, &mut self.environment.thermal_world, &mut self.environment.ph_world);
+                polymini.act_phase(substep, &mut self.environment.physical_world, &mut self.environment.thermal_world, &mut self.environment.ph_world,
+                                   &mut self.environment.scent_world);
             }
         }
     }
@@ -491,6 +634,9 @@ impl SimulationEpoch
         /* Physics */
         self.environment.physical_world.step();
 
+        /* Scent */
+        self.environment.scent_world.step();
+
         for s in &mut self.species
         {
             let generation = s.get_generation_mut();
@@ -516,22 +662,58 @@ impl SimulationEpoch
         
         // GA Evaluation and Sort
     }
-    fn sense_for(&self, perspective: &Perspective) -> SensoryPayload
+    fn sense_for(&self, perspective: &Perspective, owners: &HashMap<PUUID, PUUID>) -> SensoryPayload
     {
         let mut sp = SensoryPayload::new();
         // Fill the basic sensors
-        sp.insert(SensorTag::PositionX, perspective.pos.0);
-        sp.insert(SensorTag::PositionY, perspective.pos.1);
+        sp.insert(SensorTag::PositionX, vec![perspective.pos.0]);
+        sp.insert(SensorTag::PositionY, vec![perspective.pos.1]);
+
+        sp.insert(SensorTag::LastMoveSucceded, vec![if perspective.last_move_succeeded { 1.0 } else { 0.0 }]);
 
-        sp.insert(SensorTag::LastMoveSucceded, if perspective.last_move_succeeded { 1.0 } else { 0.0 });
+        sp.insert(SensorTag::Orientation, vec![perspective.orientation.to_float()]);
 
-        sp.insert(SensorTag::Orientation, perspective.orientation.to_float());
+        sp.insert(SensorTag::TimeGlobal,  vec![(self.steps as f32 / (self.max_steps * self.substeps) as f32)]);
+        sp.insert(SensorTag::TimeSubStep, vec![(self.steps % self.substeps) as f32 / self.substeps as f32]);
 
-        sp.insert(SensorTag::TimeGlobal,  (self.steps as f32 / (self.max_steps * self.substeps) as f32));
-        sp.insert(SensorTag::TimeSubStep, (self.steps % self.substeps) as f32 / self.substeps as f32);
+        sp.insert(SensorTag::Hp, vec![perspective.hp]);
+        sp.insert(SensorTag::Energy, vec![perspective.energy]);
+        sp.insert(SensorTag::Temperature, vec![perspective.temperature]);
+        sp.insert(SensorTag::Ph, vec![perspective.ph]);
 
         // Go through the environment and Polyminis filling up
         // the sensory payload
+        sp.insert(SensorTag::TemperatureGradient,
+                  self.environment.thermal_world.get_temperature_gradient(perspective.world_pos));
+        sp.insert(SensorTag::Scent,
+                  self.environment.scent_world.get_scent(perspective.species, perspective.world_pos));
+
+        if perspective.vision_rays > 0
+        {
+            let mut vision = vec![];
+            for (distance, hit) in self.environment.physical_world.cast_vision(perspective.id, perspective.vision_rays, VISION_RANGE)
+            {
+                let seen = match hit
+                {
+                    RayHit::Nothing => { VisionHit::Nothing },
+                    RayHit::Object(_) => { VisionHit::Wall },
+                    RayHit::Polymini(uuid) =>
+                    {
+                        if owners.get(&uuid) == Some(&perspective.species)
+                        {
+                            VisionHit::SameSpecies
+                        }
+                        else
+                        {
+                            VisionHit::OtherSpecies
+                        }
+                    }
+                };
+                vision.push(distance / VISION_RANGE);
+                vision.push(seen.to_float());
+            }
+            sp.insert(SensorTag::Vision, vision);
+        }
         sp
     }
 
@@ -539,12 +721,15 @@ impl SimulationEpoch
     {
         let original_env = self.environment.clone();
         let mut random_ctx = PolyminiRandomCtx::from_seed([3,1,4,3], "Solo Run".to_owned());
+        // Individuals don't change species during the run, Vision needs to tell them apart
+        let owners = self.get_species_of_individuals();
         for &(ref e, ref cfg, ref p_func) in envs
         {
             self.environment = e.clone();
             for s in 0..self.species.len()
             {
                 // This is not optional, so enforce it
+                // (how environments are combined comes from the config's Aggregation and EnvironmentImportance)
                 let mut new_cfg = cfg.clone();
                 new_cfg.accumulates_over = true;
                 self.species[s].set_ga_config(new_cfg);
@@ -578,14 +763,23 @@ impl SimulationEpoch
                                 perspective = polymini.get_perspective();
                             }
                 
-                            let sensed = self.sense_for(&perspective);
+                            let sensed = self.sense_for(&perspective, &owners);
+                            if self.environment.sensor_noise.len() > 0
+                            {
+                                self.species[s].sense_with_noise(i, &sensed, &self.environment.sensor_noise);
+                            }
+                            else
+                            {
+                                self.species[s].get_generation_mut().get_individual_mut(i).sense_phase(&sensed);
+                            }
                             let mut p = self.species[s].get_generation_mut().get_individual_mut(i);
-                            p.sense_phase(&sensed);
-                            p.think_phase();
-                            p.act_phase(ss, &mut self.environment.physical_world, &mut self.environment.thermal_world, &mut self.environment.ph_world);
+                            p.think_phase(self.steps);
+                            p.act_phase(ss, &mut self.environment.physical_world, &mut self.environment.thermal_world, &mut self.environment.ph_world,
+                                        &mut self.environment.scent_world);
                             self.environment.physical_world.step();
                             self.environment.thermal_world.step();
                             self.environment.ph_world.step();
+                            self.environment.scent_world.step();
                             p.consequence(&self.environment.physical_world, &self.environment.thermal_world, &self.environment.ph_world, ss);
 
                             if p.get_hp() <= 0
@@ -635,6 +829,7 @@ impl Serializable for SimulationEpoch
             json_obj.insert("Substeps".to_owned(), self.substeps.to_json());
 
             json_obj.insert("Proportions".to_owned(), self.proportions.to_json());
+            json_obj.insert("BatchedInference".to_owned(), self.batched_inference.to_json());
         }
 
         json_obj.insert("Environment".to_owned(), self.environment.serialize(ctx));
@@ -737,6 +932,110 @@ mod test
         }
     }
 
+    #[test]
+    fn test_step_batched()
+    {
+        let chromosomes = vec![[0, 0x09, 0x6A, 0xAD],
+                               [0, 0x0B, 0xBE, 0xDA],
+                               [0,    0, 0xBE, 0xEF],
+                               [0,    0, 0xDB, 0xAD]];
+
+        // Same seeds on both epochs, only the way they think differs
+        let new_epoch = |batched: bool|
+        {
+            let mut individuals = vec![];
+            for (i, pos) in vec![(21.0, 20.0), (17.0, 40.0)].into_iter().enumerate()
+            {
+                let morph = Morphology::new(&chromosomes, &TranslationTable::new());
+                let mut in_ctx = PolyminiRandomCtx::from_seed([i as u32 + 1, 2, 3, 4], "Batched Step In".to_string());
+                let mut out_ctx = PolyminiRandomCtx::from_seed([i as u32 + 1, 5, 6, 7], "Batched Step Out".to_string());
+                let control = Control::new_from(morph.get_sensor_list(), morph.get_actuator_list(), 7,
+                                                &mut RandomWeightsGenerator::new(&mut in_ctx),
+                                                &mut RandomWeightsGenerator::new(&mut out_ctx));
+                individuals.push(Polymini::new_with_control(pos, morph, control));
+            }
+            let mut s = SimulationEpoch::new();
+            s.set_batched_inference(batched);
+            s.add_species(Species::new(individuals));
+            s
+        };
+
+        let mut s = new_epoch(true);
+        let mut unbatched = new_epoch(false);
+        for _ in 0..10
+        {
+            s.step();
+            unbatched.step();
+
+            let generation = s.get_species()[0].get_generation();
+            let expected = unbatched.get_species()[0].get_generation();
+            for i in 0..generation.size()
+            {
+                let (p, q) = (generation.get_individual(i), expected.get_individual(i));
+                assert_eq!(p.get_physics().get_pos(), q.get_physics().get_pos());
+
+                let (outputs, expected_outputs) = (p.get_control().unwrap().get_outputs(), q.get_control().unwrap().get_outputs());
+                assert_eq!(outputs.len(), expected_outputs.len());
+                for (a, b) in outputs.iter().zip(expected_outputs.iter())
+                {
+                    assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+                }
+            }
+        }
+        assert!(s.is_batched_inference());
+        assert!(s.inference_plan.is_some());
+        assert!(unbatched.inference_plan.is_none());
+
+        s.restart();
+        assert!(s.inference_plan.is_none());
+        s.step();
+    }
+
+    #[test]
+    fn test_step_trace()
+    {
+        let chromosomes = vec![[0, 0x09, 0x6A, 0xAD],
+                               [0, 0x0B, 0xBE, 0xDA],
+                               [0,    0, 0xBE, 0xEF],
+                               [0,    0, 0xDB, 0xAD]];
+
+        let p1 = Polymini::new_at((21.0, 20.0), Morphology::new(&chromosomes, &TranslationTable::new()));
+        let mut s = SimulationEpoch::new();
+        s.add_species(Species::new(vec![p1]));
+        s.step();
+
+        assert!(s.get_species_mut()[0].get_generation_mut().get_individual_mut(0).start_trace());
+        for _ in 0..3
+        {
+            s.step();
+        }
+
+        // Steps are the simulation's, not how many times the Control thought
+        let trace = s.get_species_mut()[0].get_generation_mut().get_individual_mut(0).stop_trace().unwrap();
+        let steps: Vec<usize> = trace.get_steps().iter().map(|t| t.step).collect();
+        assert_eq!(steps, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sense_condition()
+    {
+        let chromosomes = vec![[0, 0x09, 0x6A, 0xAD],
+                               [0, 0x0B, 0xBE, 0xDA],
+                               [0,    0, 0xBE, 0xEF],
+                               [0,    0, 0xDB, 0xAD]];
+
+        let p1 = Polymini::new_at((21.0, 20.0), Morphology::new(&chromosomes, &TranslationTable::new()));
+        let mut s = SimulationEpoch::new();
+        s.add_species(Species::new(vec![p1]));
+
+        let perspective = s.species[0].get_generation().get_individual(0).get_perspective();
+        let sensed = s.sense_for(&perspective, &s.get_species_of_individuals());
+        assert_eq!(sensed.get(&SensorTag::Hp), Some(&vec![1.0]));
+        assert_eq!(sensed.get(&SensorTag::Energy), Some(&vec![1.0]));
+        assert_eq!(sensed.get(&SensorTag::Temperature), Some(&vec![0.5]));
+        assert_eq!(sensed.get(&SensorTag::Ph), Some(&vec![0.5]));
+    }
+
     #[test]
     fn test_step_double_coll()
     {
@@ -826,6 +1125,9 @@ mod test
         let evaluators = vec![FitnessEvaluator::PositionsVisited { weight: 1.0 }];
         let new_config = PGAConfig { population_size: 5,
                                      percentage_elitism: 0.2, percentage_mutation: 0.1, fitness_evaluators: evaluators, accumulates_over: false,
+                              normalisation: FitnessNormalisation::NoNormalisation,
+                              aggregation: EvaluationAggregation::Mean, environment_importance: 1.0,
+                              control: ControlConfig::defaults(),
                                      genome_size: 8 };
 
         let mut sp = Species::new(vec![p1, p2]);
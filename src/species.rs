@@ -32,6 +32,7 @@ impl Serializable for SpeciesStats
 
 pub struct Species
 {
+    uuid: PUUID,
     name: String,
     ga: PolyminiGeneticAlgorithm<Polymini>,
     creation_context: PolyminiCreationCtx,
@@ -51,10 +52,14 @@ impl Species
         // Default configuration
         let cfg = PGAConfig { population_size: pop.len() as u32,
                               percentage_elitism: 0.2, fitness_evaluators: vec![], accumulates_over: false,
+                              normalisation: FitnessNormalisation::NoNormalisation,
+                              aggregation: EvaluationAggregation::Mean, environment_importance: 1.0,
+                              control: ControlConfig::defaults(),
                               percentage_mutation: 0.1, genome_size: 8 };// instinct_weights: HashMap::new() };
 
         //
-        Species {
+        let mut s = Species {
+                  uuid: id,
                   name: sp_name,
                   ga: PolyminiGeneticAlgorithm::new(pop, id, cfg),
                   creation_context: PolyminiCreationCtx::empty(),
@@ -67,7 +72,9 @@ impl Species
                   percentage_population: 0.0,
                   instinct_weights: HashMap::new(),
                   stats: SpeciesStats {}
-                }
+                };
+        s.tag_individuals();
+        s
     }
 
     pub fn new_from(name: String,
@@ -97,23 +104,27 @@ impl Species
             let mut sensor_list = default_sensors.clone();
             sensor_list.append(&mut morph.get_sensor_list());
 
-            let hl_size = ctx.gen_range(3, 7);
-
-            let control = Control::new_from_random_ctx(sensor_list, morph.get_actuator_list(), hl_size, &mut ctx);
+            let control = Control::new_from_config(sensor_list, morph.get_actuator_list(), &pgaconfig.control, &mut ctx);
 
             inds.push(Polymini::new_with_control(pos, morph, control));
         }
 
-        Species {
+        let mut creation_context = PolyminiCreationCtx::new_from(translation_table, default_sensors.clone(), ctx);
+        creation_context.control_config = pgaconfig.control.clone();
+
+        let mut s = Species {
+                  uuid: uuid,
                   name: name,
                   ga: PolyminiGeneticAlgorithm::new_with(inds, pgaconfig),
-                  creation_context: PolyminiCreationCtx::new_from(translation_table, default_sensors.clone(), ctx),
+                  creation_context: creation_context,
                   placement_function: placement_func,
                   accumulated_score: 0.0,
                   percentage_population: 0.0,
                   instinct_weights: HashMap::new(),
                   stats: SpeciesStats{},
-                }
+                };
+        s.tag_individuals();
+        s
     }
 
     pub fn new_from_json(json: &Json, default_sensors: &Vec<Sensor>,
@@ -133,7 +144,8 @@ impl Species
                     return None
                 }
 
-                let translation_table = TranslationTable::new_from_json(json_obj.get("TranslationTable").unwrap(), master_table).unwrap();
+                let mut translation_table = TranslationTable::new_from_json(json_obj.get("TranslationTable").unwrap(), master_table).unwrap();
+                translation_table.read_options(json_obj);
                 let pgaconfig = match PGAConfig::new_from_json(json_obj.get("GAConfiguration").unwrap(), &mut SerializationCtx::new())
                 {
 
@@ -212,15 +224,20 @@ impl Species
                 }
                 else
                 {
-                    let mut s = Species { name: name,
+                    let mut creation_context = PolyminiCreationCtx::new_from(translation_table, default_sensors.clone(), ctx);
+                    creation_context.control_config = pgaconfig.control.clone();
+
+                    let mut s = Species { uuid: PolyminiUUIDCtx::next(),
+                                   name: name,
                                    ga: PolyminiGeneticAlgorithm::new_with(inds, pgaconfig),
-                                   creation_context: PolyminiCreationCtx::new_from(translation_table, default_sensors.clone(), ctx),
+                                   creation_context: creation_context,
                                    placement_function: placement_func,
                                    accumulated_score: 0.0,
                                    percentage_population: percentage,
                                    instinct_weights: iw,
                                    stats: SpeciesStats{}
                                  };
+                    s.tag_individuals();
                     s.restart();
 
                     Some(s)
@@ -252,6 +269,20 @@ impl Species
         }
     }
 
+    // Let every Polymini know which Species it belongs to
+    fn tag_individuals(&mut self)
+    {
+        for i in 0..self.ga.get_population().size()
+        {
+            self.ga.get_population_mut().get_individual_mut(i).set_species_uuid(self.uuid);
+        }
+    }
+
+    pub fn get_uuid(&self) -> PUUID
+    {
+        self.uuid
+    }
+
     pub fn get_name(&self) -> &String
     {
         &self.name
@@ -272,6 +303,13 @@ impl Species
         self.ga.get_population().get_individual(0)
     }
 
+    // Individual 'i' senses through 'noise', drawing from the Species' random context
+    pub fn sense_with_noise(&mut self, i: usize, sensed: &SensoryPayload, noise: &SensorNoiseConfig)
+    {
+        let random_ctx = self.creation_context.get_random_ctx();
+        self.ga.get_population_mut().get_individual_mut(i).sense_phase_with_noise(sensed, noise, random_ctx);
+    }
+
     pub fn evaluate(&mut self)
     {
         self.ga.evaluate_population(&self.instinct_weights);
@@ -289,11 +327,13 @@ impl Species
     pub fn advance_epoch(&mut self)
     {
         self.ga.step(&mut self.creation_context);
+        self.tag_individuals();
         self.reset();
     }
 
     pub fn set_ga_config(&mut self, config: PGAConfig)
     {
+        self.creation_context.control_config = config.control.clone();
         self.ga.change_config(config);
     }
 
@@ -328,6 +368,7 @@ impl Serializable for Species
         if ctx.has_flag(PolyminiSerializationFlags::PM_SF_STATIC)
         {
             json_obj.insert("TranslationTable".to_string(), self.creation_context.trans_table.serialize(ctx));
+            self.creation_context.trans_table.write_options(&mut json_obj, ctx);
             json_obj.insert("GAConfiguration".to_string(), self.ga.get_config().serialize(ctx));
             json_obj.insert("Percentage".to_string(), self.percentage_population.to_json());
         }
@@ -2,6 +2,7 @@ use ::actuators::*;
 use ::serialization::*;
 use ::uuid::PUUID;
 
+use std::cmp::min;
 use std::collections::HashMap;
 use std::fmt;
 use std::f32;
@@ -11,7 +12,38 @@ use std::f32;
 
 struct ThermoActionAccum
 {
+    delta: f32,
+    // Every action counted on its own, opposing actions still add up here
+    activity: f32,
 }
+impl ThermoActionAccum
+{
+    fn new() -> ThermoActionAccum
+    {
+        ThermoActionAccum { delta: 0.0, activity: 0.0 }
+    }
+
+    fn accumulate(&mut self, delta: f32)
+    {
+        self.delta += delta;
+        self.activity += delta.abs();
+    }
+
+    fn to_action(&self) -> Action
+    {
+        if self.delta != 0.0
+        {
+            Action::ThermoAction(self.delta)
+        }
+        else
+        {
+            Action::NoAction
+        }
+    }
+}
+
+// How much of the change to a Polymini's temperature leaks into its grid square
+const THERMO_GRID_LEAK: f32 = 0.1;
 
 pub struct Thermo 
 {
@@ -27,13 +59,47 @@ impl Thermo
         Thermo { uuid: uuid, min: min, max: max, current: (min + max)/ 2.0 }
     }
 
-    pub fn act_on(&self, position: (f32, f32), actions: &ActionList, thermo_world: &mut ThermoWorld)
+    // Returns how much temperature change the actions asked for, each one counted on its own
+    // (Heat and Cool together cancel out but are both paid for), so the caller can charge for it
+    pub fn act_on(&self, position: (f32, f32), actions: &ActionList, thermo_world: &mut ThermoWorld) -> f32
     {
-
         // Find actions related to thermal
+        let accum = actions.iter().fold(ThermoActionAccum::new(),
+                                        |mut accum, action|
+                                        {
+                                            match action
+                                            {
+                                                &Action::ThermoAction(delta) =>
+                                                {
+                                                    accum.accumulate(delta);
+                                                },
+                                                _ =>
+                                                {
+                                                    //Ignore
+                                                }
+                                            }
+                                            accum
+                                        });
+
+        thermo_world.move_to(self.uuid, position);
+        let action = accum.to_action();
+        thermo_world.apply(self.uuid, action);
+        accum.activity
+    }
 
+    pub fn get_current(&self) -> f32
+    {
+        self.current
+    }
 
-        thermo_world.apply(self.uuid, Action::NoAction);
+    // Where the current value is within the tolerance range, 0 and 1 being the limits
+    pub fn get_relative(&self) -> f32
+    {
+        if self.max <= self.min
+        {
+            return 0.5
+        }
+        ((self.current - self.min) / (self.max - self.min)).min(1.0).max(0.0)
     }
 
     pub fn update_state(&mut self, world: &ThermoWorld)
@@ -218,13 +284,46 @@ impl ThermoWorld
         }
     }
 
+    pub fn move_to(&mut self, uuid: PUUID, position: (f32, f32))
+    {
+        match self.thermo_objects.get_mut(&uuid)
+        {
+            Some(ref mut therm_obj) =>
+            {
+                therm_obj.position = position;
+            }
+            None =>
+            {
+                panic!("ThermoWorld - FATAL - UUID not found! - {:?}", uuid);
+            }
+        }
+    }
+
     pub fn apply(&mut self, uuid: PUUID, action: Action)
     {
+        let dims = self.dimensions;
+        let x_len = self.thermo_grid.len();
+        let y_len = self.thermo_grid[0].len();
         match self.thermo_objects.get_mut(&uuid)
         {
             Some(ref mut therm_obj) =>
             {
-                therm_obj.current_temperature = 0.5;
+                match action
+                {
+                    Action::ThermoAction(delta) =>
+                    {
+                        therm_obj.current_temperature = (therm_obj.current_temperature + delta).min(1.0).max(0.0);
+
+                        // Some of the heat (or the lack of it) leaks into the surroundings
+                        let gcoords = ThermoWorld::coord_to_grid_position(therm_obj.position, dims, x_len, y_len);
+                        let grid_v = self.thermo_grid[gcoords.0][gcoords.1] + delta * THERMO_GRID_LEAK;
+                        self.thermo_grid[gcoords.0][gcoords.1] = grid_v.min(1.0).max(0.0);
+                    },
+                    _ =>
+                    {
+                        // Nothing to do
+                    }
+                }
             }
             None =>
             {
@@ -233,6 +332,27 @@ impl ThermoWorld
         }
     }
 
+    // Difference between the temperature of the neighbouring squares (towards +x, -x, +y and -y)
+    // and the one at position, in [0..1] with 0.5 meaning no change. The edges of the world
+    // count as the same temperature
+    pub fn get_temperature_gradient(&self, position: (f32, f32)) -> Vec<f32>
+    {
+        let x_len = self.thermo_grid.len();
+        let y_len = self.thermo_grid[0].len();
+        let (x, y) = ThermoWorld::coord_to_grid_position(position, self.dimensions, x_len, y_len);
+        let current = self.thermo_grid[x][y];
+
+        let neighbours = [ (min(x + 1, x_len - 1), y),
+                           (x.saturating_sub(1), y),
+                           (x, min(y + 1, y_len - 1)),
+                           (x, y.saturating_sub(1)) ];
+
+        neighbours.iter().map(|&(n_x, n_y)|
+                              {
+                                  (0.5 + (self.thermo_grid[n_x][n_y] - current) / 2.0).min(1.0).max(0.0)
+                              }).collect()
+    }
+
     fn coord_to_grid_position(position: (f32, f32), dims: (f32, f32), x_len: usize, y_len: usize) -> (usize, usize)
     {
         if (position.0 < 0.0 || position.1 < 0.0)
@@ -243,7 +363,7 @@ impl ThermoWorld
         let mut x =
             if position.0 >= 0.0
             {
-                (position.0 / dims.0).floor() as usize  * x_len
+                ((position.0 / dims.0) * x_len as f32).floor() as usize
             }
             else
             {
@@ -258,16 +378,16 @@ impl ThermoWorld
         let mut y =
             if position.1 >= 0.0
             {
-                (position.1 / dims.1).floor() as usize  * y_len
+                ((position.1 / dims.1) * y_len as f32).floor() as usize
             }
             else
             {
                 0
             };
 
-        if (y == y_len)
+        if (y >= y_len)
         {
-            y -= 1;
+            y = y_len - 1;
         }
 
         (x, y)
@@ -303,6 +423,8 @@ mod test
     extern crate env_logger;
 
     use super::*;
+    use ::actuators::*;
+    use ::sensors::*;
 
     #[test]
     fn test_thermal_update()
@@ -317,4 +439,86 @@ mod test
         th_world.add_object(2, (9.0, 9.0), 0.20, 0.5);
         debug!("\n\n{}", th_world);
     }
+
+    #[test]
+    fn test_coord_to_grid_position()
+    {
+        // Positions are scaled to the grid before flooring, flooring first sent every position
+        // inside the world to the first cell
+        let dims = (100.0, 100.0);
+        assert_eq!(ThermoWorld::coord_to_grid_position((0.0, 0.0), dims, 10, 10), (0, 0));
+        assert_eq!(ThermoWorld::coord_to_grid_position((9.9, 0.0), dims, 10, 10), (0, 0));
+        assert_eq!(ThermoWorld::coord_to_grid_position((10.0, 0.0), dims, 10, 10), (1, 0));
+        assert_eq!(ThermoWorld::coord_to_grid_position((55.0, 55.0), dims, 10, 10), (5, 5));
+        assert_eq!(ThermoWorld::coord_to_grid_position((25.0, 75.0), dims, 4, 8), (1, 6));
+
+        // Out of the world clamps to the border cells
+        assert_eq!(ThermoWorld::coord_to_grid_position((100.0, 100.0), dims, 10, 10), (9, 9));
+        assert_eq!(ThermoWorld::coord_to_grid_position((-3.0, 150.0), dims, 10, 10), (0, 9));
+    }
+
+    #[test]
+    fn test_thermal_gradient()
+    {
+        let mut th_world = ThermoWorld::new_with_dimensions((100.0, 100.0), 0.5);
+        th_world.thermo_grid[6][5] = 0.9;
+        th_world.thermo_grid[5][4] = 0.1;
+
+        let gradient = th_world.get_temperature_gradient((55.0, 55.0));
+        assert_eq!(gradient.len(), SensorTag::TemperatureGradient.get_cardinality());
+        assert!(gradient[0] > 0.5);
+        assert_eq!(gradient[1], 0.5);
+        assert_eq!(gradient[2], 0.5);
+        assert!(gradient[3] < 0.5);
+    }
+
+    #[test]
+    fn test_thermal_actuators()
+    {
+        let mut th_world = ThermoWorld::new_with_dimensions((100.0, 100.0), 0.5);
+        let mut thermo = Thermo::new(1, 0.0, 1.0);
+        th_world.add(&mut thermo, (55.0, 55.0));
+
+        let heat = Actuator::new(ActuatorTag::Heat, 0, (0, 0));
+        let cool = Actuator::new(ActuatorTag::Cool, 1, (0, 0));
+
+        // Negative stimulus leaves the actuators idle
+        let delta = thermo.act_on((55.0, 55.0), &vec![heat.get_action(-1.0), cool.get_action(-1.0)], &mut th_world);
+        thermo.update_state(&th_world);
+        assert_eq!(delta, 0.0);
+        assert_eq!(thermo.get_current(), 0.5);
+
+        let delta = thermo.act_on((55.0, 55.0), &vec![heat.get_action(1.0), cool.get_action(-1.0)], &mut th_world);
+        thermo.update_state(&th_world);
+        assert_eq!(delta, THERMO_ACTUATOR_DELTA);
+        assert!(thermo.get_current() > 0.5);
+        assert!(th_world.thermo_grid[5][5] > 0.5);
+        assert_eq!(th_world.thermo_grid[4][5], 0.5);
+
+        thermo.act_on((55.0, 55.0), &vec![cool.get_action(1.0), cool.get_action(1.0)], &mut th_world);
+        thermo.update_state(&th_world);
+        assert!(thermo.get_current() < 0.5);
+
+        // Heating and cooling at once does nothing, but both actuators fired
+        let current = thermo.get_current();
+        let delta = thermo.act_on((55.0, 55.0), &vec![heat.get_action(1.0), cool.get_action(1.0)], &mut th_world);
+        thermo.update_state(&th_world);
+        assert_eq!(delta, 2.0 * THERMO_ACTUATOR_DELTA);
+        assert_eq!(thermo.get_current(), current);
+    }
+
+    #[test]
+    fn test_thermal_relative()
+    {
+        let mut thermo = Thermo::new(1, 0.2, 0.6);
+        assert_eq!(thermo.get_relative(), 0.5);
+
+        thermo.current = 0.3;
+        assert!((thermo.get_relative() - 0.25).abs() < 0.0001);
+
+        thermo.current = 0.9;
+        assert_eq!(thermo.get_relative(), 1.0);
+        thermo.current = 0.0;
+        assert_eq!(thermo.get_relative(), 0.0);
+    }
 }
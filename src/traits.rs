@@ -161,6 +161,9 @@ pub enum TraitTag
     // Ph
     PhBasicResist,
     PhAcidResist,
+
+    // Vision
+    VisionAcuity,
 }
 impl Serializable for TraitTag
 {
@@ -184,6 +187,7 @@ impl Deserializable for TraitTag
                     "coldresist"        => { Some(TraitTag::ThermalColdResist)},
                     "phbasictresist"    => { Some(TraitTag::PhBasicResist)    },
                     "phacidresist"      => { Some(TraitTag::PhAcidResist)     },
+                    "visionacuity"      => { Some(TraitTag::VisionAcuity)     },
                     _ =>
                     {
                         None
@@ -212,6 +216,111 @@ pub enum PolyminiTrait
     PolyminiSensor(SensorTag),
     PolyminiSimpleTrait(TraitTag)
 }
+impl PolyminiTrait
+{
+    // Character used when drawing the trait, Actuators are upper case,
+    // Sensors lower case and simple traits are symbols
+    pub fn get_glyph(&self) -> char
+    {
+        match *self
+        {
+            PolyminiTrait::PolyminiActuator(tag) =>
+            {
+                match tag
+                {
+                    ActuatorTag::MoveHorizontal => { 'H' },
+                    ActuatorTag::MoveVertical   => { 'V' },
+                    ActuatorTag::Rotate         => { 'R' },
+                    ActuatorTag::Heat           => { 'W' },
+                    ActuatorTag::Cool           => { 'C' },
+                    ActuatorTag::EmitScent      => { 'E' },
+                }
+            },
+            PolyminiTrait::PolyminiSensor(tag) =>
+            {
+                match tag
+                {
+                    SensorTag::PositionX           => { 'x' },
+                    SensorTag::PositionY           => { 'y' },
+                    SensorTag::Orientation         => { 'o' },
+                    SensorTag::LastMoveSucceded    => { 'l' },
+                    SensorTag::TimeGlobal          => { 't' },
+                    SensorTag::TimeSubStep         => { 's' },
+                    SensorTag::Hp                  => { 'h' },
+                    SensorTag::Energy              => { 'e' },
+                    SensorTag::Temperature         => { 'k' },
+                    SensorTag::Ph                  => { 'p' },
+                    SensorTag::GSensor             => { 'g' },
+                    SensorTag::TemperatureGradient => { 'd' },
+                    SensorTag::Scent               => { 'n' },
+                    SensorTag::Vision              => { 'v' },
+                }
+            },
+            PolyminiTrait::PolyminiSimpleTrait(tag) =>
+            {
+                match tag
+                {
+                    TraitTag::Empty             => { '#' },
+                    TraitTag::SpeedTrait        => { '>' },
+                    TraitTag::ThermalHotResist  => { '+' },
+                    TraitTag::ThermalColdResist => { '-' },
+                    TraitTag::PhBasicResist     => { '^' },
+                    TraitTag::PhAcidResist      => { '~' },
+                    TraitTag::VisionAcuity      => { '*' },
+                }
+            },
+        }
+    }
+
+    // Fill colour used when drawing the trait, Actuators are warm colours,
+    // Sensors cold ones and simple traits greys and purples
+    pub fn get_colour(&self) -> &'static str
+    {
+        match *self
+        {
+            PolyminiTrait::PolyminiActuator(tag) =>
+            {
+                match tag
+                {
+                    ActuatorTag::MoveHorizontal => { "#E53935" },
+                    ActuatorTag::MoveVertical   => { "#F4511E" },
+                    ActuatorTag::Rotate         => { "#FB8C00" },
+                    ActuatorTag::Heat           => { "#FDD835" },
+                    ActuatorTag::Cool           => { "#FFB300" },
+                    ActuatorTag::EmitScent      => { "#D81B60" },
+                }
+            },
+            PolyminiTrait::PolyminiSensor(tag) =>
+            {
+                match tag
+                {
+                    SensorTag::PositionX | SensorTag::PositionY |
+                    SensorTag::Orientation | SensorTag::LastMoveSucceded => { "#90CAF9" },
+                    SensorTag::TimeGlobal | SensorTag::TimeSubStep       => { "#B2DFDB" },
+                    SensorTag::Hp | SensorTag::Energy |
+                    SensorTag::Temperature | SensorTag::Ph               => { "#A5D6A7" },
+                    SensorTag::GSensor             => { "#43A047" },
+                    SensorTag::TemperatureGradient => { "#00ACC1" },
+                    SensorTag::Scent               => { "#1E88E5" },
+                    SensorTag::Vision              => { "#3949AB" },
+                }
+            },
+            PolyminiTrait::PolyminiSimpleTrait(tag) =>
+            {
+                match tag
+                {
+                    TraitTag::Empty             => { "#BDBDBD" },
+                    TraitTag::SpeedTrait        => { "#757575" },
+                    TraitTag::ThermalHotResist  => { "#8E24AA" },
+                    TraitTag::ThermalColdResist => { "#5E35B1" },
+                    TraitTag::PhBasicResist     => { "#AB47BC" },
+                    TraitTag::PhAcidResist      => { "#7E57C2" },
+                    TraitTag::VisionAcuity      => { "#CE93D8" },
+                }
+            },
+        }
+    }
+}
 impl Deserializable for PolyminiTrait
 {
     fn new_from_json(json: &Json, ctx:&mut SerializationCtx) -> Option<PolyminiTrait>
@@ -8,6 +8,7 @@ mod test
 {
     extern crate env_logger;
     use polyminis_core::actuators::*;
+    use polyminis_core::control::*;
     use polyminis_core::environment::*;
     use polyminis_core::evaluation::*;
     use polyminis_core::genetics::*;
@@ -70,6 +71,9 @@ mod test
 
         let cfg = PGAConfig { population_size: 50,
                               percentage_elitism: 0.2, percentage_mutation: 0.1, fitness_evaluators: evaluators, accumulates_over: false,
+                              normalisation: FitnessNormalisation::NoNormalisation,
+                              aggregation: EvaluationAggregation::Mean, environment_importance: 1.0,
+                              control: ControlConfig::defaults(),
                               genome_size: 8 };
 
         trace!("Creating Species");
@@ -214,6 +218,9 @@ mod test
 
         let cfg = PGAConfig { population_size: 60,
                               percentage_elitism: 0.2, percentage_mutation: 0.35, fitness_evaluators: evaluators, accumulates_over: false,
+                              normalisation: FitnessNormalisation::NoNormalisation,
+                              aggregation: EvaluationAggregation::Mean, environment_importance: 1.0,
+                              control: ControlConfig::defaults(),
                               genome_size: 4 };
 
         let mut empty = Environment::new_with_dimensions(3, default_sensors.clone(), (5000.0, 5000.0));
@@ -434,6 +441,9 @@ mod test
         let steps_per_epoch = 50;
         let cfg = PGAConfig { population_size: 60,
                               percentage_elitism: 0.2, percentage_mutation: 0.35, fitness_evaluators: evaluators, accumulates_over: false,
+                              normalisation: FitnessNormalisation::NoNormalisation,
+                              aggregation: EvaluationAggregation::Mean, environment_importance: 1.0,
+                              control: ControlConfig::defaults(),
                               genome_size: 4 };
 
 
@@ -548,6 +558,9 @@ mod test
 
         let cfg = PGAConfig { population_size: 5,
                               percentage_elitism: 0.2, percentage_mutation: 0.1, fitness_evaluators: evaluators, accumulates_over: false,
+                              normalisation: FitnessNormalisation::NoNormalisation,
+                              aggregation: EvaluationAggregation::Mean, environment_importance: 1.0,
+                              control: ControlConfig::defaults(),
                               genome_size: 8 };
 
         trace!("Creating Species");
@@ -579,5 +592,79 @@ mod test
 
         trace!("{}", Json::Array(mtt_json).to_string());
     }
+
+    fn millis(d: Duration) -> f64
+    {
+        d.as_secs() as f64 * 1000.0 + d.subsec_nanos() as f64 / 1000000.0
+    }
+
+    // Benchmark - Per individual think vs ControlBatch, run with --ignored to see the timings
+    #[ignore]
+    #[test]
+    fn test_batched_inference_speed()
+    {
+        let a_list = vec![ Actuator::new(ActuatorTag::MoveVertical, 0, (0, 0)), Actuator::new(ActuatorTag::MoveHorizontal, 1, (0, 0))];
+        let s_list = vec![ Sensor::new(SensorTag::PositionX, 0),
+                           Sensor::new(SensorTag::PositionY, 1),
+                           Sensor::new(SensorTag::Orientation, 2),
+                           Sensor::new(SensorTag::LastMoveSucceded, 3),
+                           Sensor::new(SensorTag::TimeGlobal, 4),
+                           Sensor::new(SensorTag::TimeSubStep, 5)];
+        let mut config = ControlConfig::defaults();
+        config.min_hidden_size = 6;
+        config.max_hidden_size = 6;
+        let mut payload = SensoryPayload::new();
+        payload.insert(SensorTag::PositionX, vec![0.25]);
+        payload.insert(SensorTag::PositionY, vec![0.75]);
+
+        let steps = 200;
+        for population in vec![100, 250, 500, 1000]
+        {
+            let mut ctx = PolyminiRandomCtx::new_unseeded("Batched Inference Benchmark".to_string());
+            let mut controls: Vec<Control> = (0..population).map(|_| Control::new_from_config(s_list.clone(), a_list.clone(),
+                                                                                              &config, &mut ctx)).collect();
+            for c in controls.iter_mut()
+            {
+                c.sense(&payload);
+            }
+
+            let start = Instant::now();
+            for _ in 0..steps
+            {
+                for c in controls.iter_mut()
+                {
+                    c.think();
+                }
+            }
+            let single = millis(start.elapsed());
+            let expected: Vec<Vec<f32>> = controls.iter().map(|c| c.get_outputs().clone()).collect();
+
+            let topology = controls[0].get_topology().unwrap();
+            let batch = { let refs = controls.iter().collect(); ControlBatch::new(topology, &refs) };
+            let start = Instant::now();
+            for _ in 0..steps
+            {
+                let output = { let refs = controls.iter().collect(); batch.think(&refs) };
+                for (m, c) in controls.iter_mut().enumerate()
+                {
+                    output.apply(m, c);
+                }
+            }
+            let batched = millis(start.elapsed());
+
+            // Same inputs every step, so the batch must land on the same outputs
+            for (c, outputs) in controls.iter().zip(expected.iter())
+            {
+                assert_eq!(c.get_outputs().len(), outputs.len());
+                for (a, b) in c.get_outputs().iter().zip(outputs.iter())
+                {
+                    assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+                }
+            }
+
+            println!("Population {:5} - Individual {:9.2}ms  Batched {:9.2}ms  Speed-up {:.2}x",
+                     population, single, batched, single / batched.max(0.001));
+        }
+    }
 }
 